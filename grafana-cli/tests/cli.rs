@@ -0,0 +1,226 @@
+//! Run the CLI against the in-memory Grafana server
+use std::path::PathBuf;
+use std::process::{Command,Output};
+
+use grafana_mock::MockServer;
+use serde_json::json;
+
+fn seeded() -> MockServer {
+    let server = MockServer::start();
+    let ops = server.add_folder("ops", "Operations");
+    server.add_dashboard(json!({
+        "uid" : "node",
+        "title" : "Node Exporter",
+        "schemaVersion" : 39,
+        "panels" : [{ "id" : 1, "title" : "CPU", "type" : "timeseries" }],
+    }), Some(&ops));
+    server.add_dashboard(json!({ "uid" : "home", "title" : "Home" }), None);
+    server
+}
+
+/// Config file path unique to a test
+fn config_path(test : &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("grafana-cli-{}-{test}.toml",std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// Run the CLI with `args` and the config file at `config`, without `GRAFANA_HOST`
+fn run_with_config(config : &PathBuf, args : &[&str]) -> Output {
+    command_with_config(config)
+        .args(args)
+        .output()
+        .expect("run grafana-cli")
+}
+
+/// Command using a config file, without settings from the environment
+fn command_with_config(config : &PathBuf) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_grafana-cli"));
+    command
+        .env("GRAFANA_CONFIG", config)
+        .env_remove("GRAFANA_HOST")
+        .env_remove("GRAFANA_TOKEN")
+        .env_remove("GRAFANA_TOKEN_FILE")
+        .env_remove("GRAFANA_TOKEN_COMMAND")
+        .env_remove("GRAFANA_PROFILE");
+    command
+}
+
+/// Stdout of a successful run
+fn stdout(output : Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+/// Run the CLI with `args` against `server`, returning stdout
+fn run(server : &MockServer, args : &[&str]) -> String {
+    let url = server.url();
+    let mut host = vec!["--host", url.as_str()];
+    host.extend_from_slice(args);
+    stdout(run_with_config(&config_path("none"), &host))
+}
+
+#[test]
+fn test_dashboard_list() {
+    let server = seeded();
+    let output = run(&server, &["dashboard", "list", "--verbose"]);
+    assert!(output.contains("2 results."), "{output}");
+    assert!(output.contains("Home\t[uid=home]"), "{output}");
+    assert!(output.contains("Node Exporter\t[uid=node]"), "{output}");
+}
+
+#[test]
+fn test_dashboard_get() {
+    let server = seeded();
+    let output = run(&server, &["dashboard", "get", "--uid", "node", "--panels"]);
+    assert!(output.contains("Title\t: Node Exporter"), "{output}");
+    assert!(output.contains("Folder\t: Operations"), "{output}");
+    assert!(output.contains("Panels\t: 1"), "{output}");
+}
+
+#[test]
+fn test_folder_list() {
+    let server = seeded();
+    let output = run(&server, &["folder", "list"]);
+    assert!(output.contains("1 Results.\nOperations"), "{output}");
+}
+
+#[test]
+fn test_data_source_list() {
+    let server = seeded();
+    server.add_data_source(json!({ "name" : "Prometheus", "type" : "prometheus" }));
+    let output = run(&server, &["data-source", "list"]);
+    assert!(output.contains("1 Results.\nPrometheus"), "{output}");
+}
+
+#[test]
+fn test_annotations_list() {
+    let server = seeded();
+    server.add_annotation(json!({ "dashboardUID" : "node", "panelId" : 1, "text" : "deploy" }));
+    let output = run(&server, &["annotations", "list"]);
+    assert!(output.contains("Annotations: 1"), "{output}");
+    assert!(output.contains("deploy"), "{output}");
+}
+
+#[test]
+fn test_alerting_lists() {
+    let server = seeded();
+    server.add_alert_rule(json!({ "title" : "High CPU", "folderUID" : "ops", "ruleGroup" : "node" }));
+    let output = run(&server, &["alerting", "rules", "list"]);
+    assert!(output.contains("Alert Rules: 1"), "{output}");
+    assert!(output.contains("High CPU"), "{output}");
+    let output = run(&server, &["alerting", "contact-points", "list"]);
+    assert!(output.contains("Contact Points: 0"), "{output}");
+}
+
+#[test]
+fn test_org_flag() {
+    let server = seeded();
+    run(&server, &["--org", "2", "dashboard", "list"]);
+    assert_eq!(server.requests()[0].header("x-grafana-org-id"), Some("2"));
+}
+
+#[test]
+fn test_health() {
+    let server = MockServer::start().with_version("11.1.0").with_feature_toggle("nestedFolders", true);
+    let output = run(&server, &["health", "--verbose"]);
+    assert!(output.contains("Database\t: ok"), "{output}");
+    assert!(output.contains("Version\t: 11.1.0"), "{output}");
+    assert!(output.contains("Toggles\t: nestedFolders"), "{output}");
+}
+
+#[test]
+fn test_config_profiles() {
+    let dev = seeded();
+    let prod = MockServer::start().require_token("glsa_prod");
+    prod.add_folder("billing", "Billing");
+    let config = config_path("profiles");
+    stdout(run_with_config(&config, &["--profile", "dev", "config", "set", "host", &dev.url()]));
+    stdout(run_with_config(&config, &["--profile", "prod", "config", "set", "host", &prod.url()]));
+    stdout(run_with_config(&config, &["--profile", "prod", "config", "set", "token", "glsa_prod"]));
+    stdout(run_with_config(&config, &["--profile", "prod", "config", "set", "output", "json"]));
+
+    let output = stdout(run_with_config(&config, &["config", "list"]));
+    assert!(output.contains(&format!("* dev\t{}",dev.url())), "{output}");
+    let output = stdout(run_with_config(&config, &["config", "show", "prod"]));
+    assert!(output.contains("Credential\t: token\n"), "{output}");
+    assert!(!output.contains("glsa_prod"), "{output}");
+
+    let output = stdout(run_with_config(&config, &["folder", "list"]));
+    assert!(output.contains("1 Results.\nOperations"), "{output}");
+    let output = stdout(run_with_config(&config, &["--profile", "prod", "folder", "list"]));
+    assert_eq!(output.trim(), r#"[{"uid":"billing","title":"Billing"}]"#);
+    stdout(run_with_config(&config, &["config", "use", "prod"]));
+    let output = stdout(run_with_config(&config, &["--output", "text", "folder", "list"]));
+    assert!(output.contains("1 Results.\nBilling"), "{output}");
+    std::fs::remove_file(config).unwrap();
+}
+
+#[test]
+fn test_config_errors() {
+    let config = config_path("errors");
+    let output = run_with_config(&config, &["--profile", "missing", "folder", "list"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no profile named missing"));
+    let output = run_with_config(&config, &["--profile", "dev", "config", "set", "org", "two"]);
+    assert!(!output.status.success());
+    assert!(!config.exists());
+}
+
+#[test]
+fn test_token_file() {
+    let server = MockServer::start().require_token("glsa_file");
+    server.add_folder("ops", "Operations");
+    let config = config_path("token-file");
+    let token = std::env::temp_dir().join(format!("grafana-cli-{}-token",std::process::id()));
+    std::fs::write(&token, "glsa_file\n").unwrap();
+    let url = server.url();
+    stdout(run_with_config(&config, &["--profile", "k8s", "config", "set", "host", &url]));
+    stdout(run_with_config(&config, &["config", "set", "token-file", token.to_str().unwrap()]));
+    let output = stdout(run_with_config(&config, &["folder", "list"]));
+    assert!(output.contains("1 Results.\nOperations"), "{output}");
+    let text = std::fs::read_to_string(&config).unwrap();
+    assert!(text.contains("type = \"token-file\""), "{text}");
+    std::fs::remove_file(config).unwrap();
+    std::fs::remove_file(token).unwrap();
+}
+
+#[test]
+fn test_profile_token_wins_over_env() {
+    let profile_server = MockServer::start().require_token("glsa_profile");
+    let env_server = MockServer::start().require_token("glsa_env");
+    let config = config_path("token-precedence");
+    let (profile_url, env_url) = (profile_server.url(), env_server.url());
+    stdout(run_with_config(&config, &["--profile", "prod", "config", "set", "host", &profile_url]));
+    stdout(run_with_config(&config, &["--profile", "prod", "config", "set", "token", "glsa_profile"]));
+    stdout(run_with_config(&config, &["--profile", "dev", "config", "set", "host", &env_url]));
+    stdout(run_with_config(&config, &["config", "use", "prod"]));
+    // A selected profile wins over the environment, the current one does not
+    for args in [&["--profile", "prod"][..], &["--profile", "dev"], &[]] {
+        let output = command_with_config(&config)
+            .env("GRAFANA_HOST", &env_url)
+            .env("GRAFANA_TOKEN", "glsa_env")
+            .args(args)
+            .args(["folder", "list"])
+            .output()
+            .expect("run grafana-cli");
+        let output = stdout(output);
+        assert!(output.contains("0 Results."), "{output}");
+    }
+    let sent = |server : &MockServer| server.requests().iter().map(|r| r.headers.get("authorization").cloned()).collect::<Vec<_>>();
+    assert_eq!(sent(&profile_server), vec![Some(String::from("Bearer glsa_profile"))]);
+    assert_eq!(sent(&env_server), vec![Some(String::from("Bearer glsa_env")), Some(String::from("Bearer glsa_env"))]);
+    std::fs::remove_file(config).unwrap();
+}
+
+#[test]
+fn test_dashboard_create() {
+    let server = seeded();
+    let output = run(&server, &["dashboard", "create", "--name", "Disk", "--folder", "ops", "--message", "from the CLI"]);
+    assert!(output.contains("Version\t: 1"), "{output}");
+    let body = server.requests().last().unwrap().json().unwrap();
+    assert_eq!((body["folderUid"].as_str(), body["message"].as_str()), (Some("ops"), Some("from the CLI")));
+    let output = run_with_config(&config_path("none"), &["--host", &server.url(), "dashboard", "create", "--name", "Disk", "--folder", "ops"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("name-exists"));
+}
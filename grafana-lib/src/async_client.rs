@@ -0,0 +1,104 @@
+//! Async Grafana Client
+//!
+//! Counterpart of [`crate::client::Client`] for use inside an async runtime such as tokio.
+//! Module accessors return the same API structures as the blocking client, so models and
+//! request paths are shared; use the `_async` flavour of each call, e.g.
+//! [`Search::dashboard_async`].
+
+use crate::common::config::Config;
+use crate::common::api::Api;
+use crate::common::auth::Auth;
+
+use crate::community::annotations::Annotations;
+use crate::community::alerting_provisioning::AlertingProvisioning;
+use crate::community::dashboard::Dashboard;
+use crate::community::data_source::DataSource;
+use crate::community::folder::Folder;
+use crate::community::organization::Organization;
+use crate::community::other::Other;
+use crate::community::search::Search;
+
+/// Async Client Structure
+#[derive(Debug,Clone)]
+pub struct AsyncClient {
+    /// API Instance
+    pub api : Api,
+    /// Common configuration information
+    pub config : Config,
+}
+
+impl AsyncClient {
+    /// Create a new async client instance, authenticating with `GRAFANA_TOKEN` when it is set
+    /// and anonymously otherwise.
+    /// # Example
+    /// ```
+    /// # use grafana_lib::async_client::AsyncClient;
+    /// # async fn run() {
+    /// let client = AsyncClient::new(String::from("http://localhost:3000"));
+    /// let dashboards = client.search().dashboard_async(None).await;
+    /// # }
+    /// ```
+    pub fn new(url : String) -> AsyncClient {
+        let config = Config::from_env(url);
+        let api = Api::from_config(config.clone(),config.auth());
+        AsyncClient { api, config }
+    }
+
+    /// Replace the credentials used by this client
+    pub fn with_auth(mut self, auth : Auth) -> AsyncClient {
+        self.api = self.api.with_auth(auth);
+        self
+    }
+
+    /// Scope every request made through this client to an organisation
+    pub fn with_org(mut self, org_id : u64) -> AsyncClient {
+        self.api = self.api.with_org(org_id);
+        self.config = self.config.with_org(org_id);
+        self
+    }
+
+    /// Derive a client scoped to another organisation, sharing connections and limits
+    pub fn for_org(&self, org_id : u64) -> AsyncClient {
+        self.clone().with_org(org_id)
+    }
+
+    /// Access instance of Annotations API
+    pub fn annotations(&self) -> Annotations {
+        Annotations::new(self.api.clone())
+    }
+
+    /// Access instance of Alert Provisioning API
+    pub fn alerting_provisioning(&self) -> AlertingProvisioning {
+        AlertingProvisioning::new(self.api.clone())
+    }
+
+    /// Access instance of Dashboard API
+    pub fn dashboard(&self) -> Dashboard {
+        Dashboard::new(self.api.clone())
+    }
+
+    /// Access instance of DataSource API
+    pub fn data_source(&self) -> DataSource {
+        DataSource::new(self.api.clone())
+    }
+
+    /// Access instance of Folder API
+    pub fn folder(&self) -> Folder {
+        Folder::new(self.api.clone())
+    }
+
+    /// Access instance of Organization API
+    pub fn organization(&self) -> Organization {
+        Organization::new(self.api.clone())
+    }
+
+    /// Access instance of the Other API, for health, frontend settings and capabilities
+    pub fn other(&self) -> Other {
+        Other::new(self.api.clone())
+    }
+
+    /// Access instance of Search API
+    pub fn search(&self) -> Search {
+        Search::new(self.api.clone())
+    }
+}
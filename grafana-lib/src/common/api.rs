@@ -1,59 +1,419 @@
 //! API Module
-//! 
+//!
+//! Low level access to the Grafana HTTP API. Typed helpers serialize request bodies,
+//! check the response status and deserialize the response into the requested model.
+//!
+//! An [`Api`] is cheap to clone; clones share the same transport, connection pools and limits.
+
+use std::fmt;
+use std::sync::{Arc,OnceLock};
+use std::thread;
+use std::time::Duration;
 
 use serde::{Serialize,Deserialize};
+use serde::de::DeserializeOwned;
+use reqwest::Method;
+
+use log::{info,debug,error,warn};
 
-use log::{info,debug,error};
+use crate::common::auth::Auth;
+use crate::common::capabilities::Capabilities;
+use crate::common::config::Config;
+use crate::common::error::GrafanaError;
+use crate::common::limit::Limiter;
+use crate::common::metrics::Metrics;
+use crate::common::retry::{self,Outcome,RetryPolicy};
+use crate::common::trace::CallSpan;
+use crate::common::transport::{ReqwestTransport,Request,Response,Transport};
+use crate::community::other::{FrontendSettings,FRONTEND_SETTINGS_PATH};
+
+/// Header used by Grafana to select the organisation a request applies to
+const ORG_HEADER : &str = "X-Grafana-Org-Id";
 
 /// Low level API functions
-#[derive(Debug,PartialEq,Default,Serialize,Deserialize)]
+#[derive(Debug,Clone,Default,Serialize,Deserialize)]
 pub struct Api {
-    token   : String,
+    auth    : Auth,
     /// Hostname for Grafana
     pub host    : String,
+    org_id  : Option<u64>,
+    #[serde(skip)]
+    retry   : Option<RetryPolicy>,
+    #[serde(skip)]
+    limiter : Arc<Limiter>,
+    #[serde(skip)]
+    transport : Shared,
+    #[serde(skip)]
+    metrics : Option<Metrics>,
+    #[serde(skip)]
+    capabilities : Arc<OnceLock<Capabilities>>,
+}
+
+/// Transport shared between clones of an [`Api`], reqwest unless replaced
+#[derive(Clone)]
+struct Shared(Arc<dyn Transport>);
+
+impl Default for Shared {
+    fn default() -> Self {
+        Shared(Arc::new(ReqwestTransport::default()))
+    }
+}
+
+impl fmt::Debug for Shared {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl PartialEq for Api {
+    /// Two Api instances are equal when they target the same host with the same credentials
+    fn eq(&self, other: &Self) -> bool {
+        self.auth == other.auth && self.host == other.host && self.org_id == other.org_id
+    }
 }
 
 impl Api {
     /// Create new Api instance
-    pub fn new(host : String,token : String) -> Api {
+    /// # Example
+    /// ```
+    /// # use grafana_lib::common::api::Api;
+    /// # use grafana_lib::common::auth::Auth;
+    /// let api = Api::new(String::from("http://localhost:3000"),Auth::bearer(String::from("TOKEN")));
+    /// ```
+    pub fn new(host : String,auth : Auth) -> Api {
+        Api::from_config(Config::new(host), auth)
+    }
+
+    /// Create new Api instance using the host and transport settings from a configuration
+    pub fn from_config(config : Config, auth : Auth) -> Api {
         Api {
-            token,
-            host : format!("{}/api",host),
+            auth,
+            host : format!("{}/api",config.url()),
+            org_id : config.org_id(),
+            retry : config.retry().cloned(),
+            limiter : Arc::new(Limiter::new(&config)),
+            transport : Shared(Arc::new(ReqwestTransport::new(config))),
+            metrics : None,
+            capabilities : Arc::default(),
+        }
+    }
+
+    /// Send requests through another transport, e.g. a different HTTP stack or a mock
+    pub fn with_transport<T>(mut self, transport : T) -> Api
+    where T : Transport + 'static,
+    {
+        self.transport = Shared(Arc::new(transport));
+        self
+    }
+
+    /// Record request counts, latencies and retries in a metrics registry
+    pub fn with_metrics(mut self, metrics : Metrics) -> Api {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Replace the credentials used for requests
+    pub fn with_auth(mut self, auth : Auth) -> Api {
+        self.auth = auth;
+        self
+    }
+
+    /// Scope requests to an organisation by sending `X-Grafana-Org-Id`, instead of the
+    /// user's current organisation
+    pub fn with_org(mut self, org_id : u64) -> Api {
+        self.org_id = Some(org_id);
+        self
+    }
+
+    /// Organisation requests are scoped to, None for the user's current organisation
+    pub fn org_id(&self) -> Option<u64> {
+        self.org_id
+    }
+
+    /// Capabilities of the server, detected from the frontend settings on first use and
+    /// shared with clones. A failed detection is not cached.
+    pub fn capabilities(&self) -> Result<Capabilities,GrafanaError> {
+        if let Some(c) = self.capabilities.get() {
+            return Ok(c.clone());
         }
+        let settings : FrontendSettings = self.get_json(FRONTEND_SETTINGS_PATH)?;
+        Ok(self.capabilities.get_or_init(|| Capabilities::from_settings(&settings)).clone())
     }
-    /// Perform GET operation against Grafana using blocking
-    pub fn get(&self, path : String) -> Result<String,String> {
+
+    /// Capabilities of the server using async
+    pub async fn capabilities_async(&self) -> Result<Capabilities,GrafanaError> {
+        if let Some(c) = self.capabilities.get() {
+            return Ok(c.clone());
+        }
+        let settings : FrontendSettings = self.get_json_async(FRONTEND_SETTINGS_PATH).await?;
+        Ok(self.capabilities.get_or_init(|| Capabilities::from_settings(&settings)).clone())
+    }
+
+    /// Replace the retry policy, e.g. to allow retrying a non-idempotent write for one call.
+    /// Clones share the connection pools, so this is cheap.
+    /// # Example
+    /// ```
+    /// # use grafana_lib::common::api::Api;
+    /// # use grafana_lib::common::retry::RetryPolicy;
+    /// # use reqwest::Method;
+    /// # let api = Api::default();
+    /// let result = api
+    ///     .clone()
+    ///     .with_retry(RetryPolicy::default().with_method(Method::POST))
+    ///     .post_json::<_,serde_json::Value>("dashboards/db", &serde_json::json!({}));
+    /// ```
+    pub fn with_retry(mut self, policy : RetryPolicy) -> Api {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Build a request for the given method and path with the organisation applied.
+    /// Credentials are added when it is sent, so tokens from a [`crate::common::auth::TokenSource`]
+    /// are fresh on every attempt.
+    pub fn request(&self, method : Method, path : &str) -> Request {
         let url = format!("{}/{}",self.host, path);
-        match reqwest::blocking::get(url) {
-            Ok(r) => {
-                info!("GET responded with status: {}",r.status());
-                Ok(r.text().unwrap())
-            },
-            Err(e) => {
-                error!("GET Failed: {}",e.to_string());
-                Err(e.to_string())
-            }
+        debug!("{method} {url}");
+        let mut request = Request::new(method, url);
+        if let Some(org_id) = self.org_id {
+            request.headers.insert(ORG_HEADER, org_id.into());
         }
+        request
+    }
+
+    /// Send a request, applying limits and retries, and return the response whatever its status
+    /// # Example
+    /// ```
+    /// # use grafana_lib::common::api::Api;
+    /// # use reqwest::Method;
+    /// # let api = Api::default();
+    /// let request = api.request(Method::HEAD, "health");
+    /// let healthy = api.execute(request).map(|r| r.status == 200);
+    /// ```
+    pub fn execute(&self, request : Request) -> Result<Response,GrafanaError> {
+        let span = CallSpan::new(&request, self.org_id);
+        let mut attempt = 1;
+        let result = span.in_scope(|| loop {
+            let result = self.attempt(request.clone());
+            match self.retry_delay(&request, attempt, &result) {
+                Some(delay) => {
+                    warn!("{} {} attempt {} failed, retrying in {:?}",request.method,request.path(),attempt,delay);
+                    thread::sleep(delay);
+                    attempt += 1;
+                },
+                None => break result,
+            }
+        });
+        self.finish(span, &request, &result, attempt - 1);
+        result
     }
-    /// Perform GET operation against Grafana using async
-    pub async fn get_async(&self, path : String) -> Result<String,String> {
-        let url = format!("{}/{}",self.host,path);
-        debug!("URL: {url}");
-        let _body = reqwest::get(url).await.unwrap();
-        Ok(String::from("It was good"))
+
+    /// Send a request using async, applying limits and retries, and return the response
+    /// whatever its status
+    pub async fn execute_async(&self, request : Request) -> Result<Response,GrafanaError> {
+        let span = CallSpan::new(&request, self.org_id);
+        let mut attempt = 1;
+        let result = span.instrument(async {
+            loop {
+                let result = self.attempt_async(request.clone()).await;
+                match self.retry_delay(&request, attempt, &result) {
+                    Some(delay) => {
+                        warn!("{} {} attempt {} failed, retrying in {:?}",request.method,request.path(),attempt,delay);
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    },
+                    None => break result,
+                }
+            }
+        }).await;
+        self.finish(span, &request, &result, attempt - 1);
+        result
+    }
+
+    /// Perform GET operation against Grafana using blocking, returning the raw body
+    pub fn get(&self, path : String) -> Result<String,GrafanaError> {
+        self.send::<()>(Method::GET, &path, None)
     }
 
-    /// Send compatible struct through to Grafana using async
-    pub async fn post_async<T>(self, payload : T) -> Result<String,String> 
-    where T : Sized + Serialize,
+    /// Perform GET operation against Grafana using async, returning the raw body
+    pub async fn get_async(&self, path : String) -> Result<String,GrafanaError> {
+        self.send_async::<()>(Method::GET, &path, None).await
+    }
+
+    /// GET a path and deserialize the response
+    /// # Example
+    /// ```
+    /// # use grafana_lib::common::api::Api;
+    /// # use grafana_lib::community::folder::FolderModel;
+    /// # let api = Api::default();
+    /// let folders = api.get_json::<Vec<FolderModel>>("folders");
+    /// ```
+    pub fn get_json<T>(&self, path : &str) -> Result<T,GrafanaError>
+    where T : DeserializeOwned,
     {
-        let client = reqwest::Client::new();
-        match client.post(self.host)
-            .json(&payload)
-            .bearer_auth(self.token)
-            .send().await {
-                Ok(_) => Ok("Yay".to_string()),
-                Err(e) => Err(e.to_string()),
+        decode(self.send::<()>(Method::GET, path, None)?)
+    }
+
+    /// POST a serialized body to a path and deserialize the response
+    pub fn post_json<B,T>(&self, path : &str, body : &B) -> Result<T,GrafanaError>
+    where B : Serialize + ?Sized, T : DeserializeOwned,
+    {
+        decode(self.send(Method::POST, path, Some(body))?)
+    }
+
+    /// PUT a serialized body to a path and deserialize the response
+    pub fn put_json<B,T>(&self, path : &str, body : &B) -> Result<T,GrafanaError>
+    where B : Serialize + ?Sized, T : DeserializeOwned,
+    {
+        decode(self.send(Method::PUT, path, Some(body))?)
+    }
+
+    /// PATCH a serialized body to a path and deserialize the response
+    pub fn patch_json<B,T>(&self, path : &str, body : &B) -> Result<T,GrafanaError>
+    where B : Serialize + ?Sized, T : DeserializeOwned,
+    {
+        decode(self.send(Method::PATCH, path, Some(body))?)
+    }
+
+    /// DELETE a path and deserialize the response
+    pub fn delete<T>(&self, path : &str) -> Result<T,GrafanaError>
+    where T : DeserializeOwned,
+    {
+        decode(self.send::<()>(Method::DELETE, path, None)?)
+    }
+
+    /// GET a path and deserialize the response using async
+    pub async fn get_json_async<T>(&self, path : &str) -> Result<T,GrafanaError>
+    where T : DeserializeOwned,
+    {
+        decode(self.send_async::<()>(Method::GET, path, None).await?)
+    }
+
+    /// POST a serialized body to a path and deserialize the response using async
+    pub async fn post_json_async<B,T>(&self, path : &str, body : &B) -> Result<T,GrafanaError>
+    where B : Serialize + ?Sized, T : DeserializeOwned,
+    {
+        decode(self.send_async(Method::POST, path, Some(body)).await?)
+    }
+
+    /// PUT a serialized body to a path and deserialize the response using async
+    pub async fn put_json_async<B,T>(&self, path : &str, body : &B) -> Result<T,GrafanaError>
+    where B : Serialize + ?Sized, T : DeserializeOwned,
+    {
+        decode(self.send_async(Method::PUT, path, Some(body)).await?)
+    }
+
+    /// PATCH a serialized body to a path and deserialize the response using async
+    pub async fn patch_json_async<B,T>(&self, path : &str, body : &B) -> Result<T,GrafanaError>
+    where B : Serialize + ?Sized, T : DeserializeOwned,
+    {
+        decode(self.send_async(Method::PATCH, path, Some(body)).await?)
+    }
+
+    /// DELETE a path and deserialize the response using async
+    pub async fn delete_async<T>(&self, path : &str) -> Result<T,GrafanaError>
+    where T : DeserializeOwned,
+    {
+        decode(self.send_async::<()>(Method::DELETE, path, None).await?)
+    }
+
+    /// Send a blocking request and return the body of a successful response
+    fn send<B>(&self, method : Method, path : &str, body : Option<&B>) -> Result<String,GrafanaError>
+    where B : Serialize + ?Sized,
+    {
+        let mut request = self.request(method, path);
+        if let Some(b) = body {
+            request = request.with_json(b)?;
+        }
+        self.execute(request).and_then(check)
+    }
+
+    /// Send an async request and return the body of a successful response
+    async fn send_async<B>(&self, method : Method, path : &str, body : Option<&B>) -> Result<String,GrafanaError>
+    where B : Serialize + ?Sized,
+    {
+        let mut request = self.request(method, path);
+        if let Some(b) = body {
+            request = request.with_json(b)?;
+        }
+        self.execute_async(request).await.and_then(check)
+    }
+
+    /// Send a blocking request once
+    fn attempt(&self, request : Request) -> Result<Response,GrafanaError> {
+        let request = self.authorize(request)?;
+        let method = request.method.clone();
+        let _permit = self.limiter.acquire();
+        self.check_auth(log_result(&method, self.transport.0.send(request)))
+    }
+
+    /// Send an async request once
+    async fn attempt_async(&self, request : Request) -> Result<Response,GrafanaError> {
+        let request = self.authorize(request)?;
+        let method = request.method.clone();
+        let _permit = self.limiter.acquire_async().await;
+        self.check_auth(log_result(&method, self.transport.0.send_async(request).await))
+    }
+
+    /// Add the `Authorization` header unless the caller set one
+    fn authorize(&self, mut request : Request) -> Result<Request,GrafanaError> {
+        if !request.headers.contains_key(reqwest::header::AUTHORIZATION) {
+            if let Some(value) = self.auth.header()? {
+                request.headers.insert(reqwest::header::AUTHORIZATION, value);
             }
+        }
+        Ok(request)
+    }
+
+    /// Drop a cached token the server rejected, so the next request reads a fresh one
+    fn check_auth(&self, result : Result<Response,GrafanaError>) -> Result<Response,GrafanaError> {
+        if matches!(&result, Ok(r) if r.status == 401) {
+            self.auth.invalidate();
+        }
+        result
     }
-}
\ No newline at end of file
+
+    /// Close the span of a call and record it in the metrics
+    fn finish(&self, span : CallSpan, request : &Request, result : &Result<Response,GrafanaError>, retries : u32) {
+        let latency = span.finish(result, retries);
+        if let Some(metrics) = &self.metrics {
+            metrics.observe(request, result, latency, retries);
+        }
+    }
+
+    /// Delay before retrying the outcome of an attempt, None when it should be returned
+    fn retry_delay(&self, request : &Request, attempt : u32, result : &Result<Response,GrafanaError>) -> Option<Duration> {
+        let policy = self.retry.as_ref()?;
+        let outcome = match result {
+            Ok(r) => Outcome::Response { status : r.status, retry_after : retry::retry_after(&r.headers) },
+            Err(e) => Outcome::Failed(e),
+        };
+        policy.next_delay(&request.method, attempt, &outcome)
+    }
+}
+
+/// Log the outcome of a single attempt
+fn log_result(method : &Method, result : Result<Response,GrafanaError>) -> Result<Response,GrafanaError> {
+    match &result {
+        Ok(r) => info!("{} responded with status: {}",method,r.status),
+        Err(e) => error!("{} Failed: {}",method,e),
+    }
+    result
+}
+
+/// Map non-2xx responses onto an error
+fn check(response : Response) -> Result<String,GrafanaError> {
+    match (200..300).contains(&response.status) {
+        true => Ok(response.body),
+        false => Err(GrafanaError::from_response(response.status, &response.body)),
+    }
+}
+/// Deserialize a response body, treating an empty body as JSON `null`
+fn decode<T>(body : String) -> Result<T,GrafanaError>
+where T : DeserializeOwned,
+{
+    let payload = match body.trim().is_empty() {
+        true => "null",
+        false => body.as_str(),
+    };
+    serde_json::from_str(payload).map_err(|e| GrafanaError::decode(e, &body))
+}
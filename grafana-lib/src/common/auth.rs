@@ -0,0 +1,283 @@
+//! Authentication Module
+//!
+//! Credentials attached to every request made through [`crate::common::api::Api`].
+//!
+//! Tokens can be given directly, or read when needed from a file (e.g. a mounted
+//! Kubernetes secret) or from the output of a command (e.g. a vault helper) through a
+//! [`TokenSource`]. [`Auth::from_env`] looks for credentials in the environment in this
+//! order, the first one found wins:
+//!
+//! 1. `GRAFANA_TOKEN` - the token itself
+//! 2. `GRAFANA_TOKEN_FILE` - path of a file holding the token
+//! 3. `GRAFANA_TOKEN_COMMAND` - shell command printing the token, cached for
+//!    `GRAFANA_TOKEN_TTL` seconds (default 300)
+//!
+//! Credentials set explicitly on a client or in a CLI profile take precedence over the
+//! environment. The CLI ignores the current profile's credentials when `GRAFANA_HOST`
+//! picks the host, so they are never sent to another server.
+
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc,Mutex};
+use std::time::{Duration,Instant};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use log::debug;
+use reqwest::header::HeaderValue;
+use serde::{Serialize,Deserialize};
+
+use crate::common::config::Config;
+use crate::common::error::GrafanaError;
+use crate::common::secret::Secret;
+
+/// How long a token read from a file is used before the file is read again
+const FILE_TTL : Duration = Duration::from_secs(60);
+
+/// How long a token printed by a command is used before the command is run again
+const COMMAND_TTL : Duration = Duration::from_secs(300);
+
+/// Authentication strategy used when talking to Grafana
+#[derive(Debug,Clone,PartialEq,Default,Serialize,Deserialize)]
+pub enum Auth {
+    /// No credentials, relies on anonymous access being enabled
+    #[default]
+    Anonymous,
+    /// API key or service account token, sent as a bearer token
+    Bearer(Secret),
+    /// Username and password using HTTP basic authentication
+    Basic {
+        /// Username
+        user : String,
+        /// Password
+        pass : Secret,
+    },
+    /// Bearer token read from a file or command when needed
+    Source(TokenSource),
+}
+
+impl Auth {
+    /// Create bearer authentication from an API key or service account token
+    /// # Example
+    /// ```
+    /// # use grafana_lib::common::auth::Auth;
+    /// let auth = Auth::bearer(String::from("glsa_XXXX"));
+    /// ```
+    pub fn bearer(token : String) -> Auth {
+        Auth::Bearer(Secret::new(token))
+    }
+
+    /// Create basic authentication from a username and password
+    pub fn basic(user : String, pass : String) -> Auth {
+        Auth::Basic { user, pass : Secret::new(pass) }
+    }
+
+    /// Credentials from the environment, see the module documentation for the order
+    /// variables are checked in. None when no variable is set.
+    pub fn from_env() -> Option<Auth> {
+        if let Some(token) = Config::get_env("GRAFANA_TOKEN") {
+            return Some(Auth::bearer(token));
+        }
+        if let Some(path) = Config::get_env("GRAFANA_TOKEN_FILE") {
+            return Some(Auth::Source(TokenSource::file(path)));
+        }
+        let command = Config::get_env("GRAFANA_TOKEN_COMMAND")?;
+        let source = match Config::get_env("GRAFANA_TOKEN_TTL").and_then(|t| t.parse().ok()) {
+            Some(secs) => TokenSource::command(command).with_ttl(Duration::from_secs(secs)),
+            None => TokenSource::command(command),
+        };
+        Some(Auth::Source(source))
+    }
+
+    /// Value of the `Authorization` header, marked sensitive so it is never logged.
+    /// Fails when a token source cannot be read.
+    pub(crate) fn header(&self) -> Result<Option<HeaderValue>,GrafanaError> {
+        let value = match self {
+            Auth::Anonymous => return Ok(None),
+            Auth::Bearer(token) => format!("Bearer {}",token.expose()),
+            Auth::Basic { user, pass } => format!("Basic {}",STANDARD.encode(format!("{user}:{}",pass.expose()))),
+            Auth::Source(source) => format!("Bearer {}",source.token()?.expose()),
+        };
+        let mut value = HeaderValue::from_str(&value)
+            .map_err(|_| GrafanaError::Config(String::from("credential contains characters not allowed in a header")))?;
+        value.set_sensitive(true);
+        Ok(Some(value))
+    }
+
+    /// Forget any cached token so the next request reads it again, e.g. after a 401
+    pub(crate) fn invalidate(&self) {
+        if let Auth::Source(source) = self {
+            source.invalidate();
+        }
+    }
+}
+
+/// Where a [`TokenSource`] reads its token from
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub enum Origin {
+    /// File holding the token, surrounding whitespace is ignored
+    File(PathBuf),
+    /// Shell command printing the token on stdout
+    Command(String),
+}
+
+/// Token read from a file or command on first use and cached until its time to live
+/// expires, or the server rejects it
+/// # Example
+/// ```
+/// # use grafana_lib::common::auth::{Auth,TokenSource};
+/// # use std::time::Duration;
+/// let auth = Auth::Source(TokenSource::command("vault read -field=token secret/grafana")
+///     .with_ttl(Duration::from_secs(600)));
+/// ```
+#[derive(Clone,Serialize,Deserialize)]
+pub struct TokenSource {
+    origin : Origin,
+    ttl : Duration,
+    #[serde(skip)]
+    cache : Arc<Mutex<Option<(Secret,Instant)>>>,
+}
+
+impl TokenSource {
+    /// Read the token from a file, re-reading it every minute to pick up rotated secrets
+    pub fn file<P>(path : P) -> TokenSource
+    where P : Into<PathBuf>,
+    {
+        TokenSource::new(Origin::File(path.into()), FILE_TTL)
+    }
+
+    /// Run a shell command printing the token, running it again every five minutes
+    pub fn command<S>(command : S) -> TokenSource
+    where S : Into<String>,
+    {
+        TokenSource::new(Origin::Command(command.into()), COMMAND_TTL)
+    }
+
+    fn new(origin : Origin, ttl : Duration) -> TokenSource {
+        TokenSource { origin, ttl, cache : Arc::default() }
+    }
+
+    /// Change how long a token is used before it is read again
+    pub fn with_ttl(mut self, ttl : Duration) -> TokenSource {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Where the token is read from
+    pub fn origin(&self) -> &Origin {
+        &self.origin
+    }
+
+    /// Current token, read again when the cached one has expired. Clones share the cache.
+    pub fn token(&self) -> Result<Secret,GrafanaError> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((token, read)) = cache.as_ref() {
+            if read.elapsed() < self.ttl {
+                return Ok(token.clone());
+            }
+        }
+        let token = self.read()?;
+        *cache = Some((token.clone(), Instant::now()));
+        Ok(token)
+    }
+
+    /// Forget the cached token
+    pub fn invalidate(&self) {
+        *self.cache.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Read the token from its origin. Errors never include the output, it may hold secrets.
+    fn read(&self) -> Result<Secret,GrafanaError> {
+        let text = match &self.origin {
+            Origin::File(path) => {
+                debug!("Reading token from {}",path.display());
+                fs::read_to_string(path)
+                    .map_err(|e| GrafanaError::Config(format!("could not read token file {} : {}",path.display(),e)))?
+            },
+            Origin::Command(command) => {
+                debug!("Running token command");
+                let output = shell(command).output()
+                    .map_err(|e| GrafanaError::Config(format!("could not run token command : {}",e)))?;
+                if !output.status.success() {
+                    return Err(GrafanaError::Config(format!("token command failed with {}",output.status)));
+                }
+                String::from_utf8(output.stdout)
+                    .map_err(|_| GrafanaError::Config(String::from("token command printed invalid UTF-8")))?
+            },
+        };
+        match text.trim() {
+            "" => Err(GrafanaError::Config(String::from("token source is empty"))),
+            token => Ok(Secret::new(token)),
+        }
+    }
+}
+
+impl PartialEq for TokenSource {
+    /// Sources are equal when they read from the same place, whatever they have cached
+    fn eq(&self, other: &Self) -> bool {
+        self.origin == other.origin && self.ttl == other.ttl
+    }
+}
+
+impl fmt::Debug for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenSource")
+            .field("origin", &self.origin)
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+/// Command running `command` through the platform shell
+fn shell(command : &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    }
+    #[cfg(not(windows))]
+    {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_debug_redacts() {
+        let text = format!("{:?}", Auth::basic(String::from("admin"), String::from("s3cret")));
+        assert!(!text.contains("s3cret"), "{text}");
+        let text = serde_json::to_string(&Auth::bearer(String::from("glsa_XXXX"))).unwrap();
+        assert!(!text.contains("glsa_XXXX"), "{text}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_cached() {
+        let count = std::env::temp_dir().join(format!("grafana-lib-token-count-{}",std::process::id()));
+        let source = TokenSource::command(format!("echo x >> {0}; echo token-$(wc -l < {0} | tr -d ' ')",count.display()));
+        assert_eq!(source.token().unwrap().expose(), "token-1");
+        assert_eq!(source.clone().token().unwrap().expose(), "token-1");
+        source.invalidate();
+        assert_eq!(source.token().unwrap().expose(), "token-2");
+        let expired = source.clone().with_ttl(Duration::ZERO);
+        assert_eq!(expired.token().unwrap().expose(), "token-3");
+        std::fs::remove_file(count).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_errors_hide_output() {
+        let err = TokenSource::command("echo glsa_XXXX; exit 3").token().unwrap_err();
+        assert!(!err.to_string().contains("glsa_XXXX"), "{err}");
+        let err = TokenSource::file("/nonexistent/token").token().unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/token"), "{err}");
+    }
+}
@@ -0,0 +1,173 @@
+//! Capabilities Module
+//!
+//! Which Grafana the client is talking to and what it supports. Capabilities are derived
+//! from the version and feature toggles in the frontend settings, detected on first use
+//! and cached by the [`crate::common::api::Api`]. Modules call [`Capabilities::require`]
+//! before using an endpoint that only some servers have, so callers get
+//! [`GrafanaError::Unsupported`] rather than an unexplained 404.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::common::error::GrafanaError;
+use crate::community::other::FrontendSettings;
+
+/// Functionality only available on some servers
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Capability {
+    /// Folders inside folders, GA in Grafana 11 and behind `nestedFolders` before
+    NestedFolders,
+}
+
+impl Capability {
+    /// Version the capability is enabled by default and the toggles enabling it earlier
+    fn requirement(&self) -> (Version, &'static [&'static str]) {
+        match self {
+            Capability::NestedFolders => (Version::new(11, 0, 0), &["nestedFolders"]),
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Capability::NestedFolders => write!(f, "nested folders"),
+        }
+    }
+}
+
+/// Grafana version, ignoring any pre-release or build suffix
+#[derive(Debug,Clone,Copy,Default,PartialEq,Eq,PartialOrd,Ord)]
+pub struct Version {
+    /// Major version
+    pub major : u32,
+    /// Minor version
+    pub minor : u32,
+    /// Patch version
+    pub patch : u32,
+}
+
+impl Version {
+    /// Create a version
+    pub fn new(major : u32, minor : u32, patch : u32) -> Version {
+        Version { major, minor, patch }
+    }
+
+    /// Parse a version such as `10.4.1` or `11.0.0-pre`, None when it does not start with a
+    /// major version
+    /// # Example
+    /// ```
+    /// # use grafana_lib::common::capabilities::Version;
+    /// assert_eq!(Version::parse("11.0.0-preview"), Some(Version::new(11, 0, 0)));
+    /// assert_eq!(Version::parse("v10.4"), Some(Version::new(10, 4, 0)));
+    /// ```
+    pub fn parse(version : &str) -> Option<Version> {
+        let mut parts = version.trim().trim_start_matches('v').split('.').map(|p| {
+            let digits : String = p.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse::<u32>().ok()
+        });
+        let major = parts.next()??;
+        let minor = parts.next().flatten().unwrap_or_default();
+        let patch = parts.next().flatten().unwrap_or_default();
+        Some(Version { major, minor, patch })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}",self.major,self.minor,self.patch)
+    }
+}
+
+/// What a Grafana instance supports
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct Capabilities {
+    /// Server version, None when the server hides it
+    pub version : Option<Version>,
+    /// Edition, e.g. `Open Source` or `Enterprise`
+    pub edition : String,
+    /// Enabled feature toggles
+    pub toggles : BTreeSet<String>,
+    /// Feature toggles explicitly turned off
+    pub disabled : BTreeSet<String>,
+}
+
+impl Capabilities {
+    /// Derive capabilities from the frontend settings
+    pub fn from_settings(settings : &FrontendSettings) -> Capabilities {
+        let (toggles, disabled) = settings.feature_toggles.iter().fold((BTreeSet::new(), BTreeSet::new()), |(mut on, mut off), (name, enabled)| {
+            match enabled {
+                true => on.insert(name.clone()),
+                false => off.insert(name.clone()),
+            };
+            (on, off)
+        });
+        Capabilities {
+            version : Version::parse(&settings.build_info.version),
+            edition : settings.build_info.edition.clone(),
+            toggles,
+            disabled,
+        }
+    }
+
+    /// Is a feature toggle enabled?
+    pub fn toggle(&self, name : &str) -> bool {
+        self.toggles.contains(name)
+    }
+
+    /// Does the server support a capability? A toggle explicitly turned off disables it even
+    /// on versions where it is on by default.
+    /// # Example
+    /// ```
+    /// # use grafana_lib::common::capabilities::{Capabilities,Capability,Version};
+    /// let capabilities = Capabilities { version : Some(Version::new(11, 2, 0)), ..Default::default() };
+    /// assert!(capabilities.supports(Capability::NestedFolders));
+    /// let older = Capabilities { version : Some(Version::new(10, 4, 0)), ..Default::default() };
+    /// assert!(!older.supports(Capability::NestedFolders));
+    /// ```
+    pub fn supports(&self, capability : Capability) -> bool {
+        let (since, toggles) = capability.requirement();
+        if toggles.iter().any(|t| self.toggle(t)) {
+            return true;
+        }
+        let default_on = self.version.map(|v| v >= since).unwrap_or(false);
+        default_on && !toggles.iter().all(|t| self.disabled.contains(*t))
+    }
+
+    /// Fail with [`GrafanaError::Unsupported`] unless the server supports a capability
+    pub fn require(&self, capability : Capability) -> Result<(),GrafanaError> {
+        if self.supports(capability) {
+            return Ok(());
+        }
+        let (since, toggles) = capability.requirement();
+        let version = self.version.map(|v| v.to_string()).unwrap_or(String::from("an unknown version"));
+        Err(GrafanaError::Unsupported(format!("{capability} need Grafana {since} or the {} feature toggle, server is {version}",toggles.join(" or "))))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn settings(version : &str, toggles : &[(&str, bool)]) -> Capabilities {
+        let mut settings = FrontendSettings::default();
+        settings.build_info.version = version.to_string();
+        settings.feature_toggles = toggles.iter().map(|(n,on)| (n.to_string(), *on)).collect();
+        Capabilities::from_settings(&settings)
+    }
+
+    #[test]
+    fn test_supports() {
+        assert!(!settings("10.4.0", &[]).supports(Capability::NestedFolders));
+        assert!(settings("10.4.0", &[("nestedFolders", true)]).supports(Capability::NestedFolders));
+        assert!(settings("11.0.0", &[]).supports(Capability::NestedFolders));
+        assert!(!settings("11.0.0", &[("nestedFolders", false)]).supports(Capability::NestedFolders));
+        assert!(!settings("", &[]).supports(Capability::NestedFolders));
+    }
+
+    #[test]
+    fn test_require_message() {
+        let err = settings("10.4.0", &[]).require(Capability::NestedFolders).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported by server : nested folders need Grafana 11.0.0 or the nestedFolders feature toggle, server is 10.4.0");
+    }
+}
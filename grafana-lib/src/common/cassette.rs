@@ -0,0 +1,247 @@
+//! Cassette Module
+//!
+//! Record-and-replay [`Transport`], available with the `test-util` feature. Record once
+//! against a real Grafana instance, check the cassette file in and replay it offline in CI.
+//! Credentials are never written: authentication and cookie headers are dropped and any
+//! JSON field that looks like a secret (`password`, `token`, `secureJsonData`, ...) is
+//! replaced with [`REDACTED`].
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path,PathBuf};
+use std::sync::{Arc,Mutex,MutexGuard};
+
+use log::debug;
+use reqwest::header::{HeaderMap,HeaderName,HeaderValue,CONTENT_TYPE,RETRY_AFTER};
+use serde::{Serialize,Deserialize};
+use serde_json::Value;
+
+use crate::common::error::GrafanaError;
+use crate::common::transport::{BoxFuture,Request,Response,Transport};
+
+/// Placeholder written in place of secrets
+pub const REDACTED : &str = "REDACTED";
+
+/// Response headers kept in a cassette, everything else is dropped
+const KEPT_HEADERS : [HeaderName; 2] = [CONTENT_TYPE, RETRY_AFTER];
+
+/// Field names, or parts of them, that hold secrets
+const SECRET_FIELDS : [&str; 6] = ["password", "token", "secret", "securejsondata", "apikey", "key"];
+
+/// A recorded request and its response
+#[derive(Debug,Clone,PartialEq,Serialize,Deserialize)]
+pub struct Interaction {
+    /// HTTP method
+    pub method : String,
+    /// Path and query, independent of the host that was recorded
+    pub path : String,
+    /// Request body, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body : Option<Value>,
+    /// HTTP status code of the response
+    pub status : u16,
+    /// Response content type and retry headers
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers : BTreeMap<String,String>,
+    /// Response body
+    pub response : String,
+}
+
+/// Recording or replaying transport backed by a cassette file
+///
+/// # Example
+/// ```
+/// # use grafana_lib::client::ClientBuilder;
+/// # use grafana_lib::common::auth::Auth;
+/// # use grafana_lib::common::cassette::Cassette;
+/// # let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/search.json");
+/// let client = ClientBuilder::new("http://localhost:3000")
+///     .with_auth(Auth::Anonymous)
+///     .with_transport(Cassette::replay(path).unwrap())
+///     .build()
+///     .unwrap();
+/// let dashboards = client.search().dashboard(None).unwrap();
+/// assert_eq!(dashboards[0].title.as_deref(), Some("Home"));
+/// ```
+#[derive(Debug,Clone)]
+pub struct Cassette {
+    mode : Mode,
+    state : Arc<Mutex<Vec<(Interaction,bool)>>>,
+}
+
+#[derive(Debug,Clone)]
+enum Mode {
+    Record { path : PathBuf, inner : Arc<dyn Transport> },
+    Replay,
+}
+
+impl Cassette {
+    /// Send requests through `inner` and write every interaction to `path`, replacing any
+    /// existing cassette. The file is rewritten after each interaction.
+    /// # Example
+    /// ```no_run
+    /// # use grafana_lib::client::ClientBuilder;
+    /// # use grafana_lib::common::cassette::Cassette;
+    /// # use grafana_lib::common::config::Config;
+    /// # use grafana_lib::common::transport::ReqwestTransport;
+    /// let recorder = Cassette::record("tests/cassettes/search.json", ReqwestTransport::new(Config::new(String::from("http://localhost:3000"))));
+    /// let client = ClientBuilder::new("http://localhost:3000")
+    ///     .with_token(String::from("glsa_XXXX"))
+    ///     .with_transport(recorder)
+    ///     .build()
+    ///     .unwrap();
+    /// client.search().dashboard(None).unwrap();
+    /// ```
+    pub fn record<P,T>(path : P, inner : T) -> Cassette
+    where P : AsRef<Path>, T : Transport + 'static,
+    {
+        Cassette {
+            mode : Mode::Record { path : path.as_ref().to_path_buf(), inner : Arc::new(inner) },
+            state : Arc::default(),
+        }
+    }
+
+    /// Serve the interactions in a cassette file. Each interaction is served once, in
+    /// recorded order, and a request without a matching interaction fails.
+    pub fn replay<P>(path : P) -> Result<Cassette,GrafanaError>
+    where P : AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let payload = fs::read_to_string(path)
+            .map_err(|e| GrafanaError::Config(format!("could not read cassette {} : {}",path.display(),e)))?;
+        let interactions : Vec<Interaction> = serde_json::from_str(&payload)
+            .map_err(|e| GrafanaError::Config(format!("invalid cassette {} : {}",path.display(),e)))?;
+        Ok(Cassette {
+            mode : Mode::Replay,
+            state : Arc::new(Mutex::new(interactions.into_iter().map(|i| (i,false)).collect())),
+        })
+    }
+
+    /// Interactions recorded or loaded so far
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.lock().iter().map(|(i,_)| i.clone()).collect()
+    }
+
+    /// Record the outcome of a request and rewrite the cassette file
+    fn save(&self, path : &Path, request : &Request, response : &Response) -> Result<(),GrafanaError> {
+        let interaction = Interaction {
+            method : request.method.to_string(),
+            path : request.path().to_string(),
+            body : request.body.as_deref().map(|b| redact_value(serde_json::from_slice(b).unwrap_or(Value::Null))),
+            status : response.status,
+            headers : KEPT_HEADERS.iter()
+                .filter_map(|h| Some((h.to_string(), response.headers.get(h)?.to_str().ok()?.to_string())))
+                .collect(),
+            response : redact_body(&response.body),
+        };
+        let mut state = self.lock();
+        state.push((interaction,true));
+        let interactions : Vec<_> = state.iter().map(|(i,_)| i).collect();
+        let payload = serde_json::to_string_pretty(&interactions).map_err(|e| GrafanaError::Other(e.to_string()))?;
+        fs::write(path, payload)
+            .map_err(|e| GrafanaError::Other(format!("could not write cassette {} : {}",path.display(),e)))
+    }
+
+    /// Find the next unused interaction matching a request
+    fn play(&self, request : &Request) -> Result<Response,GrafanaError> {
+        let mut state = self.lock();
+        let method = request.method.as_str();
+        let (interaction, used) = state.iter_mut()
+            .find(|(i,used)| !used && i.method == method && i.path == request.path())
+            .ok_or_else(|| GrafanaError::Transport(format!("no recorded interaction for {} {}",request.method,request.path()).into()))?;
+        *used = true;
+        debug!("Replaying {} {}",interaction.method,interaction.path);
+        let mut headers = HeaderMap::new();
+        for (k,v) in &interaction.headers {
+            if let (Ok(k), Ok(v)) = (HeaderName::try_from(k.as_str()), HeaderValue::from_str(v)) {
+                headers.insert(k, v);
+            }
+        }
+        Ok(Response { status : interaction.status, headers, body : interaction.response.clone() })
+    }
+
+    fn lock(&self) -> MutexGuard<'_,Vec<(Interaction,bool)>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Transport for Cassette {
+    fn send(&self, request : Request) -> Result<Response,GrafanaError> {
+        match &self.mode {
+            Mode::Replay => self.play(&request),
+            Mode::Record { path, inner } => {
+                let response = inner.send(request.clone())?;
+                self.save(path, &request, &response)?;
+                Ok(response)
+            },
+        }
+    }
+
+    fn send_async(&self, request : Request) -> BoxFuture<'_, Result<Response,GrafanaError>> {
+        Box::pin(async move {
+            match &self.mode {
+                Mode::Replay => self.play(&request),
+                Mode::Record { path, inner } => {
+                    let response = inner.send_async(request.clone()).await?;
+                    self.save(path, &request, &response)?;
+                    Ok(response)
+                },
+            }
+        })
+    }
+}
+
+/// Redact secrets from a response body, leaving bodies that are not JSON untouched
+fn redact_body(body : &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(v) => redact_value(v).to_string(),
+        Err(_) => body.to_string(),
+    }
+}
+
+/// Replace the value of every field that looks like a secret
+fn redact_value(value : Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(map.into_iter().map(|(k,v)| {
+            let name = k.to_lowercase();
+            let secret = SECRET_FIELDS.iter().any(|s| name == *s || (s.len() > 3 && name.contains(s)));
+            match secret && !v.is_null() {
+                true => (k, Value::String(String::from(REDACTED))),
+                false => (k, redact_value(v)),
+            }
+        }).collect()),
+        Value::Array(items) => Value::Array(items.into_iter().map(redact_value).collect()),
+        v => v,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_value() {
+        let value = json!({
+            "name" : "prometheus",
+            "basicAuthPassword" : "hunter2",
+            "secureJsonData" : { "httpHeaderValue1" : "Bearer abc" },
+            "keys" : [{ "key" : "glsa_abc", "id" : 1 }],
+            "monkey" : "kept",
+            "token" : null,
+        });
+        assert_eq!(redact_value(value), json!({
+            "name" : "prometheus",
+            "basicAuthPassword" : REDACTED,
+            "secureJsonData" : REDACTED,
+            "keys" : [{ "key" : REDACTED, "id" : 1 }],
+            "monkey" : "kept",
+            "token" : null,
+        }));
+    }
+
+    #[test]
+    fn test_redact_body_not_json() {
+        assert_eq!(redact_body("<html>Bad Gateway</html>"), "<html>Bad Gateway</html>");
+    }
+}
@@ -1,41 +1,244 @@
 //! Error Module
-//! 
-//! 
+//!
+//! All fallible operations in this crate return a [`GrafanaError`]. Non-2xx responses
+//! from Grafana are mapped onto distinct variants so callers can branch on a 404 vs a 412
+//! without matching on strings.
 use std::error::Error;
+use std::fmt;
+
+use serde::Deserialize;
+
+/// Maximum number of characters of a response body kept in an error
+const EXCERPT_LEN : usize = 256;
+
+/// Error body returned by Grafana alongside a non-2xx status
+#[derive(Debug,Clone,PartialEq,Default,Deserialize)]
+pub struct ErrorBody {
+    /// Human readable error message
+    pub message : Option<String>,
+    /// Grafana status string, e.g. "version-mismatch" or "name-exists"
+    pub status : Option<String>,
+}
+
+impl ErrorBody {
+    /// Parse an error body from a response payload, falling back to using an excerpt of
+    /// the raw payload as the message when it is not JSON (e.g. an HTML error page).
+    /// # Example
+    /// ```
+    /// # use grafana_lib::common::error::ErrorBody;
+    /// let body = ErrorBody::parse(r#"{"message":"Dashboard not found"}"#);
+    /// assert_eq!(body.message, Some(String::from("Dashboard not found")));
+    /// ```
+    pub fn parse(payload : &str) -> ErrorBody {
+        match serde_json::from_str(payload) {
+            Ok(b) => b,
+            Err(_) => {
+                let message = excerpt(payload);
+                ErrorBody {
+                    message : (!message.is_empty()).then_some(message),
+                    status : None,
+                }
+            },
+        }
+    }
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.message, &self.status) {
+            (Some(m), Some(s)) => write!(f, "{m} ({s})"),
+            (Some(m), None) => write!(f, "{m}"),
+            (None, Some(s)) => write!(f, "{s}"),
+            (None, None) => write!(f, "no message"),
+        }
+    }
+}
 
 /// Error Structure
 #[derive(Debug)]
-pub struct GrafanaError {
-    /// Error Message
-    message : String,
-    /// Error Status Code
-    status : String,
+pub enum GrafanaError {
+    /// The request could not be sent or the response could not be read
+    Transport(Box<dyn Error + Send + Sync>),
+    /// Grafana rejected the credentials (401)
+    Unauthorized(ErrorBody),
+    /// Credentials are valid but lack permission (403)
+    Forbidden(ErrorBody),
+    /// Requested object does not exist (404)
+    NotFound(ErrorBody),
+    /// Object conflicts with an existing one (409)
+    Conflict(ErrorBody),
+    /// The object was changed by someone else since it was read (412 `version-mismatch`),
+    /// reload it or save with overwrite
+    VersionMismatch(ErrorBody),
+    /// Another object in the folder already has the same name (412 `name-exists`)
+    NameExists(ErrorBody),
+    /// Any other failed precondition (412)
+    PreconditionFailed(ErrorBody),
+    /// Any other non-2xx response
+    Http {
+        /// HTTP status code
+        status : u16,
+        /// Parsed error body
+        body : ErrorBody,
+    },
+    /// Response body could not be decoded into the expected model
+    Decode {
+        /// Underlying serde error
+        source : serde_json::Error,
+        /// Leading portion of the offending payload
+        excerpt : String,
+    },
+    /// Client configuration is invalid, e.g. a malformed URL or missing credential
+    Config(String),
+    /// The server does not support the requested functionality, e.g. nested folders on
+    /// Grafana 10, see [`crate::common::capabilities::Capabilities`]
+    Unsupported(String),
+    /// Any other failure, e.g. functionality that is not yet implemented
+    Other(String),
 }
 
 impl GrafanaError {
-    /// Create a new instance of GrafanaError passing in a message and status code.
+    /// Map a non-2xx response onto the matching error variant
     /// # Example
     /// ```
     /// # use grafana_lib::common::error::GrafanaError;
-    /// let err = GrafanaError::new(String::from("A Message"), String::from("A Status"));
+    /// let err = GrafanaError::from_response(404, r#"{"message":"Dashboard not found"}"#);
+    /// assert!(matches!(err, GrafanaError::NotFound(_)));
+    /// assert_eq!(err.status(), Some(404));
     /// ```
-    pub fn new(message : String, status : String) -> GrafanaError {
-        GrafanaError { message, status }
+    pub fn from_response(status : u16, payload : &str) -> GrafanaError {
+        let body = ErrorBody::parse(payload);
+        match status {
+            401 => GrafanaError::Unauthorized(body),
+            403 => GrafanaError::Forbidden(body),
+            404 => GrafanaError::NotFound(body),
+            409 => GrafanaError::Conflict(body),
+            412 => match body.status.as_deref() {
+                Some("version-mismatch") => GrafanaError::VersionMismatch(body),
+                Some("name-exists") => GrafanaError::NameExists(body),
+                _ => GrafanaError::PreconditionFailed(body),
+            },
+            _ => GrafanaError::Http { status, body },
+        }
+    }
+
+    /// Create a decode error, keeping an excerpt of the payload that failed to parse
+    pub fn decode(source : serde_json::Error, payload : &str) -> GrafanaError {
+        GrafanaError::Decode { source, excerpt : excerpt(payload) }
+    }
+
+    /// HTTP status code for errors caused by a non-2xx response
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            GrafanaError::Unauthorized(_) => Some(401),
+            GrafanaError::Forbidden(_) => Some(403),
+            GrafanaError::NotFound(_) => Some(404),
+            GrafanaError::Conflict(_) => Some(409),
+            GrafanaError::VersionMismatch(_)
+            | GrafanaError::NameExists(_)
+            | GrafanaError::PreconditionFailed(_) => Some(412),
+            GrafanaError::Http { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Error body returned by Grafana, if any
+    pub fn body(&self) -> Option<&ErrorBody> {
+        match self {
+            GrafanaError::Unauthorized(b)
+            | GrafanaError::Forbidden(b)
+            | GrafanaError::NotFound(b)
+            | GrafanaError::Conflict(b)
+            | GrafanaError::VersionMismatch(b)
+            | GrafanaError::NameExists(b)
+            | GrafanaError::PreconditionFailed(b)
+            | GrafanaError::Http { body : b, .. } => Some(b),
+            _ => None,
+        }
     }
 }
 
-impl std::fmt::Display for GrafanaError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} : {}", self.status, self.message)
+impl fmt::Display for GrafanaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrafanaError::Transport(e) => write!(f, "transport error : {e}"),
+            GrafanaError::Decode { source, excerpt } => write!(f, "decode error : {source} : {excerpt}"),
+            GrafanaError::Config(m) => write!(f, "configuration error : {m}"),
+            GrafanaError::Unsupported(m) => write!(f, "unsupported by server : {m}"),
+            GrafanaError::Other(m) => write!(f, "{m}"),
+            e => write!(f, "{} : {}", e.status().unwrap_or_default(), e.body().cloned().unwrap_or_default()),
+        }
     }
 }
 
-impl std::convert::From<String> for GrafanaError {
+impl From<String> for GrafanaError {
     fn from(msg: String) -> Self {
-        GrafanaError { message:  msg, status: String::from("simple-error") }
+        GrafanaError::Other(msg)
+    }
+}
+
+impl From<reqwest::Error> for GrafanaError {
+    fn from(e: reqwest::Error) -> Self {
+        GrafanaError::Transport(Box::new(e))
     }
 }
 
 impl Error for GrafanaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GrafanaError::Transport(e) => Some(e.as_ref()),
+            GrafanaError::Decode { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Trim a payload down to a short excerpt suitable for error messages
+fn excerpt(payload : &str) -> String {
+    let trimmed = payload.trim();
+    match trimmed.char_indices().nth(EXCERPT_LEN) {
+        Some((idx,_)) => format!("{}...",&trimmed[..idx]),
+        None => trimmed.to_string(),
+    }
+}
 
-}
\ No newline at end of file
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_status_mapping() {
+        assert!(matches!(GrafanaError::from_response(401, ""), GrafanaError::Unauthorized(_)));
+        assert!(matches!(GrafanaError::from_response(409, ""), GrafanaError::Conflict(_)));
+        assert!(matches!(GrafanaError::from_response(412, ""), GrafanaError::PreconditionFailed(_)));
+        assert_eq!(GrafanaError::from_response(502, "").status(), Some(502));
+    }
+
+    #[test]
+    fn test_error_body_json() {
+        let err = GrafanaError::from_response(412, r#"{"message":"The dashboard has been changed by someone else","status":"version-mismatch"}"#);
+        assert!(matches!(err, GrafanaError::VersionMismatch(_)));
+        let body = err.body().unwrap();
+        assert_eq!(body.status, Some(String::from("version-mismatch")));
+        let err = GrafanaError::from_response(412, r#"{"message":"A dashboard with the same name in the folder already exists","status":"name-exists"}"#);
+        assert!(matches!(err, GrafanaError::NameExists(_)));
+        assert_eq!(err.status(), Some(412));
+    }
+
+    #[test]
+    fn test_error_body_html() {
+        let body = ErrorBody::parse("<html><body>Bad Gateway</body></html>");
+        assert_eq!(body.message, Some(String::from("<html><body>Bad Gateway</body></html>")));
+        assert_eq!(body.status, None);
+    }
+
+    #[test]
+    fn test_decode_excerpt() {
+        let payload = "x".repeat(1000);
+        let source = serde_json::from_str::<u32>(&payload).unwrap_err();
+        match GrafanaError::decode(source, &payload) {
+            GrafanaError::Decode { excerpt, .. } => assert_eq!(excerpt.len(), EXCERPT_LEN + 3),
+            e => panic!("unexpected error {e}"),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Limit Module
+//!
+//! Client-side throttling for bulk operations. A token bucket caps the request rate and a
+//! semaphore caps the number of requests in flight. Both are shared by every clone of an
+//! [`crate::common::api::Api`] and by its blocking and async paths.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Condvar,Mutex,MutexGuard};
+use std::task::{Context,Poll,Waker};
+use std::time::{Duration,Instant};
+
+use crate::common::config::Config;
+
+/// Token bucket rate limit
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct RateLimit {
+    /// Sustained requests per second
+    pub per_second : f64,
+    /// Requests that may be sent back to back before the rate applies
+    pub burst : u32,
+}
+
+/// Rate and concurrency limits applied to every request sent through a connection pool
+#[derive(Debug,Default)]
+pub(crate) struct Limiter {
+    bucket : Option<Bucket>,
+    semaphore : Option<Semaphore>,
+}
+
+impl Limiter {
+    pub(crate) fn new(config : &Config) -> Limiter {
+        Limiter {
+            bucket : config.rate_limit().map(Bucket::new),
+            semaphore : config.max_in_flight().map(Semaphore::new),
+        }
+    }
+
+    /// Wait for a request slot, blocking the current thread
+    pub(crate) fn acquire(&self) -> Option<Permit<'_>> {
+        if let Some(bucket) = &self.bucket {
+            let delay = bucket.reserve();
+            if !delay.is_zero() {
+                std::thread::sleep(delay);
+            }
+        }
+        self.semaphore.as_ref().map(|s| s.acquire())
+    }
+
+    /// Wait for a request slot without blocking the runtime
+    pub(crate) async fn acquire_async(&self) -> Option<Permit<'_>> {
+        if let Some(bucket) = &self.bucket {
+            let delay = bucket.reserve();
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+        }
+        match &self.semaphore {
+            Some(s) => Some(Acquire { semaphore : s }.await),
+            None => None,
+        }
+    }
+}
+
+/// Token bucket where each request reserves a token, waiting when the bucket is in debt
+#[derive(Debug)]
+struct Bucket {
+    limit : RateLimit,
+    state : Mutex<(f64,Instant)>,
+}
+
+impl Bucket {
+    fn new(limit : RateLimit) -> Bucket {
+        let limit = RateLimit { per_second : limit.per_second.max(f64::MIN_POSITIVE), burst : limit.burst.max(1) };
+        Bucket { limit, state : Mutex::new((limit.burst as f64, Instant::now())) }
+    }
+
+    /// Take a token, returning how long to wait before using it
+    fn reserve(&self) -> Duration {
+        let mut state = lock(&self.state);
+        let (tokens, last) = *state;
+        let now = Instant::now();
+        let tokens = (tokens + now.duration_since(last).as_secs_f64() * self.limit.per_second)
+            .min(self.limit.burst as f64) - 1.0;
+        *state = (tokens, now);
+        match tokens >= 0.0 {
+            true => Duration::ZERO,
+            false => Duration::try_from_secs_f64(-tokens / self.limit.per_second).unwrap_or(Duration::MAX),
+        }
+    }
+}
+
+/// Counting semaphore usable from both blocking threads and async tasks
+#[derive(Debug)]
+struct Semaphore {
+    state : Mutex<Slots>,
+    released : Condvar,
+}
+
+#[derive(Debug)]
+struct Slots {
+    available : usize,
+    wakers : Vec<Waker>,
+}
+
+impl Semaphore {
+    fn new(permits : usize) -> Semaphore {
+        Semaphore {
+            state : Mutex::new(Slots { available : permits.max(1), wakers : Vec::new() }),
+            released : Condvar::new(),
+        }
+    }
+
+    fn acquire(&self) -> Permit<'_> {
+        let mut state = lock(&self.state);
+        while state.available == 0 {
+            state = self.released.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        state.available -= 1;
+        Permit { semaphore : self }
+    }
+
+    fn release(&self) {
+        let wakers = {
+            let mut state = lock(&self.state);
+            state.available += 1;
+            std::mem::take(&mut state.wakers)
+        };
+        // Wake every waiter, those that lose the race simply wait again
+        self.released.notify_all();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// Future resolving once a semaphore slot is free
+struct Acquire<'a> {
+    semaphore : &'a Semaphore,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = Permit<'a>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = lock(&self.semaphore.state);
+        match state.available {
+            0 => {
+                state.wakers.push(cx.waker().clone());
+                Poll::Pending
+            },
+            _ => {
+                state.available -= 1;
+                Poll::Ready(Permit { semaphore : self.semaphore })
+            },
+        }
+    }
+}
+
+/// Slot held for the duration of a request, released on drop
+#[derive(Debug)]
+pub(crate) struct Permit<'a> {
+    semaphore : &'a Semaphore,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+/// Lock a mutex, carrying on if another thread panicked while holding it
+fn lock<T>(mutex : &Mutex<T>) -> MutexGuard<'_,T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bucket_burst_then_rate() {
+        let bucket = Bucket::new(RateLimit { per_second : 10.0, burst : 2 });
+        assert_eq!(bucket.reserve(), Duration::ZERO);
+        assert_eq!(bucket.reserve(), Duration::ZERO);
+        let delay = bucket.reserve();
+        assert!(delay > Duration::from_millis(80) && delay <= Duration::from_millis(100), "{delay:?}");
+        let delay = bucket.reserve();
+        assert!(delay > Duration::from_millis(180) && delay <= Duration::from_millis(200), "{delay:?}");
+    }
+
+    #[test]
+    fn test_bucket_tiny_rate() {
+        for per_second in [0.0, f64::MIN_POSITIVE, -1.0, f64::NAN] {
+            let bucket = Bucket::new(RateLimit { per_second, burst : 1 });
+            assert_eq!(bucket.reserve(), Duration::ZERO);
+            assert_eq!(bucket.reserve(), Duration::MAX);
+        }
+    }
+
+    #[test]
+    fn test_semaphore_release() {
+        let semaphore = Semaphore::new(1);
+        let permit = semaphore.acquire();
+        assert_eq!(lock(&semaphore.state).available, 0);
+        drop(permit);
+        assert_eq!(lock(&semaphore.state).available, 1);
+    }
+}
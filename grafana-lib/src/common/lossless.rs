@@ -0,0 +1,158 @@
+//! Lossless Module
+//!
+//! Grafana's JSON models are large, plugin specific and change between versions, so the
+//! models in this crate only type the fields they use. [`lossless!`] implements `Serialize`
+//! and `Deserialize` for a model with optional typed fields and an `extra` map holding the
+//! JSON as it was read. Typed fields are decoded from it when they have the expected shape
+//! and replace its value in place when written, so keys keep their order. Explicit nulls
+//! and values of an unexpected type are written back exactly as they were read. A value
+//! that does not decode is logged as a warning, as it usually means the model's type is
+//! wrong for some Grafana version. Models are equal when they would be written the same.
+
+use log::warn;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map,Value};
+
+/// Decode a typed field from raw JSON, None for null or unexpected values
+pub(crate) fn take<T>(model : &str, map : &Map<String,Value>, key : &str) -> Option<T>
+where T : DeserializeOwned,
+{
+    let value = map.get(key).filter(|v| !v.is_null())?;
+    match T::deserialize(value) {
+        Ok(typed) => Some(typed),
+        Err(e) => {
+            warn!("{model}: keeping '{key}' as raw JSON, it does not decode: {e}");
+            None
+        },
+    }
+}
+
+/// Is the raw value of a typed field replaced or removed when the model is written? Values
+/// the field was read from are, null or unexpected values are not.
+pub(crate) fn shadowed<T>(map : &Map<String,Value>, key : &str, value : &Option<T>) -> bool
+where T : DeserializeOwned,
+{
+    value.is_some() || map.get(key).is_some_and(|v| !v.is_null() && T::deserialize(v).is_ok())
+}
+
+/// Put a typed field back into raw JSON at the position of the value it was read from. A
+/// field cleared after it was read is removed.
+pub(crate) fn put<T,E>(map : &mut Map<String,Value>, key : &str, value : &Option<T>) -> Result<(),E>
+where T : Serialize + DeserializeOwned, E : serde::ser::Error,
+{
+    match value {
+        Some(v) => {
+            map.insert(key.to_string(), serde_json::to_value(v).map_err(E::custom)?);
+        },
+        None => if shadowed(map, key, value) {
+            map.shift_remove(key);
+        },
+    }
+    Ok(())
+}
+
+/// Implement `Serialize`, `Deserialize` and `PartialEq` for a model whose fields are all
+/// `Option`s plus an `extra : Map<String,Value>`, given the JSON key of each field
+macro_rules! lossless {
+    ($model:ident { $($field:ident : $key:literal),* $(,)? }) => {
+        impl<'de> serde::Deserialize<'de> for $model {
+            fn deserialize<D>(deserializer : D) -> Result<Self,D::Error>
+            where D : serde::Deserializer<'de>,
+            {
+                let extra = <serde_json::Map<String,serde_json::Value> as serde::Deserialize>::deserialize(deserializer)?;
+                Ok($model {
+                    $($field : $crate::common::lossless::take(stringify!($model), &extra, $key),)*
+                    extra,
+                })
+            }
+        }
+
+        impl serde::Serialize for $model {
+            fn serialize<S>(&self, serializer : S) -> Result<S::Ok,S::Error>
+            where S : serde::Serializer,
+            {
+                let mut map = self.extra.clone();
+                $($crate::common::lossless::put(&mut map, $key, &self.$field)?;)*
+                serde::Serialize::serialize(&map, serializer)
+            }
+        }
+
+        impl PartialEq for $model {
+            fn eq(&self, other : &Self) -> bool {
+                // Raw values replaced by the typed fields do not count
+                let written = |model : &$model| {
+                    let mut extra = model.extra.clone();
+                    $(if $crate::common::lossless::shadowed(&model.extra, $key, &model.$field) {
+                        extra.shift_remove($key);
+                    })*
+                    extra
+                };
+                $(self.$field == other.$field &&)* written(self) == written(other)
+            }
+        }
+    };
+}
+
+pub(crate) use lossless;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    use log::{Log,Metadata,Record};
+    use std::cell::RefCell;
+
+    thread_local! {
+        static WARNINGS : RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Logger collecting the warnings of the current thread
+    struct Capture;
+
+    impl Log for Capture {
+        fn enabled(&self, metadata : &Metadata) -> bool {
+            metadata.level() <= log::Level::Warn
+        }
+        fn log(&self, record : &Record) {
+            if self.enabled(record.metadata()) {
+                WARNINGS.with(|w| w.borrow_mut().push(record.args().to_string()));
+            }
+        }
+        fn flush(&self) {}
+    }
+
+    fn warnings() -> Vec<String> {
+        let _installed = log::set_logger(&Capture).map(|_| log::set_max_level(log::LevelFilter::Warn));
+        WARNINGS.with(|w| w.take())
+    }
+
+    #[test]
+    fn test_put_in_place() {
+        let mut map = json!({ "refresh" : false, "uid" : null, "title" : "Home", "version" : 1 }).as_object().unwrap().clone();
+        assert_eq!(take::<String>("Model", &map, "refresh"), None);
+        assert_eq!(take::<String>("Model", &map, "uid"), None);
+        assert_eq!(take::<String>("Model", &map, "title"), Some(String::from("Home")));
+        assert_eq!(take::<u64>("Model", &map, "version"), Some(1));
+        put::<String,serde_json::Error>(&mut map, "refresh", &None).unwrap();
+        put::<String,serde_json::Error>(&mut map, "uid", &None).unwrap();
+        put::<String,serde_json::Error>(&mut map, "title", &Some(String::from("Edited"))).unwrap();
+        put::<u64,serde_json::Error>(&mut map, "version", &None).unwrap();
+        put::<String,serde_json::Error>(&mut map, "timezone", &Some(String::from("utc"))).unwrap();
+        assert_eq!(serde_json::to_string(&map).unwrap(), r#"{"refresh":false,"uid":null,"title":"Edited","timezone":"utc"}"#);
+    }
+
+    #[test]
+    fn test_take_warns() {
+        warnings();
+        let map = json!({ "panels" : [{ "id" : 1 }, { "id" : "two" }], "uid" : null }).as_object().unwrap().clone();
+        assert_eq!(take::<Vec<Map<String,Value>>>("Model", &map, "uid"), None);
+        assert_eq!(warnings(), Vec::<String>::new());
+        assert_eq!(take::<Vec<u64>>("Model", &map, "panels"), None);
+        let logged = warnings();
+        assert_eq!(logged.len(), 1);
+        assert!(logged[0].starts_with("Model: keeping 'panels' as raw JSON, it does not decode: "), "{logged:?}");
+        assert!(map.contains_key("panels"));
+    }
+}
@@ -0,0 +1,179 @@
+//! Metrics Module
+//!
+//! Opt-in request metrics for services embedding the client. Attach a [`Metrics`] registry
+//! with [`crate::client::ClientBuilder::with_metrics`] and render it in the Prometheus text
+//! exposition format from your own `/metrics` handler. Requests are labelled by method and
+//! path template (see [`crate::common::trace::path_template`]), so identifiers never create
+//! new series.
+//!
+//! | Metric | Type | Labels |
+//! |--------|------|--------|
+//! | `grafana_client_requests_total` | counter | `method`, `route`, `status` (`2xx`, `4xx`, ..., `error`) |
+//! | `grafana_client_request_duration_seconds` | histogram | `method`, `route` |
+//! | `grafana_client_retries_total` | counter | `method`, `route` |
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc,Mutex,MutexGuard};
+use std::time::Duration;
+
+use reqwest::Method;
+
+use crate::common::error::GrafanaError;
+use crate::common::trace::path_template;
+use crate::common::transport::{Request,Response};
+
+/// Upper bounds of the latency histogram buckets, in seconds
+const BUCKETS : [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Registry of client request metrics
+///
+/// Clones share the same counters, so keep a clone to render after handing one to a client.
+/// # Example
+/// ```
+/// # use grafana_lib::client::ClientBuilder;
+/// # use grafana_lib::common::metrics::Metrics;
+/// let metrics = Metrics::new();
+/// let client = ClientBuilder::new("http://localhost:3000")
+///     .with_token(String::from("glsa_XXXX"))
+///     .with_metrics(metrics.clone())
+///     .build()
+///     .unwrap();
+/// let _ = client.search().dashboard(None);
+/// println!("{}", metrics.render());
+/// ```
+#[derive(Debug,Clone,Default)]
+pub struct Metrics {
+    registry : Arc<Mutex<Registry>>,
+}
+
+/// Method and path template
+type Route = (String,String);
+
+#[derive(Debug,Default)]
+struct Registry {
+    requests : BTreeMap<(String,String,String),u64>,
+    durations : BTreeMap<Route,Histogram>,
+    retries : BTreeMap<Route,u64>,
+}
+
+#[derive(Debug,Default)]
+struct Histogram {
+    buckets : [u64; BUCKETS.len()],
+    count : u64,
+    sum : f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds : f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+impl Metrics {
+    /// Create an empty registry
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /// Number of requests counted for a method, path template and status class such as
+    /// `2xx` or `error`
+    pub fn requests(&self, method : &Method, route : &str, status : &str) -> u64 {
+        let key = (method.to_string(), route.to_string(), status.to_string());
+        self.lock().requests.get(&key).copied().unwrap_or_default()
+    }
+
+    /// Number of retries counted for a method and path template
+    pub fn retries(&self, method : &Method, route : &str) -> u64 {
+        self.lock().retries.get(&(method.to_string(), route.to_string())).copied().unwrap_or_default()
+    }
+
+    /// Record the outcome of an API call
+    pub(crate) fn observe(&self, request : &Request, result : &Result<Response,GrafanaError>, latency : Duration, retries : u32) {
+        let route = (request.method.to_string(), path_template(request.path()));
+        let status = match result {
+            Ok(r) => format!("{}xx",r.status / 100),
+            Err(_) => String::from("error"),
+        };
+        let mut registry = self.lock();
+        *registry.requests.entry((route.0.clone(), route.1.clone(), status)).or_default() += 1;
+        registry.durations.entry(route.clone()).or_default().observe(latency.as_secs_f64());
+        if retries > 0 {
+            *registry.retries.entry(route).or_default() += u64::from(retries);
+        }
+    }
+
+    /// Render every metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let registry = self.lock();
+        let mut out = String::new();
+        out.push_str("# HELP grafana_client_requests_total Requests made to Grafana.\n");
+        out.push_str("# TYPE grafana_client_requests_total counter\n");
+        for ((method, route, status), count) in &registry.requests {
+            let _ = writeln!(out, "grafana_client_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                escape(method), escape(route), escape(status), count);
+        }
+        out.push_str("# HELP grafana_client_request_duration_seconds Time taken by requests to Grafana, including retries.\n");
+        out.push_str("# TYPE grafana_client_request_duration_seconds histogram\n");
+        for ((method, route), histogram) in &registry.durations {
+            let labels = format!("method=\"{}\",route=\"{}\"",escape(method),escape(route));
+            for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+                let _ = writeln!(out, "grafana_client_request_duration_seconds_bucket{{{labels},le=\"{bound}\"}} {count}");
+            }
+            let _ = writeln!(out, "grafana_client_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",histogram.count);
+            let _ = writeln!(out, "grafana_client_request_duration_seconds_sum{{{labels}}} {}",histogram.sum);
+            let _ = writeln!(out, "grafana_client_request_duration_seconds_count{{{labels}}} {}",histogram.count);
+        }
+        out.push_str("# HELP grafana_client_retries_total Retries of requests to Grafana.\n");
+        out.push_str("# TYPE grafana_client_retries_total counter\n");
+        for ((method, route), count) in &registry.retries {
+            let _ = writeln!(out, "grafana_client_retries_total{{method=\"{}\",route=\"{}\"}} {}",
+                escape(method), escape(route), count);
+        }
+        out
+    }
+
+    fn lock(&self) -> MutexGuard<'_,Registry> {
+        self.registry.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Escape a label value for the text exposition format
+fn escape(value : &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+        let request = Request::new(Method::GET, String::from("http://grafana/api/dashboards/uid/abc"));
+        metrics.observe(&request, &Ok(Response::new(200, "{}")), Duration::from_millis(20), 0);
+        metrics.observe(&request, &Ok(Response::new(404, "{}")), Duration::from_millis(2), 2);
+        metrics.observe(&request, &Err(GrafanaError::Other(String::from("reset"))), Duration::from_secs(20), 0);
+        let text = metrics.render();
+        assert!(text.contains("grafana_client_requests_total{method=\"GET\",route=\"/api/dashboards/uid/{uid}\",status=\"2xx\"} 1\n"), "{text}");
+        assert!(text.contains("status=\"4xx\"} 1\n"));
+        assert!(text.contains("status=\"error\"} 1\n"));
+        assert!(text.contains("grafana_client_request_duration_seconds_bucket{method=\"GET\",route=\"/api/dashboards/uid/{uid}\",le=\"0.005\"} 1\n"));
+        assert!(text.contains(",le=\"0.025\"} 2\n"));
+        assert!(text.contains(",le=\"10\"} 2\n"));
+        assert!(text.contains(",le=\"+Inf\"} 3\n"));
+        assert!(text.contains("grafana_client_request_duration_seconds_count{method=\"GET\",route=\"/api/dashboards/uid/{uid}\"} 3\n"));
+        assert!(text.contains("grafana_client_retries_total{method=\"GET\",route=\"/api/dashboards/uid/{uid}\"} 2\n"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
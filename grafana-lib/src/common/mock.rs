@@ -0,0 +1,117 @@
+//! Mock Module
+//!
+//! In-memory [`Transport`] for unit tests, available with the `test-util` feature. Register
+//! canned responses per method and path, hand the mock to a client and assert on the
+//! requests it received afterwards.
+
+use std::collections::VecDeque;
+use std::sync::{Arc,Mutex,MutexGuard};
+
+use reqwest::Method;
+
+use crate::common::error::GrafanaError;
+use crate::common::transport::{Request,Response,Transport};
+
+/// In-memory transport serving canned responses
+///
+/// Clones share routes and recorded requests, so keep a clone to make assertions after
+/// passing the mock to a client.
+/// # Example
+/// ```
+/// # use grafana_lib::client::ClientBuilder;
+/// # use grafana_lib::common::auth::Auth;
+/// # use grafana_lib::common::mock::MockTransport;
+/// # use grafana_lib::common::transport::Response;
+/// # use reqwest::Method;
+/// let mock = MockTransport::new();
+/// mock.on(Method::GET, "/api/search?type=dash-db", Response::json("[]"));
+/// let client = ClientBuilder::new("http://grafana.test")
+///     .with_auth(Auth::Anonymous)
+///     .with_transport(mock.clone())
+///     .build()
+///     .unwrap();
+/// assert!(client.search().dashboard(None).unwrap().is_empty());
+/// mock.assert_requested(Method::GET, "/api/search?type=dash-db");
+/// ```
+#[derive(Debug,Clone,Default)]
+pub struct MockTransport {
+    state : Arc<Mutex<State>>,
+}
+
+#[derive(Debug,Default)]
+struct State {
+    routes : Vec<Route>,
+    requests : Vec<Request>,
+}
+
+#[derive(Debug)]
+struct Route {
+    method : Method,
+    path : String,
+    responses : VecDeque<Response>,
+}
+
+impl Route {
+    /// A path containing a query must match exactly, otherwise the query is ignored
+    fn matches(&self, request : &Request) -> bool {
+        let path = request.path();
+        self.method == request.method && match self.path.contains('?') {
+            true => self.path == path,
+            false => self.path == path.split('?').next().unwrap_or_default(),
+        }
+    }
+}
+
+impl MockTransport {
+    /// Create a mock without any responses
+    pub fn new() -> MockTransport {
+        MockTransport::default()
+    }
+
+    /// Queue a response for a method and path, e.g. `/api/search`. Responses for the same
+    /// route are served in order and the last one is repeated.
+    pub fn on(&self, method : Method, path : &str, response : Response) -> &MockTransport {
+        let mut state = self.lock();
+        match state.routes.iter_mut().find(|r| r.method == method && r.path == path) {
+            Some(route) => route.responses.push_back(response),
+            None => state.routes.push(Route { method, path : path.to_string(), responses : VecDeque::from([response]) }),
+        }
+        self
+    }
+
+    /// All requests received so far
+    pub fn requests(&self) -> Vec<Request> {
+        self.lock().requests.clone()
+    }
+
+    /// Requests received for a method and path, matched like routes
+    pub fn requests_to(&self, method : Method, path : &str) -> Vec<Request> {
+        let route = Route { method, path : path.to_string(), responses : VecDeque::new() };
+        self.lock().requests.iter().filter(|r| route.matches(r)).cloned().collect()
+    }
+
+    /// Panic unless at least one request was received for a method and path
+    pub fn assert_requested(&self, method : Method, path : &str) {
+        if self.requests_to(method.clone(), path).is_empty() {
+            let received : Vec<_> = self.requests().iter().map(|r| format!("{} {}",r.method,r.path())).collect();
+            panic!("no request for {method} {path}, received {received:?}");
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_,State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&self, request : Request) -> Result<Response,GrafanaError> {
+        let mut state = self.lock();
+        state.requests.push(request.clone());
+        let route = state.routes.iter_mut().find(|r| r.matches(&request))
+            .ok_or_else(|| GrafanaError::Transport(format!("no mock response for {} {}",request.method,request.path()).into()))?;
+        match route.responses.len() {
+            1 => Ok(route.responses[0].clone()),
+            _ => Ok(route.responses.pop_front().unwrap_or_default()),
+        }
+    }
+}
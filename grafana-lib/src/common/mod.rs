@@ -1,6 +1,19 @@
 //! Common Module
 //! 
 
+pub mod auth;
+pub mod capabilities;
+#[cfg(feature = "test-util")]
+pub mod cassette;
 pub mod config;
 pub mod error;
+pub mod limit;
+pub(crate) mod lossless;
+pub mod metrics;
+#[cfg(feature = "test-util")]
+pub mod mock;
+pub mod retry;
+pub mod secret;
+pub mod trace;
+pub mod transport;
 pub mod api;
\ No newline at end of file
@@ -0,0 +1,236 @@
+//! Retry Module
+//!
+//! A [`RetryPolicy`] decides whether a failed request is sent again and how long to wait
+//! first. By default only idempotent methods are retried, on connection failures and on
+//! the statuses Grafana returns while overloaded or behind a restarting load balancer.
+//! A `Retry-After` header is waited for as requested, unless it asks for longer than the
+//! policy allows, in which case the response is returned instead.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher,Hasher};
+use std::time::{Duration,SystemTime};
+
+use reqwest::Method;
+use reqwest::header::{HeaderMap,RETRY_AFTER};
+
+use crate::common::error::GrafanaError;
+
+/// Policy controlling automatic retries of failed requests
+#[derive(Debug,Clone,PartialEq)]
+pub struct RetryPolicy {
+    max_attempts : u32,
+    initial_backoff : Duration,
+    max_backoff : Duration,
+    max_retry_after : Duration,
+    jitter : bool,
+    statuses : Vec<u16>,
+    methods : Vec<Method>,
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts, backing off from 200ms up to 10s with jitter or waiting up to 60s
+    /// when asked to by `Retry-After`, retrying idempotent methods on 429, 502, 503 and 504
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts : 3,
+            initial_backoff : Duration::from_millis(200),
+            max_backoff : Duration::from_secs(10),
+            max_retry_after : Duration::from_secs(60),
+            jitter : true,
+            statuses : vec![429, 502, 503, 504],
+            methods : vec![Method::GET, Method::HEAD, Method::OPTIONS, Method::PUT, Method::DELETE],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that never retries
+    pub fn never() -> RetryPolicy {
+        RetryPolicy::default().with_max_attempts(1)
+    }
+
+    /// Maximum number of attempts, including the first one
+    pub fn with_max_attempts(mut self, max_attempts : u32) -> RetryPolicy {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Wait `initial` before the first retry, doubling for each further retry up to `max`
+    pub fn with_backoff(mut self, initial : Duration, max : Duration) -> RetryPolicy {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Longest `Retry-After` delay waited for, a response asking for longer is returned
+    /// to the caller without retrying
+    pub fn with_max_retry_after(mut self, max : Duration) -> RetryPolicy {
+        self.max_retry_after = max;
+        self
+    }
+
+    /// Randomise each backoff between half and the full delay so clients do not retry in step
+    pub fn with_jitter(mut self, jitter : bool) -> RetryPolicy {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Response statuses that are retried
+    pub fn with_statuses(mut self, statuses : Vec<u16>) -> RetryPolicy {
+        self.statuses = statuses;
+        self
+    }
+
+    /// HTTP methods that are safe to retry
+    pub fn with_methods(mut self, methods : Vec<Method>) -> RetryPolicy {
+        self.methods = methods;
+        self
+    }
+
+    /// Also retry a method that is not idempotent, e.g. POST for a save that sets an explicit version
+    /// # Example
+    /// ```
+    /// # use grafana_lib::common::retry::RetryPolicy;
+    /// # use reqwest::Method;
+    /// let policy = RetryPolicy::default().with_method(Method::POST);
+    /// assert!(policy.retries(&Method::POST));
+    /// ```
+    pub fn with_method(mut self, method : Method) -> RetryPolicy {
+        if !self.methods.contains(&method) {
+            self.methods.push(method);
+        }
+        self
+    }
+
+    /// Maximum number of attempts, including the first one
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Is the method retried by this policy
+    pub fn retries(&self, method : &Method) -> bool {
+        self.max_attempts > 1 && self.methods.contains(method)
+    }
+
+    /// Delay before the next attempt, or None when the outcome of attempt number `attempt`
+    /// (starting at 1) should be returned to the caller
+    pub(crate) fn next_delay(&self, method : &Method, attempt : u32, outcome : &Outcome) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.methods.contains(method) {
+            return None;
+        }
+        let retry_after = match outcome {
+            Outcome::Response { status, retry_after } if self.statuses.contains(status) => *retry_after,
+            Outcome::Response { .. } => return None,
+            Outcome::Failed(GrafanaError::Transport(_)) => None,
+            Outcome::Failed(_) => return None,
+        };
+        match retry_after {
+            Some(d) if d > self.max_retry_after => None,
+            Some(d) => Some(d),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+
+    /// Exponential backoff following attempt number `attempt`
+    fn backoff(&self, attempt : u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.initial_backoff.saturating_mul(factor).min(self.max_backoff);
+        match self.jitter {
+            true => delay / 2 + delay.mul_f64(random() / 2.0),
+            false => delay,
+        }
+    }
+}
+
+/// Result of a single attempt, as far as the retry decision is concerned
+pub(crate) enum Outcome<'a> {
+    /// A response was received
+    Response {
+        /// HTTP status code
+        status : u16,
+        /// Delay requested by the server
+        retry_after : Option<Duration>,
+    },
+    /// No response was received
+    Failed(&'a GrafanaError),
+}
+
+/// Parse a `Retry-After` header given either in seconds or as an HTTP date
+pub(crate) fn retry_after(headers : &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let when = httpdate::parse_http_date(value).ok()?;
+    Some(when.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Random number in [0,1), good enough to spread out retries
+fn random() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_nanos());
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn status(status : u16) -> Outcome<'static> {
+        Outcome::Response { status, retry_after : None }
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RetryPolicy::default()
+            .with_jitter(false)
+            .with_max_attempts(10)
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(500));
+        let delays : Vec<_> = (1..5).map(|a| policy.next_delay(&Method::GET, a, &status(503)).unwrap()).collect();
+        assert_eq!(delays, vec![
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+            Duration::from_millis(400),
+            Duration::from_millis(500),
+        ]);
+    }
+
+    #[test]
+    fn test_jitter_within_bounds() {
+        let policy = RetryPolicy::default().with_backoff(Duration::from_millis(100), Duration::from_secs(1));
+        for _ in 0..100 {
+            let delay = policy.backoff(1);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100), "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn test_retry_decision() {
+        let policy = RetryPolicy::default();
+        assert!(policy.next_delay(&Method::GET, 1, &status(502)).is_some());
+        assert!(policy.next_delay(&Method::GET, 3, &status(502)).is_none());
+        assert!(policy.next_delay(&Method::GET, 1, &status(500)).is_none());
+        assert!(policy.next_delay(&Method::POST, 1, &status(502)).is_none());
+        assert!(RetryPolicy::never().next_delay(&Method::GET, 1, &status(502)).is_none());
+        let error = GrafanaError::Config(String::from("bad"));
+        assert!(policy.next_delay(&Method::GET, 1, &Outcome::Failed(&error)).is_none());
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("2"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(2)));
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+        let policy = RetryPolicy::default();
+        let outcome = Outcome::Response { status : 429, retry_after : Some(Duration::from_secs(60)) };
+        assert_eq!(policy.next_delay(&Method::GET, 1, &outcome), Some(Duration::from_secs(60)));
+        let outcome = Outcome::Response { status : 429, retry_after : Some(Duration::from_secs(61)) };
+        assert_eq!(policy.next_delay(&Method::GET, 1, &outcome), None);
+        let policy = policy.with_max_retry_after(Duration::from_secs(120));
+        assert_eq!(policy.next_delay(&Method::GET, 1, &outcome), Some(Duration::from_secs(61)));
+    }
+}
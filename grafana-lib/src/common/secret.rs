@@ -0,0 +1,113 @@
+//! Secret Module
+//!
+//! Tokens and passwords are held in a [`Secret`], which never shows its value through
+//! `Debug`, `Display` or `Serialize`, so credentials do not leak into logs, traces or
+//! serialized clients. Code that needs the value asks for it with [`Secret::expose`].
+//! Reading back the placeholder a serialized secret was replaced with fails, so a client
+//! serialized and deserialized again does not send it as a credential.
+
+use std::fmt;
+
+use serde::{Serialize,Serializer,Deserialize,Deserializer};
+use serde::de::Error;
+
+/// Placeholder shown instead of a secret value
+const REDACTED : &str = "********";
+
+/// A token or password that redacts itself
+/// # Example
+/// ```
+/// # use grafana_lib::common::secret::Secret;
+/// let secret = Secret::new("glsa_XXXX");
+/// assert_eq!(format!("{secret:?}"), "Secret(********)");
+/// assert_eq!(secret.expose(), "glsa_XXXX");
+/// ```
+#[derive(Clone,Default,PartialEq,Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Wrap a secret value
+    pub fn new<S>(value : S) -> Secret
+    where S : Into<String>,
+    {
+        Secret(value.into())
+    }
+
+    /// The secret value, only to be used where it is actually sent or stored
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Is the value empty?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Serialize the actual value, for files that are meant to store the secret such as
+    /// the CLI configuration. Use with `#[serde(serialize_with = "Secret::serialize_exposed")]`.
+    pub fn serialize_exposed<S>(secret : &Secret, serializer : S) -> Result<S::Ok,S::Error>
+    where S : Serializer,
+    {
+        serializer.serialize_str(&secret.0)
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value : String) -> Self {
+        Secret(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value : &str) -> Self {
+        Secret(value.to_string())
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({REDACTED})")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{REDACTED}")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S>(&self, serializer : S) -> Result<S::Ok,S::Error>
+    where S : Serializer,
+    {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D>(deserializer : D) -> Result<Self,D::Error>
+    where D : Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        if value == REDACTED {
+            return Err(D::Error::custom("secret was redacted when serialized, set the credential again"));
+        }
+        Ok(Secret(value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_redacted() {
+        let secret = Secret::new("glsa_XXXX");
+        assert_eq!(secret.to_string(), REDACTED);
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"********\"");
+        let parsed : Secret = serde_json::from_str("\"glsa_XXXX\"").unwrap();
+        assert_eq!(parsed, secret);
+        let redacted = serde_json::to_string(&secret).unwrap();
+        assert!(serde_json::from_str::<Secret>(&redacted).is_err());
+    }
+}
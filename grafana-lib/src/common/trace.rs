@@ -0,0 +1,139 @@
+//! Trace Module
+//!
+//! With the `tracing` feature every API call runs inside a `grafana.request` span, so
+//! calls can be correlated with the caller's own spans and exported through
+//! OpenTelemetry. The span records:
+//!
+//! * `http.request.method` - the HTTP method
+//! * `http.route` - the path template, e.g. `/api/dashboards/uid/{uid}`
+//! * `grafana.org_id` - the organisation, when the call is scoped to one
+//! * `http.response.status_code` - the final status code
+//! * `latency_ms` - time taken including retries
+//! * `retries` - number of retries made
+//! * `error` - the error when no response was received
+//!
+//! Headers, query strings and bodies are never recorded, so credentials stay out of traces.
+//! Without the feature the span is a no-op.
+
+use std::future::Future;
+use std::time::{Duration,Instant};
+
+use crate::common::error::GrafanaError;
+use crate::common::transport::{Request,Response};
+
+/// Collections whose children are addressed by uid rather than a numeric id
+const UID_COLLECTIONS : [&str; 8] = [
+    "alert-rules",
+    "contact-points",
+    "folders",
+    "library-elements",
+    "mute-timings",
+    "playlists",
+    "snapshots",
+    "templates",
+];
+
+/// Path template of a request path, replacing identifiers with placeholders so spans and
+/// metrics can be grouped by endpoint
+/// # Example
+/// ```
+/// # use grafana_lib::common::trace::path_template;
+/// assert_eq!(path_template("/api/dashboards/uid/cIBgcSjkk"), "/api/dashboards/uid/{uid}");
+/// assert_eq!(path_template("/api/search?type=dash-db&query=home"), "/api/search");
+/// assert_eq!(path_template("/api/datasources/12"), "/api/datasources/{id}");
+/// ```
+pub fn path_template(path : &str) -> String {
+    let path = path.split('?').next().unwrap_or_default();
+    let mut previous = "";
+    let segments : Vec<&str> = path.split('/').map(|segment| {
+        let template = match previous {
+            _ if segment.is_empty() => segment,
+            "uid" => "{uid}",
+            "name" => "{name}",
+            _ if segment.bytes().all(|b| b.is_ascii_digit()) => "{id}",
+            p if UID_COLLECTIONS.contains(&p) => "{uid}",
+            _ => segment,
+        };
+        previous = segment;
+        template
+    }).collect();
+    segments.join("/")
+}
+
+/// Span covering one API call, including its retries
+pub(crate) struct CallSpan {
+    #[cfg(feature = "tracing")]
+    span : tracing::Span,
+    started : Instant,
+}
+
+impl CallSpan {
+    /// Open a span for a request
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn new(request : &Request, org_id : Option<u64>) -> CallSpan {
+        CallSpan {
+            #[cfg(feature = "tracing")]
+            span : tracing::info_span!(
+                "grafana.request",
+                http.request.method = %request.method,
+                http.route = %path_template(request.path()),
+                grafana.org_id = org_id,
+                http.response.status_code = tracing::field::Empty,
+                latency_ms = tracing::field::Empty,
+                retries = tracing::field::Empty,
+                error = tracing::field::Empty,
+            ),
+            started : Instant::now(),
+        }
+    }
+
+    /// Run a blocking call inside the span
+    pub(crate) fn in_scope<F,R>(&self, f : F) -> R
+    where F : FnOnce() -> R,
+    {
+        #[cfg(feature = "tracing")]
+        return self.span.in_scope(f);
+        #[cfg(not(feature = "tracing"))]
+        f()
+    }
+
+    /// Run a future inside the span
+    pub(crate) fn instrument<F>(&self, future : F) -> impl Future<Output = F::Output>
+    where F : Future,
+    {
+        #[cfg(feature = "tracing")]
+        return tracing::Instrument::instrument(future, self.span.clone());
+        #[cfg(not(feature = "tracing"))]
+        future
+    }
+
+    /// Record the outcome of the call and close the span, returning the time taken
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn finish(self, result : &Result<Response,GrafanaError>, retries : u32) -> Duration {
+        let latency = self.started.elapsed();
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("latency_ms", latency.as_millis() as u64);
+            self.span.record("retries", retries);
+            match result {
+                Ok(r) => { self.span.record("http.response.status_code", r.status); },
+                Err(e) => { self.span.record("error", tracing::field::display(e)); },
+            }
+        }
+        latency
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_path_template() {
+        assert_eq!(path_template("/api/datasources/name/my%20prom"), "/api/datasources/name/{name}");
+        assert_eq!(path_template("/api/folders/ops/permissions"), "/api/folders/{uid}/permissions");
+        assert_eq!(path_template("/api/v1/provisioning/alert-rules/abc"), "/api/v1/provisioning/alert-rules/{uid}");
+        assert_eq!(path_template("/api/dashboards/db"), "/api/dashboards/db");
+        assert_eq!(path_template("/api/orgs/2/users/"), "/api/orgs/{id}/users/");
+    }
+}
@@ -0,0 +1,232 @@
+//! Transport Module
+//!
+//! [`crate::common::api::Api`] builds a transport-neutral [`Request`] and hands it to a
+//! [`Transport`], which sends it and returns the status, headers and body. The default
+//! [`ReqwestTransport`] uses reqwest with the TLS, proxy and timeout settings from a
+//! [`Config`]; implement the trait to plug in another HTTP stack.
+
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc,OnceLock};
+
+use log::warn;
+use reqwest::Method;
+use reqwest::header::{HeaderMap,HeaderName,HeaderValue,CONTENT_TYPE};
+use serde::Serialize;
+
+use crate::common::config::Config;
+use crate::common::error::GrafanaError;
+
+/// Boxed future returned by [`Transport::send_async`]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// An HTTP request ready to be sent
+#[derive(Debug,Clone)]
+pub struct Request {
+    /// HTTP method
+    pub method : Method,
+    /// Absolute URL including the query string
+    pub url : String,
+    /// Headers, including credentials
+    pub headers : HeaderMap,
+    /// Serialized body, if any
+    pub body : Option<Vec<u8>>,
+}
+
+impl Request {
+    /// Create a request without headers or body
+    pub fn new(method : Method, url : String) -> Request {
+        Request { method, url, headers : HeaderMap::new(), body : None }
+    }
+
+    /// Serialize a body as JSON, setting the content type
+    pub fn with_json<B>(mut self, body : &B) -> Result<Request,GrafanaError>
+    where B : Serialize + ?Sized,
+    {
+        let body = serde_json::to_vec(body).map_err(|e| GrafanaError::Other(format!("could not serialize request : {e}")))?;
+        self.headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        self.body = Some(body);
+        Ok(self)
+    }
+
+    /// Path and query of the URL, e.g. `/api/search?type=dash-db`
+    pub fn path(&self) -> &str {
+        let rest = self.url.split_once("://").map(|(_,r)| r).unwrap_or(&self.url);
+        rest.find('/').map(|i| &rest[i..]).unwrap_or("/")
+    }
+
+    /// Look up a header value
+    pub fn header(&self, name : &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+}
+
+/// Response to a request
+#[derive(Debug,Clone,Default)]
+pub struct Response {
+    /// HTTP status code
+    pub status : u16,
+    /// Response headers
+    pub headers : HeaderMap,
+    /// Response body
+    pub body : String,
+}
+
+impl Response {
+    /// Create a response with a status and body
+    pub fn new(status : u16, body : &str) -> Response {
+        Response { status, headers : HeaderMap::new(), body : body.to_string() }
+    }
+
+    /// Create a 200 response with a JSON body
+    pub fn json(body : &str) -> Response {
+        Response::new(200, body).with_header(CONTENT_TYPE, "application/json")
+    }
+
+    /// Add a header, ignoring values that are not valid header text
+    pub fn with_header(mut self, name : HeaderName, value : &str) -> Response {
+        match HeaderValue::from_str(value) {
+            Ok(v) => { self.headers.insert(name, v); },
+            Err(_) => warn!("Ignoring invalid value for header {name}"),
+        }
+        self
+    }
+}
+
+/// Sends requests to Grafana
+///
+/// Implementations must be cheap to share between threads. Errors that prevent a response
+/// being received should be reported as [`GrafanaError::Transport`], which makes them
+/// eligible for retries.
+pub trait Transport : Debug + Send + Sync {
+    /// Send a request, blocking until the response has been read
+    fn send(&self, request : Request) -> Result<Response,GrafanaError>;
+
+    /// Send a request asynchronously. The default implementation calls [`Transport::send`]
+    /// and blocks the calling task, which is only suitable for in-memory transports.
+    fn send_async(&self, request : Request) -> BoxFuture<'_, Result<Response,GrafanaError>> {
+        let response = self.send(request);
+        Box::pin(async move { response })
+    }
+}
+
+impl<T> Transport for Arc<T>
+where T : Transport + ?Sized,
+{
+    fn send(&self, request : Request) -> Result<Response,GrafanaError> {
+        self.as_ref().send(request)
+    }
+
+    fn send_async(&self, request : Request) -> BoxFuture<'_, Result<Response,GrafanaError>> {
+        self.as_ref().send_async(request)
+    }
+}
+
+/// Transport built on reqwest, the default
+///
+/// Each client is created on first use, so a purely async user never builds the blocking
+/// client (which cannot be created or dropped inside an async runtime). Clones share the
+/// same connection pools.
+#[derive(Debug,Clone,Default)]
+pub struct ReqwestTransport {
+    config : Arc<Config>,
+    blocking : Arc<OnceLock<Result<reqwest::blocking::Client,String>>>,
+    asynchronous : Arc<OnceLock<Result<reqwest::Client,String>>>,
+}
+
+/// Build either flavour of reqwest client with the transport settings from a [`Config`]
+macro_rules! configure {
+    ($builder:expr, $config:expr) => {{
+        let config : &Config = $config;
+        let tls = config.tls()?;
+        let mut builder = $builder.user_agent(config.user_agent());
+        if let Some(proxy) = config.proxy()? {
+            builder = builder.proxy(proxy);
+        }
+        if let Some(t) = config.connect_timeout() {
+            builder = builder.connect_timeout(t);
+        }
+        if let Some(t) = config.timeout() {
+            builder = builder.timeout(t);
+        }
+        for root in tls.roots {
+            builder = builder.add_root_certificate(root);
+        }
+        if let Some(identity) = tls.identity {
+            builder = builder.identity(identity);
+        }
+        if tls.insecure {
+            warn!("TLS verification disabled for {}",config.url());
+            builder = builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
+        builder.build().map_err(|e| GrafanaError::Config(e.to_string()))
+    }};
+}
+
+impl ReqwestTransport {
+    /// Create a transport using the TLS, proxy, timeout and user agent settings of a configuration
+    pub fn new(config : Config) -> ReqwestTransport {
+        ReqwestTransport { config : Arc::new(config), ..Default::default() }
+    }
+
+    fn blocking(&self) -> Result<&reqwest::blocking::Client,GrafanaError> {
+        self.blocking
+            .get_or_init(|| build_blocking(&self.config).map_err(config_message))
+            .as_ref()
+            .map_err(|e| GrafanaError::Config(e.clone()))
+    }
+
+    fn asynchronous(&self) -> Result<&reqwest::Client,GrafanaError> {
+        self.asynchronous
+            .get_or_init(|| build_async(&self.config).map_err(config_message))
+            .as_ref()
+            .map_err(|e| GrafanaError::Config(e.clone()))
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request : Request) -> Result<Response,GrafanaError> {
+        let mut builder = self.blocking()?.request(request.method, request.url).headers(request.headers);
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let response = builder.send()?;
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        Ok(Response { status, headers, body : response.text()? })
+    }
+
+    fn send_async(&self, request : Request) -> BoxFuture<'_, Result<Response,GrafanaError>> {
+        Box::pin(async move {
+            let mut builder = self.asynchronous()?.request(request.method, request.url).headers(request.headers);
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+            let response = builder.send().await?;
+            let status = response.status().as_u16();
+            let headers = response.headers().clone();
+            Ok(Response { status, headers, body : response.text().await? })
+        })
+    }
+}
+
+/// Build the blocking reqwest client for a configuration
+fn build_blocking(config : &Config) -> Result<reqwest::blocking::Client,GrafanaError> {
+    configure!(reqwest::blocking::Client::builder(), config)
+}
+
+/// Build the async reqwest client for a configuration
+fn build_async(config : &Config) -> Result<reqwest::Client,GrafanaError> {
+    configure!(reqwest::Client::builder(), config)
+}
+
+/// Keep the message of a configuration error so it can be reported on every request
+fn config_message(e : GrafanaError) -> String {
+    match e {
+        GrafanaError::Config(m) => m,
+        e => e.to_string(),
+    }
+}
@@ -2,4 +2,5 @@
 //! 
 
 /// Struct for calling Admin API
+#[derive(Debug,Clone,Default)]
 pub struct Admin {}
\ No newline at end of file
@@ -1,6 +1,7 @@
 //! Alerting Provisioning Module
 //! 
 use std::default::Default;
+use std::fmt;
 use log::debug;
 use crate::common::api::Api;
 use crate::common::error::GrafanaError;
@@ -10,6 +11,60 @@ const ALERT_PROVISIONING_PATH : &str = "v1/provisioning";
 const ALERT_RULES_PATH : &str = "alert-rules";
 const ALERT_CONTACT_PATH : &str = "contact-points";
 
+/// Path to a provisioning resource
+fn provisioning_path(resource : &str) -> String {
+    let path = format!("{}/{}",ALERT_PROVISIONING_PATH,resource);
+    debug!("Fetching {}",&path);
+    path
+}
+
+/// Alert rule as returned by the provisioning API
+#[derive(Debug,Default,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRuleModel {
+    /// Unique Id of the rule
+    pub uid : Option<String>,
+    /// Title of the rule
+    pub title : String,
+    /// Folder containing the rule
+    #[serde(rename = "folderUID")]
+    pub folder_uid : Option<String>,
+    /// Rule group the rule is evaluated in
+    pub rule_group : Option<String>,
+}
+
+impl fmt::Display for AlertRuleModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}\t[uid={}, group={}]",
+            self.title,
+            self.uid.clone().unwrap_or_default(),
+            self.rule_group.clone().unwrap_or_default(),
+        )
+    }
+}
+
+/// Contact point as returned by the provisioning API
+#[derive(Debug,Default,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactPointModel {
+    /// Unique Id of the contact point
+    pub uid : Option<String>,
+    /// Name of the contact point
+    pub name : String,
+    /// Integration type, e.g. email or slack
+    pub r#type : String,
+}
+
+impl fmt::Display for ContactPointModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}\t[uid={}, type={}]",
+            self.name,
+            self.uid.clone().unwrap_or_default(),
+            self.r#type,
+        )
+    }
+}
+
 /// Alert Rule Model
 #[derive(Debug,Default,Serialize,Deserialize)]
 pub struct AlertRule {
@@ -24,17 +79,14 @@ impl AlertRule {
         AlertRule { api, title : None }
     }
     /// Generate list of Alert Rules for alerting
-    pub fn list(&self) -> Result<String,GrafanaError> {
+    pub fn list(&self) -> Result<Vec<AlertRuleModel>,GrafanaError> {
         // Genereate API call and collect the results
-        let path = format!("{}/{}",ALERT_PROVISIONING_PATH,ALERT_RULES_PATH);
-        debug!("Fetching alert rules: {}",&path);
-        let body = self.api.get(path).expect("Could not get response body");
-        let result = serde_json::from_str(body.as_str());
-       
-        match result {
-            Ok(r) => Ok(r),
-            Err(e) => Err(GrafanaError::new(e.to_string(), String::from("-1"))),
-        }
+        self.api.get_json(&provisioning_path(ALERT_RULES_PATH))
+    }
+
+    /// Generate list of Alert Rules for alerting using async
+    pub async fn list_async(&self) -> Result<Vec<AlertRuleModel>,GrafanaError> {
+        self.api.get_json_async(&provisioning_path(ALERT_RULES_PATH)).await
     }
 
     /// Create an instance of AlertRule
@@ -44,10 +96,10 @@ impl AlertRule {
     }
 
     /// Send instance of Alert Rule to Grafana
-    pub fn build(&self) -> Result<String,String> {
-        let body = serde_json::to_string(self).unwrap();
+    pub fn build(&self) -> Result<String,GrafanaError> {
+        let body = serde_json::to_string(self).map_err(|e| GrafanaError::Other(e.to_string()))?;
         debug!("BODY: {}",body);
-        Err(String::from("Not implemented"))
+        Err(GrafanaError::Other(String::from("Not implemented")))
     }
 }
 
@@ -63,17 +115,14 @@ impl ContactPoint {
         ContactPoint { api }
     }
     /// Generate list of Contact Ponits defined in the Alerting module
-    pub fn list(&self) -> Result<String,GrafanaError> {
+    pub fn list(&self) -> Result<Vec<ContactPointModel>,GrafanaError> {
         // Generate API call
-        let path = format!("{}/{}",ALERT_PROVISIONING_PATH,ALERT_CONTACT_PATH);
-        debug!("Fetching alert rules: {}",&path);
-        let body = self.api.get(path).expect("Could not get response body");
-        let result = serde_json::from_str(body.as_str());
-       
-        match result {
-            Ok(r) => Ok(r),
-            Err(e) => Err(GrafanaError::new(e.to_string(), String::from("-1"))),
-        }
+        self.api.get_json(&provisioning_path(ALERT_CONTACT_PATH))
+    }
+
+    /// Generate list of Contact Points using async
+    pub async fn list_async(&self) -> Result<Vec<ContactPointModel>,GrafanaError> {
+        self.api.get_json_async(&provisioning_path(ALERT_CONTACT_PATH)).await
     }
 }
 /// Notification Policy Model
@@ -161,24 +210,12 @@ pub struct AlertProvisioningModel {
 
 impl AlertProvisioningModel {
     /// Create new instance of AlertRule model
-    pub fn alert_rule(mut self, api : Api) -> AlertRule {
-        match self.alert_rule {
-            Some(ar) => ar,
-            None => {
-                self.alert_rule = Some(AlertRule::new(api));
-                self.alert_rule.unwrap()
-            }
-        }
+    pub fn alert_rule(self, api : Api) -> AlertRule {
+        self.alert_rule.unwrap_or_else(|| AlertRule::new(api))
     }
     /// Create new instance of ContactPoint model
-    pub fn contact_point(mut self, api : Api) -> ContactPoint {
-        match self.contact_point {
-            Some(cp) => cp,
-            None => {
-                self.contact_point = Some(ContactPoint::new(api));
-                self.contact_point.unwrap()
-            }
-        }
+    pub fn contact_point(self, api : Api) -> ContactPoint {
+        self.contact_point.unwrap_or_else(|| ContactPoint::new(api))
     }
 }
 
@@ -202,10 +239,10 @@ impl AlertingProvisioning {
     }
     /// Return instance of AlertRule model
     pub fn alert_rule(self) -> AlertRule {
-        self.model.unwrap().alert_rule(self.api)
+        self.model.unwrap_or_default().alert_rule(self.api)
     }
     /// Return instance of ContactPoint model
     pub fn contact_point(self) -> ContactPoint {
-        self.model.unwrap().contact_point(self.api)
+        self.model.unwrap_or_default().contact_point(self.api)
     }
 }
\ No newline at end of file
@@ -30,8 +30,18 @@ impl fmt::Display for AnnotationsModel {
     }
 }
 
-/// Annotations Structure
+/// Build annotation list path with optional filters
+fn list_path(limit : Option<u16>, dashboard_id : Option<u16>) -> String {
+    let limit = limit.unwrap_or(ANNOTATION_LIMIT);
+    let mut path = format!("{}?limit={}",ANNOTATION_PATH,limit);
+    // Add options
+    if let Some(id) = dashboard_id {
+        path.push_str(format!("&dashboardId={}",id).as_str())
+    }
+    path
+}
 
+/// Annotations Structure
 pub struct Annotations {
     api : Api,
     models : Option<Vec<AnnotationsModel>>,
@@ -68,18 +78,13 @@ impl Annotations {
     pub fn list(&self,
             limit : Option<u16>,
             dashboard_id : Option<u16>) -> Result<Vec<AnnotationsModel>,GrafanaError> {
-        let limit = limit.unwrap_or(ANNOTATION_LIMIT);
-        let mut path = format!("{}?limit={}",ANNOTATION_PATH,limit);
-        // Add options
-        if dashboard_id.is_some() {
-            path.push_str(format!("&dashboardId={}",dashboard_id.unwrap()).as_str())
-        }
-        match self.api.get(path) {
-            Ok(r) => {
-                let result : Vec<AnnotationsModel> = serde_json::from_str(r.as_str()).unwrap();
-                Ok(result)
-            },
-            Err(e) => Err(GrafanaError::new(e,String::from("ERROR")))
-        }
+        self.api.get_json(&list_path(limit, dashboard_id))
+    }
+
+    /// Get a list of annotations using async
+    pub async fn list_async(&self,
+            limit : Option<u16>,
+            dashboard_id : Option<u16>) -> Result<Vec<AnnotationsModel>,GrafanaError> {
+        self.api.get_json_async(&list_path(limit, dashboard_id)).await
     }
 }
\ No newline at end of file
@@ -15,6 +15,7 @@ pub struct AuthenticationKey {
 }
 
 /// Authentication Structure
+#[derive(Debug,Clone,Default)]
 pub struct Authentication {}
 
 impl Authentication {
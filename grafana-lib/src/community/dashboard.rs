@@ -2,36 +2,35 @@
 //! 
 use crate::common::error::GrafanaError;
 use crate::common::api::Api;
+use crate::common::retry::RetryPolicy;
 
-use serde::Deserialize;
+use crate::common::lossless::lossless;
+use crate::community::layout::{self,Item,LayoutIssue};
+use crate::community::panel::number_panels;
+
+use serde::{Serialize,Deserialize};
+use serde_json::{Map,Value};
 use std::fmt;
 
 const DASHBOARD_PATH : &str = "dashboards";
 const DASHBOARD_UID_PATH : &str = "uid";
+const DASHBOARD_DB_PATH : &str = "dashboards/db";
 
-/// Panel Model
-#[derive(PartialEq,Debug,Deserialize)]
-pub struct PanelModel {
-    title   : String,
-    id  : u16,
-    r#type : String,
-}
-
-impl fmt::Display for PanelModel {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut output = String::default();
-        output.push_str(format!("Title\t: {} [{}] - {}",
-            self.title.clone(),
-            self.id,
-            self.r#type,
-        ).as_str());
-        writeln!(f, "{output}")
-    }
-}
+pub use crate::community::panel::{PanelBuilder,PanelModel};
 
-/// Complete Dashboard Model
-#[derive(PartialEq,Debug,Deserialize)]
-#[serde(rename_all = "camelCase")]
+/// Complete Dashboard Model. Only the commonly used fields are typed, everything else
+/// (templating, annotations, links, ...) is kept in [`DashboardModel::extra`] so a dashboard
+/// read from Grafana is written back unchanged apart from the edits made to it.
+/// # Example
+/// ```
+/// # use grafana_lib::community::dashboard::DashboardModel;
+/// let json = serde_json::json!({ "uid" : "home", "title" : "Home", "id" : null, "templating" : { "list" : [] } });
+/// let mut model : DashboardModel = serde_json::from_value(json.clone()).unwrap();
+/// assert_eq!(serde_json::to_value(&model).unwrap(), json);
+/// model.title = Some(String::from("Start"));
+/// assert_eq!(serde_json::to_value(&model).unwrap()["templating"], json["templating"]);
+/// ```
+#[derive(Debug,Clone)]
 pub struct DashboardModel {
     id : Option<u32>,
     /// Unique Id of Dashboard, used in other queries
@@ -47,15 +46,47 @@ pub struct DashboardModel {
     refresh : Option<String>,
     /// Dashboard Version
     pub version : Option<u16>,
+    /// Fields this model does not type, kept so saving writes them back unchanged
+    pub extra : Map<String,Value>,
+}
+
+lossless!(DashboardModel {
+    id : "id",
+    uid : "uid",
+    panels : "panels",
+    title : "title",
+    tags : "tags",
+    timezone : "timezone",
+    schema_version : "schemaVersion",
+    refresh : "refresh",
+    version : "version",
+});
+
+impl DashboardModel {
+    /// Find panels that overlap, do not fit in the grid or share an id
+    /// # Example
+    /// ```
+    /// # use grafana_lib::community::dashboard::DashboardModel;
+    /// let json = serde_json::json!({ "panels" : [
+    ///     { "id" : 1, "title" : "CPU", "gridPos" : { "h" : 8, "w" : 12, "x" : 0, "y" : 0 } },
+    ///     { "id" : 2, "title" : "Memory", "gridPos" : { "h" : 8, "w" : 12, "x" : 6, "y" : 4 } },
+    /// ] });
+    /// let model : DashboardModel = serde_json::from_value(json).unwrap();
+    /// let issues : Vec<_> = model.validate_layout().iter().map(|i| i.to_string()).collect();
+    /// assert_eq!(issues, vec!["panel 'CPU' [1] overlaps panel 'Memory' [2]"]);
+    /// ```
+    pub fn validate_layout(&self) -> Vec<LayoutIssue<'_>> {
+        self.panels.as_deref().map(layout::validate).unwrap_or_default()
+    }
 }
 
 impl fmt::Display for DashboardModel {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut output = String::default();
         output.push_str(format!("Title\t: {}\n",self.title.clone().unwrap_or_default()).as_str());
-        output.push_str(format!("UID\t: {}\n",self.uid.clone().unwrap()).as_str());
-        output.push_str(format!("Version\t: {}\n",self.version.unwrap()).as_str());
-        output.push_str(format!("Schema\t: {}\n",self.schema_version.unwrap()).as_str());
+        output.push_str(format!("UID\t: {}\n",self.uid.clone().unwrap_or_default()).as_str());
+        output.push_str(format!("Version\t: {}\n",self.version.unwrap_or_default()).as_str());
+        output.push_str(format!("Schema\t: {}\n",self.schema_version.unwrap_or_default()).as_str());
         write!(f, "{output}" )
     }
 }
@@ -92,6 +123,53 @@ pub struct FullDashboardModel {
 }
 
 
+/// Result of saving a dashboard
+#[derive(Debug,Clone,PartialEq,Default,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedDashboardModel {
+    /// Numeric id of the dashboard
+    pub id : u64,
+    /// Unique id of the dashboard
+    pub uid : String,
+    /// Path of the dashboard in the Grafana UI, e.g. `/d/cIBgcSjkk/home`
+    pub url : String,
+    /// Version after saving
+    pub version : u64,
+    /// Status reported by Grafana, `success` when saved
+    pub status : String,
+    /// Slug of the title, used in the URL
+    #[serde(default)]
+    pub slug : Option<String>,
+}
+
+impl fmt::Display for SavedDashboardModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "UID\t: {}",self.uid)?;
+        writeln!(f, "URL\t: {}",self.url)?;
+        writeln!(f, "Version\t: {}",self.version)?;
+        writeln!(f, "Status\t: {}",self.status)
+    }
+}
+
+/// Body of a save request
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveDashboard<'a> {
+    dashboard : &'a DashboardModel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    folder_uid : Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    folder_id : Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message : Option<&'a str>,
+    overwrite : bool,
+}
+
+/// Path to a dashboard by UID
+fn uid_path(uid : &str) -> String {
+    format!("{}/{}/{}",DASHBOARD_PATH,DASHBOARD_UID_PATH,uid)
+}
+
 /// Dashboard API Structure
 #[derive(PartialEq,Debug,Default)]
 pub struct Dashboard {
@@ -111,7 +189,9 @@ impl Dashboard {
         Dashboard { api, dashboard: None, folder_id: None, folder_uid: None, message: None, overwrite: false }
     }
 
-    /// Create a new dashboard in Grafana
+    /// Create or update a dashboard in Grafana. A model without a uid, or with a uid that does
+    /// not exist yet, creates a dashboard, otherwise the dashboard is updated when the model's
+    /// version matches the stored one.
     /// # Examples
     /// ```
     /// # use grafana_lib::community::dashboard::{Dashboard,DashboardBuilder,PanelBuilder};
@@ -136,7 +216,7 @@ impl Dashboard {
         self
     }
 
-    /// Set folder Id
+    /// Set folder Id, deprecated by Grafana in favour of [`Dashboard::with_folder_uid`]
     pub fn with_folder_id(mut self,id : u16) -> Dashboard {
         self.folder_id = Some(id);
         self
@@ -148,54 +228,85 @@ impl Dashboard {
         self
     }
 
-    /// Set overwrite flag
+    /// Overwrite a dashboard changed by someone else, or with the same title in the folder
     pub fn with_overwrite(mut self, overwrite: bool) -> Dashboard {
         self.overwrite = overwrite;
         self
     }
 
-    /// Send Dashboard to Grafana
-    pub fn send(self) -> Result<Dashboard,GrafanaError> {
-        // Send current data to Grafana
-        
-        Ok(self)
+    /// Retry the save according to a policy. Saving is not idempotent, so it is only retried
+    /// when the policy explicitly includes POST.
+    pub fn with_retry(mut self, policy : RetryPolicy) -> Dashboard {
+        self.api = self.api.with_retry(policy);
+        self
     }
 
-    /// Get a dashboard by UID
-    pub fn get(&self, uid : String) -> Result<FullDashboardModel,GrafanaError> {
-        let path = format!("{}/{}/{}",DASHBOARD_PATH,DASHBOARD_UID_PATH,uid);
-        match self.api.get(path) {
-            Ok(r) => {
-                let result : FullDashboardModel = serde_json::from_str(r.as_str()).expect("Could not parse JSON");
-                Ok(result)
-            },
-            Err(e) => {
-                Err(GrafanaError::new(e,String::from("ERROR")))
-            }
-        }
-        
+    /// Save the dashboard. Fails with [`GrafanaError::VersionMismatch`] when it was changed
+    /// by someone else and [`GrafanaError::NameExists`] when another dashboard in the folder
+    /// has the same title, unless overwrite is set.
+    /// # Example
+    /// ```
+    /// # use grafana_lib::client::Client;
+    /// # use grafana_lib::common::auth::Auth;
+    /// # use grafana_lib::common::mock::MockTransport;
+    /// # use grafana_lib::common::transport::Response;
+    /// # use grafana_lib::community::dashboard::DashboardBuilder;
+    /// # use reqwest::Method;
+    /// # let mock = MockTransport::new();
+    /// # mock.on(Method::POST, "/api/dashboards/db", Response::json(r#"{"id":7,"uid":"abc","url":"/d/abc/cpu","version":1,"status":"success"}"#));
+    /// # let client = Client::builder("http://localhost:3000").with_auth(Auth::Anonymous).with_transport(mock).build().unwrap();
+    /// let saved = client.dashboard()
+    ///     .create(DashboardBuilder::new(String::from("CPU")).build())
+    ///     .with_folder_uid(String::from("ops"))
+    ///     .send()
+    ///     .unwrap();
+    /// assert_eq!(saved.url, "/d/abc/cpu");
+    /// ```
+    pub fn send(self) -> Result<SavedDashboardModel,GrafanaError> {
+        self.api.post_json(DASHBOARD_DB_PATH, &self.body()?)
     }
-}
 
-/// Builder for Panels
-pub struct PanelBuilder {
-    title : String,
-    r#type : String,
-}
+    /// Save the dashboard using async
+    pub async fn send_async(self) -> Result<SavedDashboardModel,GrafanaError> {
+        self.api.post_json_async(DASHBOARD_DB_PATH, &self.body()?).await
+    }
 
-impl PanelBuilder {
-    /// Create a new PanelBuilder object
-    pub fn new(title : String) -> PanelBuilder {
-        PanelBuilder { title, r#type : String::default() } 
+    /// Body of the save request
+    fn body(&self) -> Result<SaveDashboard<'_>,GrafanaError> {
+        let dashboard = self.dashboard.as_ref()
+            .ok_or(GrafanaError::Other(String::from("no dashboard to send, set one with create")))?;
+        Ok(SaveDashboard {
+            dashboard,
+            folder_uid : self.folder_uid.as_deref(),
+            folder_id : self.folder_id,
+            message : self.message.as_deref(),
+            overwrite : self.overwrite,
+        })
     }
-    /// Set type of panel
-    pub fn with_type(mut self, r#type : String) -> PanelBuilder {
-        self.r#type = r#type;
-        self
+
+    /// Get a dashboard by UID
+    /// # Example
+    /// ```
+    /// # use grafana_lib::client::Client;
+    /// # use grafana_lib::common::auth::Auth;
+    /// # use grafana_lib::common::cassette::Cassette;
+    /// # use grafana_lib::common::error::GrafanaError;
+    /// # let cassette = Cassette::replay(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/dashboard.json")).unwrap();
+    /// # let client = Client::builder("http://localhost:3000").with_auth(Auth::Anonymous).with_transport(cassette).build().unwrap();
+    /// let home = client.dashboard().get(String::from("cIBgcSjkk")).unwrap();
+    /// assert_eq!(home.dashboard.title.as_deref(), Some("Home"));
+    /// assert_eq!(home.meta.folder_title.as_deref(), Some("Operations"));
+    ///
+    /// let missing = client.dashboard().get(String::from("missing"));
+    /// assert!(matches!(missing, Err(GrafanaError::NotFound(_))));
+    /// ```
+    pub fn get(&self, uid : String) -> Result<FullDashboardModel,GrafanaError> {
+        self.api.get_json(&uid_path(&uid))
     }
-    /// Build a Panel
-    pub fn build(self) -> PanelModel {
-        PanelModel { title : self.title, id : 0, r#type : self.r#type}
+
+    /// Get a dashboard by UID using async
+    pub async fn get_async(&self, uid : String) -> Result<FullDashboardModel,GrafanaError> {
+        self.api.get_json_async(&uid_path(&uid)).await
     }
 }
 
@@ -205,6 +316,7 @@ pub struct DashboardBuilder {
     id : Option<u32>,
     uid : Option<String>,
     panels : Option<Vec<PanelModel>>,
+    layout : Vec<Item>,
     title : String,
     tags : Option<Vec<String>>,
     timezone : Option<String>,
@@ -224,6 +336,7 @@ impl DashboardBuilder {
             id : None,
             uid : None,
             panels : None,
+            layout : Vec::new(),
             title,
             tags : None,
             timezone : None,
@@ -248,30 +361,80 @@ impl DashboardBuilder {
         self
     }
 
-    /// Add panel models
+    /// Add panel models, panels without an id are given one when the dashboard is built
     /// # Examples
     /// ```
     /// # use grafana_lib::community::dashboard::{DashboardBuilder,PanelBuilder};
     /// # use grafana_lib::client::Client;
-    /// # let client = Client::new(String::from("http://localhost:3000"));
+    /// # use grafana_lib::common::auth::Auth;
+    /// # let server = grafana_mock::MockServer::start();
+    /// # server.add_folder("ops", "Operations");
+    /// # let client = Client::builder(&server.url()).with_auth(Auth::Anonymous).build().unwrap();
     /// let panel = PanelBuilder::new(String::from("MyPanel"))
     ///     .build();
     /// let panel_vec = vec![panel];
     /// let model = DashboardBuilder::new(String::from("MyDashboard"))
     ///     .with_panels(panel_vec)
     ///     .build();
-    /// let _output = client.dashboard()
+    /// let output = client.dashboard()
     ///     .create(model)
     ///     .with_message(String::from("New Dashboard via CLI"))
-    ///     .with_folder_id(6)
-    ///     .send();
+    ///     .with_folder_uid(String::from("ops"))
+    ///     .send()
+    ///     .unwrap();
+    /// assert_eq!(output.version, 1);
     /// ```
     pub fn with_panels(mut self, panels : Vec<PanelModel>) -> DashboardBuilder {
         self.panels = Some(panels);
         self
     }
 
-    /// Build the Dashboard
+    /// Add a panel at the highest free position, leftmost first, keeping the size of its
+    /// `gridPos` or 12 columns by 8 lines
+    /// # Examples
+    /// ```
+    /// # use grafana_lib::community::dashboard::DashboardBuilder;
+    /// # use grafana_lib::community::panel::{GridPos,PanelBuilder};
+    /// let model = DashboardBuilder::new(String::from("Nodes"))
+    ///     .with_panel(PanelBuilder::new(String::from("CPU")).with_grid_pos(GridPos::new(0, 0, 8, 10)).build())
+    ///     .with_panel(PanelBuilder::new(String::from("Memory")).build())
+    ///     .with_panel(PanelBuilder::new(String::from("Disk")).build())
+    ///     .with_row(String::from("Network"))
+    ///     .with_columns(6, vec![(16, PanelBuilder::new(String::from("Traffic")).build()), (8, PanelBuilder::new(String::from("Errors")).build())])
+    ///     .with_collapsed_row(String::from("Details"), vec![PanelBuilder::new(String::from("Sockets")).build()])
+    ///     .build();
+    /// let panels = model.panels.as_deref().unwrap();
+    /// let at : Vec<_> = panels.iter().map(|p| p.grid_pos.as_ref().map(|g| (g.x, g.y))).collect();
+    /// assert_eq!(at, vec![Some((0, 0)), Some((8, 0)), Some((8, 8)), Some((0, 16)), Some((0, 17)), Some((16, 17)), Some((0, 23))]);
+    /// assert!(model.validate_layout().is_empty());
+    /// ```
+    pub fn with_panel(mut self, panel : PanelModel) -> DashboardBuilder {
+        self.layout.push(Item::Panel(Box::new(panel)));
+        self
+    }
+
+    /// Add panels side by side on a new line, each with a width out of the 24 columns,
+    /// wrapping to the next line when they do not fit
+    pub fn with_columns(mut self, height : u32, panels : Vec<(u32,PanelModel)>) -> DashboardBuilder {
+        self.layout.push(Item::Columns(height, panels));
+        self
+    }
+
+    /// Add a row below all panels, panels added after it are placed in the row
+    pub fn with_row(mut self, title : String) -> DashboardBuilder {
+        self.layout.push(Item::Row(title));
+        self
+    }
+
+    /// Add a collapsed row below all panels, holding panels laid out as when it is expanded
+    pub fn with_collapsed_row(mut self, title : String, panels : Vec<PanelModel>) -> DashboardBuilder {
+        self.layout.push(Item::CollapsedRow(title, panels));
+        self
+    }
+
+    /// Build the Dashboard. Panels added with [`DashboardBuilder::with_panels`] keep their
+    /// position, the others are placed below, and panels without an id or with the id of a
+    /// panel before them are given a new one.
     /// # Examples
     /// ```
     /// # use grafana_lib::community::dashboard::DashboardBuilder;
@@ -279,17 +442,25 @@ impl DashboardBuilder {
     /// let model = DashboardBuilder::new(String::from("MyDashboard"))
     ///     .build();
     /// ```
-    pub fn build(self) -> DashboardModel {
+    pub fn build(mut self) -> DashboardModel {
+        if self.panels.is_some() || !self.layout.is_empty() {
+            let panels = self.panels.take().unwrap_or_default();
+            self.panels = Some(layout::arrange(panels, self.layout));
+        }
+        if let Some(panels) = self.panels.as_mut() {
+            number_panels(panels);
+        }
         DashboardModel {
             id : self.id,
             uid : self.uid,
-            panels : None,
+            panels : self.panels,
             title : Some(self.title),
             tags : None,
             timezone : self.timezone,
             schema_version : Some(self.schema_version),
             refresh : self.refresh,
             version : Some(self.version),
+            extra : Map::new(),
         }
     }
 }
@@ -311,6 +482,7 @@ mod test {
             tags : None,
             refresh : None,
             version : Some(0),
+            extra : Map::new(),
         };
         assert_eq!(dashboard,test_dashboard);
     }
@@ -329,8 +501,35 @@ mod test {
             tags : None,
             refresh : None,
             version: Some(0),
+            extra : Map::new(),
         };
         assert_eq!(dashboard,test_dashboard);
     }
+    #[test]
+    fn test_dashboard_with_panels() {
+        let dashboard = DashboardBuilder::new("test".to_string())
+            .with_panels(vec![
+                PanelBuilder::new("first".to_string()).build(),
+                PanelBuilder::new("second".to_string()).build(),
+            ])
+            .build();
+        let ids : Vec<_> = dashboard.panels.iter().flatten().map(|p| p.id).collect();
+        assert_eq!(ids, vec![Some(1), Some(2)]);
+    }
+    #[test]
+    fn test_dashboard_duplicate_ids() {
+        let dashboard = DashboardBuilder::new("test".to_string())
+            .with_panels(vec![
+                PanelBuilder::new("first".to_string()).with_id(3).build(),
+                PanelBuilder::new("second".to_string()).with_id(3).build(),
+            ])
+            .with_collapsed_row("Row".to_string(), vec![PanelBuilder::new("nested".to_string()).with_id(3).build()])
+            .build();
+        let panels = dashboard.panels.as_deref().unwrap();
+        let ids : Vec<_> = panels.iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![Some(3), Some(4), Some(5)]);
+        assert_eq!(panels[2].panels.as_ref().unwrap()[0].id, Some(6));
+        assert_eq!(dashboard.validate_layout(), vec![]);
+    }
 
 }
\ No newline at end of file
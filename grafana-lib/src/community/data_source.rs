@@ -8,6 +8,7 @@
 //! The uid can have a maximum length of 40 characters.
 //use log::debug;
 use crate::common::api::Api;
+use crate::common::error::GrafanaError;
 use serde::Deserialize;
 
 const DATASOURCE_PATH : &str= "datasources";
@@ -85,12 +86,12 @@ impl DataSource {
     /// # Example
     /// ```
     /// # use grafana_lib::community::data_source::DataSource;
-    /// let datasource = DataSource::new(
-    ///     String::from("http://localhost:3000"),
-    ///     String::from("TOKEN"));
+    /// # use grafana_lib::common::api::Api;
+    /// # use grafana_lib::common::auth::Auth;
+    /// let api = Api::new(String::from("http://localhost:3000"),Auth::bearer(String::from("TOKEN")));
+    /// let datasource = DataSource::new(api);
     /// ```
-    pub fn new(host : String, token : String) -> DataSource {
-        let api = Api::new(host,token);
+    pub fn new(api : Api) -> DataSource {
         DataSource { 
             api,
             model : None
@@ -98,31 +99,26 @@ impl DataSource {
     }
 
     /// List data sources
-    pub fn get(&self, _name : Option<String>) -> Result<Vec<DataSourceModel>,String> {
-        let path = DATASOURCE_PATH.to_string();
-        let body = self.api.get(path).unwrap();
-        let result = serde_json::from_str(body.as_str()); 
-        match result {
-            Ok(r) => {
-                Ok(r)
-            },
-            Err(e) => Err(e.to_string())
-        }
-        
+    pub fn get(&self, _name : Option<String>) -> Result<Vec<DataSourceModel>,GrafanaError> {
+        self.api.get_json(DATASOURCE_PATH)
+    }
+
+    /// List data sources using async
+    pub async fn get_async(&self, _name : Option<String>) -> Result<Vec<DataSourceModel>,GrafanaError> {
+        self.api.get_json_async(DATASOURCE_PATH).await
     }
     /// Create a new dashboard, can fail if there is a conflict in the data, e.g. folder_id vs folder_uid
     /// # Example
     /// ```
     /// # use grafana_lib::community::data_source::{DataSource,DataSourceBuilder};
-    /// # let datasource = DataSource::new(
-    ///     String::from("http://localhost:3000"),
-    ///     String::from("TOKEN"));
+    /// # use grafana_lib::common::api::Api;
+    /// # let datasource = DataSource::new(Api::default());
     /// let model = DataSourceBuilder::new(String::from("MyDataSource"))
     ///     .build();
     /// let result = datasource
     ///     .create(model);
     /// ```
-    pub fn create(mut self, model : DataSourceModel) -> Result<DataSource,String> {
+    pub fn create(mut self, model : DataSourceModel) -> Result<DataSource,GrafanaError> {
         self.model = Some(model);
         Ok(self)
     }
@@ -131,9 +127,8 @@ impl DataSource {
     /// # Example 
     /// ```
     /// # use grafana_lib::community::data_source::{DataSource,DataSourceBuilder};
-    /// # let datasource = DataSource::new(
-    ///     String::from("http://localhost:3000"),
-    ///     String::from("TOKEN"));
+    /// # use grafana_lib::common::api::Api;
+    /// # let datasource = DataSource::new(Api::default());
     /// let model = DataSourceBuilder::new(String::from("MyDataSource"))
     ///     .build();
     /// let result = datasource
@@ -141,8 +136,8 @@ impl DataSource {
     ///     .expect("Could not create Dashboard instance")
     ///     .send();
     /// ```
-    pub fn send(&self) -> Result<String,String> {
+    pub fn send(&self) -> Result<String,GrafanaError> {
         // Send data source to Grafana
-        Err(String::from("datasource.send() - Not implemented"))
+        Err(GrafanaError::Other(String::from("datasource.send() - Not implemented")))
     }
 }
\ No newline at end of file
@@ -1,10 +1,15 @@
 //! Folder Module
-//! 
+//!
+use crate::common::api::Api;
+use crate::common::error::GrafanaError;
+
 use log::info;
-use serde::Deserialize;
+use serde::{Serialize,Deserialize};
+
+const FOLDERS_PATH : &str = "folders";
 
 /// Data model for a folder
-#[derive(Debug,Default,Deserialize)]
+#[derive(Debug,Clone,PartialEq,Default,Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FolderModel {
     /// Numerical Id
@@ -20,48 +25,149 @@ pub struct FolderModel {
     /// Optional Tag array
     pub tags    : Option<Vec<String>>,
     /// Is this folder starred?
+    #[serde(default)]
     pub is_starred  : bool,
-    /// Legacy URI 
+    /// Legacy URI
     pub uri     : Option<String>,
+    /// Parent folder, for nested folders
+    pub parent_uid : Option<String>,
+    /// Folder version, used to detect changes made by someone else when updating
+    pub version : Option<u64>,
 }
 
 impl FolderModel {
     /// Create empty FolderModel instance
     pub fn new(title : String) -> FolderModel {
-        FolderModel { 
+        FolderModel {
             id: 0,
-            uid: None, 
+            uid: None,
             title,
             url: None,
             r#type: None,
             tags: None,
             is_starred: false,
-            uri: None 
+            uri: None,
+            parent_uid: None,
+            version: None,
         }
     }
 }
 
+/// Body of a create or update request
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveFolder<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uid : Option<&'a str>,
+    title : &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_uid : Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version : Option<u64>,
+    overwrite : bool,
+}
+
+/// Path to a folder by UID
+fn uid_path(uid : &str) -> String {
+    format!("{}/{}",FOLDERS_PATH,uid)
+}
+
 /// Folder Struct
 #[derive(Debug,Default)]
 pub struct Folder {
+    api : Api,
     model : Option<FolderModel>,
+    /// Folder updated by send, a new folder is created when not set
+    uid : Option<String>,
+    overwrite : bool,
 }
 
 impl Folder {
-    /// Create empty instance of folder API
-    pub fn new() -> Folder {
+    /// Create a new instance of the Folder API
+    pub fn new(api : Api) -> Folder {
         Folder {
+            api,
             model : None,
+            uid : None,
+            overwrite : false,
         }
     }
-    /// Create new folder 
+
+    /// Create new folder, with the uid and parent of the model when set
+    /// # Example
+    /// ```
+    /// # use grafana_lib::client::Client;
+    /// # use grafana_lib::common::auth::Auth;
+    /// # use grafana_lib::community::folder::FolderModel;
+    /// # let server = grafana_mock::MockServer::start();
+    /// # let client = Client::builder(&server.url()).with_auth(Auth::Anonymous).build().unwrap();
+    /// let folder = client.folder()
+    ///     .create(FolderModel::new(String::from("Operations")))
+    ///     .send()
+    ///     .unwrap();
+    /// assert_eq!(folder.version, Some(1));
+    /// ```
     pub fn create(mut self, model : FolderModel) -> Folder {
-        info!("Creating new dashboard: {}",model.title);
+        info!("Creating new folder: {}",model.title);
         self.model = Some(model);
+        self.uid = None;
         self
     }
+
+    /// Update the title of a folder. Fails with [`GrafanaError::VersionMismatch`] when the
+    /// model's version is not the stored one, unless overwrite is set.
+    pub fn update(mut self, uid : String, model : FolderModel) -> Folder {
+        info!("Updating folder {uid}: {}",model.title);
+        self.model = Some(model);
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Update a folder changed by someone else
+    pub fn with_overwrite(mut self, overwrite : bool) -> Folder {
+        self.overwrite = overwrite;
+        self
+    }
+
     /// Send folder to Grafana
-    pub fn send(&self) -> Result<String,String> {
-        Err(String::from("Create Folder: Not implemented"))
+    pub fn send(&self) -> Result<FolderModel,GrafanaError> {
+        let body = self.body()?;
+        match &self.uid {
+            Some(uid) => self.api.put_json(&uid_path(uid), &body),
+            None => self.api.post_json(FOLDERS_PATH, &body),
+        }
     }
-}
\ No newline at end of file
+
+    /// Send folder to Grafana using async
+    pub async fn send_async(&self) -> Result<FolderModel,GrafanaError> {
+        let body = self.body()?;
+        match &self.uid {
+            Some(uid) => self.api.put_json_async(&uid_path(uid), &body).await,
+            None => self.api.post_json_async(FOLDERS_PATH, &body).await,
+        }
+    }
+
+    /// Body of the create or update request
+    fn body(&self) -> Result<SaveFolder<'_>,GrafanaError> {
+        let model = self.model.as_ref()
+            .ok_or(GrafanaError::Other(String::from("no folder to send, set one with create or update")))?;
+        let update = self.uid.is_some();
+        Ok(SaveFolder {
+            uid : model.uid.as_deref().filter(|_| !update),
+            title : &model.title,
+            parent_uid : model.parent_uid.as_deref().filter(|_| !update),
+            version : model.version.filter(|_| update),
+            overwrite : self.overwrite,
+        })
+    }
+
+    /// Get a folder by UID
+    pub fn get(&self, uid : String) -> Result<FolderModel,GrafanaError> {
+        self.api.get_json(&uid_path(&uid))
+    }
+
+    /// Get a folder by UID using async
+    pub async fn get_async(&self, uid : String) -> Result<FolderModel,GrafanaError> {
+        self.api.get_json_async(&uid_path(&uid)).await
+    }
+}
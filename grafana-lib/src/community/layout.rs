@@ -0,0 +1,302 @@
+//! Layout Module
+//!
+//! Placement of panels on Grafana's 24 column dashboard grid. [`Grid`] packs panels the
+//! way Grafana does when they are dragged up: each panel goes to the highest free
+//! position, leftmost first. [`validate`] finds panels that overlap, do not fit in the
+//! grid or share an id, e.g. in dashboards fetched from Grafana.
+//! # Example
+//! ```
+//! # use grafana_lib::community::layout::Grid;
+//! let mut grid = Grid::new();
+//! let cpu = grid.place(12, 8);
+//! let memory = grid.place(12, 8);
+//! let row = grid.row();
+//! assert_eq!((memory.x, memory.y), (12, 0));
+//! assert_eq!((row.y, row.w), (8, 24));
+//! assert_eq!(grid.place(24, 4).y, 9);
+//! # assert_eq!((cpu.x, cpu.y), (0, 0));
+//! ```
+use crate::community::panel::{GridPos,PanelBuilder,PanelModel};
+
+use std::collections::HashSet;
+use std::fmt;
+
+/// Number of columns of the dashboard grid
+pub const GRID_COLUMNS : u32 = 24;
+/// Width of panels without a size
+pub const DEFAULT_WIDTH : u32 = 12;
+/// Height of panels without a size
+pub const DEFAULT_HEIGHT : u32 = 8;
+
+/// Free space of a dashboard grid, the first free line of each column
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct Grid {
+    heights : [u32; GRID_COLUMNS as usize],
+}
+
+impl Grid {
+    /// Create an empty grid
+    pub fn new() -> Grid {
+        Grid::default()
+    }
+
+    /// Create an empty grid starting at a line
+    fn below(y : u32) -> Grid {
+        Grid { heights : [y; GRID_COLUMNS as usize] }
+    }
+
+    /// Create a grid with the space the panels of a dashboard take
+    pub fn from_panels(panels : &[PanelModel]) -> Grid {
+        let mut grid = Grid::new();
+        panels.iter().filter_map(|p| p.grid_pos.as_ref()).for_each(|p| grid.occupy(p));
+        grid
+    }
+
+    /// First line below all panels
+    pub fn bottom(&self) -> u32 {
+        self.heights.iter().copied().max().unwrap_or_default()
+    }
+
+    /// Mark the space of a panel placed by hand as taken
+    pub fn occupy(&mut self, pos : &GridPos) {
+        let end = pos.x.saturating_add(pos.w).min(GRID_COLUMNS);
+        for x in pos.x.min(end)..end {
+            let height = &mut self.heights[x as usize];
+            *height = (*height).max(pos.y.saturating_add(pos.h));
+        }
+    }
+
+    /// Place a panel at the highest free position, widths are limited to the grid. Lines
+    /// past `u32::MAX` are all placed on the last one.
+    pub fn place(&mut self, w : u32, h : u32) -> GridPos {
+        let (w, h) = (w.clamp(1, GRID_COLUMNS), h.max(1));
+        let (x, y) = (0..=GRID_COLUMNS - w)
+            .map(|x| (x, self.top(x, w)))
+            .min_by_key(|&(x, y)| (y, x))
+            .unwrap_or_default();
+        self.fill(x, w, y.saturating_add(h));
+        GridPos::new(x, y, w, h)
+    }
+
+    /// Place panels side by side on a new line, wrapping when they do not fit
+    pub fn columns(&mut self, widths : &[u32], h : u32) -> Vec<GridPos> {
+        let h = h.max(1);
+        let (mut x, mut y) = (0, self.bottom());
+        let mut placed = Vec::new();
+        for w in widths.iter().map(|w| (*w).clamp(1, GRID_COLUMNS)) {
+            if x + w > GRID_COLUMNS {
+                (x, y) = (0, y.saturating_add(h));
+            }
+            self.fill(x, w, y.saturating_add(h));
+            placed.push(GridPos::new(x, y, w, h));
+            x += w;
+        }
+        placed
+    }
+
+    /// Place a row header below all panels, panels placed next go below it
+    pub fn row(&mut self) -> GridPos {
+        let y = self.bottom();
+        *self = Grid::below(y.saturating_add(1));
+        GridPos::new(0, y, GRID_COLUMNS, 1)
+    }
+
+    fn top(&self, x : u32, w : u32) -> u32 {
+        self.heights[x as usize..(x + w) as usize].iter().copied().max().unwrap_or_default()
+    }
+
+    fn fill(&mut self, x : u32, w : u32, height : u32) {
+        self.heights[x as usize..(x + w) as usize].fill(height);
+    }
+}
+
+/// Panels added to a dashboard builder, placed when it is built
+#[derive(Clone,Debug,PartialEq)]
+pub(crate) enum Item {
+    Panel(Box<PanelModel>),
+    Columns(u32, Vec<(u32,PanelModel)>),
+    Row(String),
+    CollapsedRow(String, Vec<PanelModel>),
+}
+
+/// Size of the grid position of a panel, or the default size
+fn size(panel : &PanelModel) -> (u32, u32) {
+    panel.grid_pos.as_ref().map(|p| (p.w, p.h)).unwrap_or((DEFAULT_WIDTH, DEFAULT_HEIGHT))
+}
+
+/// Move a panel, keeping the unknown fields of its grid position
+fn moved(mut panel : PanelModel, pos : GridPos) -> PanelModel {
+    panel.grid_pos = Some(GridPos { extra : panel.grid_pos.take().map(|p| p.extra).unwrap_or_default(), ..pos });
+    panel
+}
+
+/// Place a panel at the highest free position, keeping its size
+fn place(grid : &mut Grid, panel : PanelModel) -> PanelModel {
+    let pos = if panel.is_row() { grid.row() } else { let (w, h) = size(&panel); grid.place(w, h) };
+    moved(panel, pos)
+}
+
+/// Lay out panels: panels with a position keep it, the others and the items are placed in
+/// the free space below
+pub(crate) fn arrange(panels : Vec<PanelModel>, items : Vec<Item>) -> Vec<PanelModel> {
+    let mut grid = Grid::from_panels(&panels);
+    let mut placed : Vec<_> = panels.into_iter()
+        .map(|p| if p.grid_pos.is_some() { p } else { place(&mut grid, p) })
+        .collect();
+    for item in items {
+        match item {
+            Item::Panel(panel) => placed.push(place(&mut grid, *panel)),
+            Item::Columns(h, columns) => {
+                let widths : Vec<_> = columns.iter().map(|(w, _)| *w).collect();
+                for (pos, (_, panel)) in grid.columns(&widths, h).into_iter().zip(columns) {
+                    placed.push(moved(panel, pos));
+                }
+            },
+            Item::Row(title) => placed.push(place(&mut grid, PanelBuilder::row(title).build())),
+            Item::CollapsedRow(title, panels) => {
+                let mut row = place(&mut grid, PanelBuilder::row(title).with_collapsed(true).build());
+                let mut nested = Grid::below(grid.bottom());
+                row.panels = Some(panels.into_iter().map(|p| place(&mut nested, p)).collect());
+                placed.push(row);
+            },
+        }
+    }
+    placed
+}
+
+/// Problem in the layout of a dashboard
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum LayoutIssue<'a> {
+    /// Two panels take the same space
+    Overlap(&'a PanelModel, &'a PanelModel),
+    /// A panel is wider than the grid or starts outside of it
+    OutOfGrid(&'a PanelModel),
+    /// A panel has the id of a panel before it
+    DuplicateId(&'a PanelModel),
+}
+
+/// Title and id of a panel for messages
+struct Name<'a>(&'a PanelModel);
+
+impl fmt::Display for Name<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' [{}]", self.0.title.as_deref().unwrap_or_default(), self.0.id.map(|i| i.to_string()).unwrap_or_default())
+    }
+}
+
+impl fmt::Display for LayoutIssue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutIssue::Overlap(a, b) => write!(f, "panel {} overlaps panel {}", Name(a), Name(b)),
+            LayoutIssue::OutOfGrid(p) => write!(f, "panel {} does not fit in the {GRID_COLUMNS} column grid", Name(p)),
+            LayoutIssue::DuplicateId(p) => write!(f, "panel {} has the id of another panel", Name(p)),
+        }
+    }
+}
+
+/// Find layout problems. The panels of a collapsed row are checked against each other,
+/// they take the space below the row only when it is expanded.
+/// # Example
+/// ```
+/// # use grafana_lib::community::layout::{validate,LayoutIssue};
+/// # use grafana_lib::community::panel::{GridPos,PanelBuilder};
+/// let panels = vec![
+///     PanelBuilder::new(String::from("CPU")).with_id(1).with_grid_pos(GridPos::new(0, 0, 12, 8)).build(),
+///     PanelBuilder::new(String::from("Memory")).with_id(2).with_grid_pos(GridPos::new(8, 4, 12, 8)).build(),
+/// ];
+/// let issues = validate(&panels);
+/// assert_eq!(issues, vec![LayoutIssue::Overlap(&panels[0], &panels[1])]);
+/// assert_eq!(issues[0].to_string(), "panel 'CPU' [1] overlaps panel 'Memory' [2]");
+/// ```
+pub fn validate(panels : &[PanelModel]) -> Vec<LayoutIssue<'_>> {
+    let mut issues = Vec::new();
+    let mut ids = HashSet::new();
+    check(panels, &mut ids, &mut issues);
+    issues
+}
+
+fn check<'a>(panels : &'a [PanelModel], ids : &mut HashSet<u64>, issues : &mut Vec<LayoutIssue<'a>>) {
+    for (i, panel) in panels.iter().enumerate() {
+        if panel.id.is_some_and(|id| !ids.insert(id)) {
+            issues.push(LayoutIssue::DuplicateId(panel));
+        }
+        let Some(pos) = panel.grid_pos.as_ref() else { continue };
+        if pos.x.saturating_add(pos.w) > GRID_COLUMNS {
+            issues.push(LayoutIssue::OutOfGrid(panel));
+        }
+        for other in &panels[i + 1..] {
+            if other.grid_pos.as_ref().is_some_and(|o| overlap(pos, o)) {
+                issues.push(LayoutIssue::Overlap(panel, other));
+            }
+        }
+    }
+    for nested in panels.iter().filter_map(|p| p.panels.as_deref()) {
+        check(nested, ids, issues);
+    }
+}
+
+fn overlap(a : &GridPos, b : &GridPos) -> bool {
+    a.x < b.x.saturating_add(b.w) && b.x < a.x.saturating_add(a.w)
+        && a.y < b.y.saturating_add(b.h) && b.y < a.y.saturating_add(a.h)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_place_packs() {
+        let mut grid = Grid::new();
+        assert_eq!(grid.place(8, 10), GridPos::new(0, 0, 8, 10));
+        assert_eq!(grid.place(16, 4), GridPos::new(8, 0, 16, 4));
+        assert_eq!(grid.place(8, 4), GridPos::new(8, 4, 8, 4));
+        assert_eq!(grid.place(30, 2), GridPos::new(0, 10, 24, 2));
+        assert_eq!(grid.columns(&[6, 6, 12, 8], 3), vec![
+            GridPos::new(0, 12, 6, 3), GridPos::new(6, 12, 6, 3), GridPos::new(12, 12, 12, 3), GridPos::new(0, 15, 8, 3),
+        ]);
+        assert_eq!(grid.bottom(), 18);
+    }
+
+    #[test]
+    fn test_place_at_bottom() {
+        let low = PanelBuilder::new(String::from("Low")).with_grid_pos(GridPos::new(0, u32::MAX - 1, 24, 8)).build();
+        let mut grid = Grid::from_panels(std::slice::from_ref(&low));
+        assert_eq!(grid.place(12, 8), GridPos::new(0, u32::MAX, 12, 8));
+        assert_eq!(grid.columns(&[12, 12, 12], 4), vec![
+            GridPos::new(0, u32::MAX, 12, 4), GridPos::new(12, u32::MAX, 12, 4), GridPos::new(0, u32::MAX, 12, 4),
+        ]);
+        assert_eq!(grid.row(), GridPos::new(0, u32::MAX, 24, 1));
+        let items = vec![Item::Row(String::from("Row")), Item::Panel(Box::new(PanelBuilder::new(String::from("Next")).build()))];
+        let placed = arrange(vec![low], items);
+        assert_eq!(placed[2].grid_pos.as_ref().map(|p| p.y), Some(u32::MAX));
+    }
+
+    #[test]
+    fn test_arrange_keeps_extra() {
+        let mut pos = GridPos::new(0, 0, 6, 4);
+        pos.extra.insert(String::from("static"), serde_json::Value::Bool(true));
+        let panel = PanelBuilder::new(String::from("CPU")).with_grid_pos(pos).build();
+        let items = vec![Item::Panel(Box::new(panel.clone())), Item::Columns(4, vec![(8, panel)])];
+        let placed = arrange(Vec::new(), items);
+        let positions : Vec<_> = placed.iter().filter_map(|p| p.grid_pos.as_ref()).collect();
+        assert_eq!(positions.iter().map(|p| (p.x, p.y, p.w)).collect::<Vec<_>>(), vec![(0, 0, 6), (0, 4, 8)]);
+        assert!(positions.iter().all(|p| p.extra.get("static") == Some(&serde_json::Value::Bool(true))));
+    }
+
+    #[test]
+    fn test_validate() {
+        let panel = |id, pos| PanelBuilder::new(format!("p{id}")).with_id(id).with_grid_pos(pos).build();
+        let row = PanelBuilder::row(String::from("Row"))
+            .with_id(3)
+            .with_grid_pos(GridPos::new(0, 8, 24, 1))
+            .with_panel(panel(4, GridPos::new(0, 9, 12, 8)))
+            .with_panel(panel(1, GridPos::new(12, 9, 12, 8)))
+            .build();
+        let panels = vec![panel(1, GridPos::new(0, 0, 24, 8)), row, panel(5, GridPos::new(20, 9, 6, 8))];
+        let issues = validate(&panels);
+        assert_eq!(issues, vec![
+            LayoutIssue::OutOfGrid(&panels[2]),
+            LayoutIssue::DuplicateId(&panels[1].panels.as_ref().unwrap()[1]),
+        ]);
+    }
+}
@@ -14,9 +14,11 @@ pub mod dashboard;
 pub mod data_source;
 pub mod folder;
 pub mod folder_permissions;
+pub mod layout;
 pub mod library_element;
 pub mod organization;
 pub mod other;
+pub mod panel;
 pub mod playlists;
 pub mod preferences;
 pub mod query_history;
@@ -24,4 +26,5 @@ pub mod search;
 pub mod short_url;
 pub mod snapshot;
 pub mod team;
-pub mod user;
\ No newline at end of file
+pub mod user;
+pub mod visualization;
\ No newline at end of file
@@ -1,20 +1,52 @@
 //! Organization Module
-//! 
+//!
 //! This module handles management of organizations within Grafana
+use crate::common::api::Api;
+use crate::common::auth::Auth;
+use crate::common::error::GrafanaError;
+use crate::common::secret::Secret;
 
+use serde::{Serialize,Deserialize};
+use std::fmt;
+
+const ORGS_PATH : &str = "orgs";
+
+/// Result of creating an organization
+#[derive(Debug,Clone,PartialEq,Default,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrgCreatedModel {
+    /// Id of the new organization
+    pub org_id : u64,
+    /// Message from Grafana
+    pub message : String,
+}
+
+impl fmt::Display for OrgCreatedModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]",self.message,self.org_id)
+    }
+}
+
+/// Body of a create request
+#[derive(Serialize)]
+struct CreateOrg<'a> {
+    name : &'a str,
+}
 
 /// Organization Struct
 #[derive(Debug,Default)]
 pub struct Organization {
+    api : Api,
     name : Option<String>,
     user : Option<String>,
-    pass : Option<String>,
+    pass : Option<Secret>,
 }
 
 impl Organization {
-    /// Create empty instance of organization
-    pub fn new() -> Organization {
-        Organization { 
+    /// Create a new instance of the Organization API
+    pub fn new(api : Api) -> Organization {
+        Organization {
+            api,
             name : None,
             user : None,
             pass : None,
@@ -22,13 +54,15 @@ impl Organization {
     }
     /// Create a new organisation model
     /// # Examples
-    /// ```
+    /// ```no_run
     /// # use grafana_lib::client::Client;
     /// # let client = Client::new(String::from("http://localhost:3000"));
     /// let result = client.organization()
     ///     .create(String::from("MyOrg"))
+    ///     .with_user(String::from("admin"))
+    ///     .with_pass(String::from("admin"))
     ///     .send();
-    ///    
+    ///
     /// ```
     pub fn create(mut self,name : String) -> Organization {
         self.name = Some(name);
@@ -39,16 +73,34 @@ impl Organization {
         self.user = Some(user);
         self
     }
-    /// Set Password for Basic Authentication
+    /// Set Password for Basic Authentication, hidden when the request is printed
     pub fn with_pass(mut self, pass : String) -> Organization {
-        self.pass = Some(pass);
+        self.pass = Some(Secret::new(pass));
         self
     }
-    /// Send organization to Grafana
-    pub fn send(&self) -> Result<String,String> {
-        if self.user.is_none() || self.pass.is_none() {
-            return Err(String::from("Basic Auth Credentials not set"));
-        }
-        Err(String::from("Org Create: Not implemented"))
+    /// Send organization to Grafana. Grafana only creates organizations for users signed
+    /// in with a password, so the user and password set here are used instead of the
+    /// client's credentials.
+    pub fn send(&self) -> Result<OrgCreatedModel,GrafanaError> {
+        let (api, body) = self.prepare()?;
+        api.post_json(ORGS_PATH, &body)
+    }
+
+    /// Send organization to Grafana using async
+    pub async fn send_async(&self) -> Result<OrgCreatedModel,GrafanaError> {
+        let (api, body) = self.prepare()?;
+        api.post_json_async(ORGS_PATH, &body).await
     }
-}
\ No newline at end of file
+
+    /// Api with the basic credentials when set, and the body of the create request
+    fn prepare(&self) -> Result<(Api,CreateOrg<'_>),GrafanaError> {
+        let name = self.name.as_deref()
+            .ok_or(GrafanaError::Other(String::from("no organization to send, set one with create")))?;
+        let api = match (&self.user, &self.pass) {
+            (Some(user), Some(pass)) => self.api.clone().with_auth(Auth::Basic { user : user.clone(), pass : pass.clone() }),
+            (None, None) => self.api.clone(),
+            _ => return Err(GrafanaError::Config(String::from("basic authentication needs both a user and a password"))),
+        };
+        Ok((api, CreateOrg { name }))
+    }
+}
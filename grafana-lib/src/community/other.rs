@@ -1,4 +1,159 @@
 //! Other Module
+//!
+//! Instance level endpoints: health and frontend settings, which carry the build
+//! information and feature toggles used to detect [`Capabilities`].
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use reqwest::Method;
+use serde::Deserialize;
+
+use crate::common::api::Api;
+use crate::common::capabilities::Capabilities;
+use crate::common::error::GrafanaError;
+use crate::common::retry::RetryPolicy;
+use crate::common::transport::Response;
+
+const HEALTH_PATH : &str = "health";
+/// Frontend settings, also the source of the server capabilities
+pub(crate) const FRONTEND_SETTINGS_PATH : &str = "frontend/settings";
+
+/// Health of a Grafana instance
+#[derive(Debug,Clone,Default,PartialEq,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthModel {
+    /// Database status, `ok` or `failing`
+    pub database : String,
+    /// Grafana version, absent when hidden by the server
+    pub version : Option<String>,
+    /// Commit Grafana was built from
+    pub commit : Option<String>,
+    /// Commit of the enterprise build, if any
+    pub enterprise_commit : Option<String>,
+}
+
+impl HealthModel {
+    /// Is the database reachable?
+    pub fn is_ok(&self) -> bool {
+        self.database == "ok"
+    }
+}
+
+impl fmt::Display for HealthModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Database\t: {}",self.database)?;
+        writeln!(f, "Version\t: {}",self.version.clone().unwrap_or_default())?;
+        writeln!(f, "Commit\t: {}",self.commit.clone().unwrap_or_default())
+    }
+}
+
+/// Build information from the frontend settings
+#[derive(Debug,Clone,Default,PartialEq,Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BuildInfo {
+    /// Grafana version, e.g. `10.4.0`
+    pub version : String,
+    /// Commit Grafana was built from
+    pub commit : String,
+    /// Edition, e.g. `Open Source` or `Enterprise`
+    pub edition : String,
+    /// Build environment, e.g. `production`
+    pub env : String,
+    /// Build time as a unix timestamp
+    pub buildstamp : i64,
+    /// Latest released version, when update checks are enabled
+    pub latest_version : Option<String>,
+    /// Is a newer version available?
+    pub has_update : bool,
+}
+
+/// Frontend settings, the subset relevant to API clients
+#[derive(Debug,Clone,Default,PartialEq,Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FrontendSettings {
+    /// Build information
+    pub build_info : BuildInfo,
+    /// Feature toggles and whether they are enabled
+    pub feature_toggles : BTreeMap<String,bool>,
+    /// Public URL of the instance
+    pub app_url : Option<String>,
+    /// Name of the default data source
+    pub default_datasource : Option<String>,
+}
+
+impl FrontendSettings {
+    /// Names of the enabled feature toggles
+    pub fn enabled_toggles(&self) -> Vec<&str> {
+        self.feature_toggles.iter().filter(|(_,on)| **on).map(|(name,_)| name.as_str()).collect()
+    }
+}
+
+impl fmt::Display for FrontendSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Version\t: {}",self.build_info.version)?;
+        writeln!(f, "Edition\t: {}",self.build_info.edition)?;
+        writeln!(f, "Commit\t: {}",self.build_info.commit)?;
+        writeln!(f, "Toggles\t: {}",self.enabled_toggles().join(", "))
+    }
+}
 
 /// Other Structure
-pub struct Other {}
\ No newline at end of file
+#[derive(Debug,Clone)]
+pub struct Other {
+    api : Api,
+}
+
+impl Other {
+    /// Create a new instance of the Other API
+    pub fn new(api : Api) -> Other {
+        Other { api }
+    }
+
+    /// Health of the instance. A failing database is reported in the model rather than as
+    /// an error, the endpoint does not require credentials. The probe is never retried, so a
+    /// failing database is reported right away.
+    pub fn health(&self) -> Result<HealthModel,GrafanaError> {
+        let api = self.probe();
+        health(api.execute(api.request(Method::GET, HEALTH_PATH))?)
+    }
+
+    /// Health of the instance using async
+    pub async fn health_async(&self) -> Result<HealthModel,GrafanaError> {
+        let api = self.probe();
+        health(api.execute_async(api.request(Method::GET, HEALTH_PATH)).await?)
+    }
+
+    /// Api without retries, for the health probe
+    fn probe(&self) -> Api {
+        self.api.clone().with_retry(RetryPolicy::never())
+    }
+
+    /// Build information and feature toggles
+    pub fn frontend_settings(&self) -> Result<FrontendSettings,GrafanaError> {
+        self.api.get_json(FRONTEND_SETTINGS_PATH)
+    }
+
+    /// Build information and feature toggles using async
+    pub async fn frontend_settings_async(&self) -> Result<FrontendSettings,GrafanaError> {
+        self.api.get_json_async(FRONTEND_SETTINGS_PATH).await
+    }
+
+    /// Capabilities of the instance, detected once and shared by clones of the client
+    pub fn capabilities(&self) -> Result<Capabilities,GrafanaError> {
+        self.api.capabilities()
+    }
+
+    /// Capabilities of the instance using async
+    pub async fn capabilities_async(&self) -> Result<Capabilities,GrafanaError> {
+        self.api.capabilities_async().await
+    }
+}
+
+/// Decode a health response, which uses 503 when the database is failing
+fn health(response : Response) -> Result<HealthModel,GrafanaError> {
+    match response.status {
+        200 | 503 => serde_json::from_str(&response.body).map_err(|e| GrafanaError::decode(e, &response.body)),
+        status => Err(GrafanaError::from_response(status, &response.body)),
+    }
+}
@@ -0,0 +1,570 @@
+//! Panel Module
+//!
+//! Typed model of dashboard panels. Fields shared by all panel plugins are typed, plugin
+//! specific settings live in `options` and `fieldConfig.defaults.custom`, and anything
+//! else is kept in `extra` so a panel is written back unchanged apart from the edits made
+//! to it.
+use crate::common::lossless::lossless;
+
+use serde::{Serialize,Deserialize};
+use serde_json::{Map,Number,Value};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Type of row panels
+pub const ROW_TYPE : &str = "row";
+
+/// Position and size of a panel on the 24 column dashboard grid
+#[derive(Clone,Debug,Default,PartialEq,Serialize,Deserialize)]
+pub struct GridPos {
+    /// Height in grid units of 30 pixels
+    pub h : u32,
+    /// Width in columns, 1 to 24
+    pub w : u32,
+    /// Column of the left edge, 0 to 23
+    pub x : u32,
+    /// Row of the top edge
+    pub y : u32,
+    /// Fields this model does not type, e.g. `static`
+    #[serde(flatten)]
+    pub extra : Map<String,Value>,
+}
+
+impl GridPos {
+    /// Create a grid position
+    pub fn new(x : u32, y : u32, w : u32, h : u32) -> GridPos {
+        GridPos { h, w, x, y, extra : Map::new() }
+    }
+}
+
+/// Reference to a data source
+#[derive(Clone,Debug,Default)]
+pub struct DataSourceRef {
+    /// Plugin id of the data source, e.g. `prometheus`
+    pub r#type : Option<String>,
+    /// UID of the data source or a variable such as `${datasource}`
+    pub uid : Option<String>,
+    /// Fields this model does not type
+    pub extra : Map<String,Value>,
+}
+
+lossless!(DataSourceRef { r#type : "type", uid : "uid" });
+
+impl DataSourceRef {
+    /// Create a reference to a data source
+    pub fn new(r#type : String, uid : String) -> DataSourceRef {
+        DataSourceRef { r#type : Some(r#type), uid : Some(uid), extra : Map::new() }
+    }
+}
+
+/// Query of a panel. The query itself depends on the data source, e.g. `expr` for
+/// Prometheus, and is kept in `extra`.
+/// # Example
+/// ```
+/// # use grafana_lib::community::panel::{DataSourceRef,Target};
+/// let target = Target::new(String::from("A"))
+///     .with_datasource(DataSourceRef::new(String::from("prometheus"), String::from("${datasource}")))
+///     .with_query(String::from("expr"), serde_json::json!("up"));
+/// assert_eq!(target.query("expr"), Some(&serde_json::json!("up")));
+/// ```
+#[derive(Clone,Debug,Default)]
+pub struct Target {
+    /// Id of the query within the panel, e.g. `A`
+    pub ref_id : Option<String>,
+    /// Data source when it differs from the panel's
+    pub datasource : Option<DataSourceRef>,
+    /// Is the query hidden from the visualization?
+    pub hide : Option<bool>,
+    /// Data source specific query fields
+    pub extra : Map<String,Value>,
+}
+
+lossless!(Target { ref_id : "refId", datasource : "datasource", hide : "hide" });
+
+impl Target {
+    /// Create a query with a ref id
+    pub fn new(ref_id : String) -> Target {
+        Target { ref_id : Some(ref_id), ..Target::default() }
+    }
+
+    /// Set the data source of the query
+    pub fn with_datasource(mut self, datasource : DataSourceRef) -> Target {
+        self.datasource = Some(datasource);
+        self
+    }
+
+    /// Set a data source specific query field
+    pub fn with_query(mut self, key : String, value : Value) -> Target {
+        self.extra.insert(key, value);
+        self
+    }
+
+    /// Get a data source specific query field
+    pub fn query(&self, key : &str) -> Option<&Value> {
+        self.extra.get(key)
+    }
+}
+
+/// Field configuration, defaults for all fields and overrides for matching fields
+#[derive(Clone,Debug,Default)]
+pub struct FieldConfig {
+    /// Settings of all fields
+    pub defaults : Option<FieldDefaults>,
+    /// Settings of the fields matching a matcher
+    pub overrides : Option<Vec<Override>>,
+    /// Fields this model does not type
+    pub extra : Map<String,Value>,
+}
+
+lossless!(FieldConfig { defaults : "defaults", overrides : "overrides" });
+
+/// Default settings of the fields of a panel
+#[derive(Clone,Debug,Default)]
+pub struct FieldDefaults {
+    /// Unit, e.g. `bytes` or `percentunit`
+    pub unit : Option<String>,
+    /// Number of decimals shown
+    pub decimals : Option<u32>,
+    /// Minimum of the value range
+    pub min : Option<Number>,
+    /// Maximum of the value range
+    pub max : Option<Number>,
+    /// Name shown instead of the field name
+    pub display_name : Option<String>,
+    /// Text shown when there is no value
+    pub no_value : Option<String>,
+    /// Color scheme, e.g. `{"mode": "palette-classic"}`
+    pub color : Option<Map<String,Value>>,
+    /// Thresholds used for coloring
+    pub thresholds : Option<Thresholds>,
+    /// Value mappings
+    pub mappings : Option<Vec<Value>>,
+    /// Data links
+    pub links : Option<Vec<Value>>,
+    /// Settings specific to the panel plugin
+    pub custom : Option<Map<String,Value>>,
+    /// Fields this model does not type
+    pub extra : Map<String,Value>,
+}
+
+lossless!(FieldDefaults {
+    unit : "unit",
+    decimals : "decimals",
+    min : "min",
+    max : "max",
+    display_name : "displayName",
+    no_value : "noValue",
+    color : "color",
+    thresholds : "thresholds",
+    mappings : "mappings",
+    links : "links",
+    custom : "custom",
+});
+
+/// Thresholds, `absolute` or `percentage` steps
+#[derive(Clone,Debug,Default)]
+pub struct Thresholds {
+    /// Mode, `absolute` or `percentage`
+    pub mode : Option<String>,
+    /// Steps in increasing order, the first one is the base without value
+    pub steps : Option<Vec<ThresholdStep>>,
+    /// Fields this model does not type
+    pub extra : Map<String,Value>,
+}
+
+lossless!(Thresholds { mode : "mode", steps : "steps" });
+
+impl Thresholds {
+    /// Create absolute thresholds with the color below the first step
+    /// # Example
+    /// ```
+    /// # use grafana_lib::community::panel::Thresholds;
+    /// let thresholds = Thresholds::absolute(String::from("green"))
+    ///     .with_step(80.0, String::from("red"));
+    /// assert_eq!(serde_json::to_value(&thresholds).unwrap(), serde_json::json!({
+    ///     "mode" : "absolute",
+    ///     "steps" : [{ "color" : "green", "value" : null }, { "color" : "red", "value" : 80.0 }],
+    /// }));
+    /// ```
+    pub fn absolute(base : String) -> Thresholds {
+        Thresholds::with_mode("absolute", base)
+    }
+
+    /// Create thresholds in percent of the value range with the color below the first step
+    pub fn percentage(base : String) -> Thresholds {
+        Thresholds::with_mode("percentage", base)
+    }
+
+    fn with_mode(mode : &str, base : String) -> Thresholds {
+        Thresholds { mode : Some(String::from(mode)), steps : Some(vec![ThresholdStep::base(base)]), extra : Map::new() }
+    }
+
+    /// Add a step, values that are not finite are ignored
+    pub fn with_step(mut self, value : f64, color : String) -> Thresholds {
+        if let Some(value) = Number::from_f64(value) {
+            self.steps.get_or_insert_with(Vec::new).push(ThresholdStep::new(color, value));
+        }
+        self
+    }
+}
+
+/// Step of thresholds
+#[derive(Clone,Debug,Default)]
+pub struct ThresholdStep {
+    /// Color from the step on
+    pub color : Option<String>,
+    /// Value the step starts at, `null` for the base step
+    pub value : Option<Number>,
+    /// Fields this model does not type
+    pub extra : Map<String,Value>,
+}
+
+lossless!(ThresholdStep { color : "color", value : "value" });
+
+impl ThresholdStep {
+    /// Create the base step, used below the first value
+    pub fn base(color : String) -> ThresholdStep {
+        let mut extra = Map::new();
+        extra.insert(String::from("value"), Value::Null);
+        ThresholdStep { color : Some(color), value : None, extra }
+    }
+
+    /// Create a step starting at a value
+    pub fn new(color : String, value : Number) -> ThresholdStep {
+        ThresholdStep { color : Some(color), value : Some(value), extra : Map::new() }
+    }
+}
+
+/// Override of settings for the fields matching a matcher
+#[derive(Clone,Debug,Default)]
+pub struct Override {
+    /// Fields the override applies to
+    pub matcher : Option<Matcher>,
+    /// Settings overridden
+    pub properties : Option<Vec<Property>>,
+    /// Fields this model does not type
+    pub extra : Map<String,Value>,
+}
+
+lossless!(Override { matcher : "matcher", properties : "properties" });
+
+/// Field matcher of an override
+#[derive(Clone,Debug,Default)]
+pub struct Matcher {
+    /// Matcher, e.g. `byName` or `byRegexp`
+    pub id : Option<String>,
+    /// Matcher argument, e.g. the field name
+    pub options : Option<Value>,
+    /// Fields this model does not type
+    pub extra : Map<String,Value>,
+}
+
+lossless!(Matcher { id : "id", options : "options" });
+
+impl Matcher {
+    /// Match the field with a name
+    pub fn by_name(name : String) -> Matcher {
+        Matcher { id : Some(String::from("byName")), options : Some(Value::String(name)), extra : Map::new() }
+    }
+}
+
+/// Setting overridden by an override
+#[derive(Clone,Debug,Default)]
+pub struct Property {
+    /// Setting, e.g. `unit` or `custom.width`
+    pub id : Option<String>,
+    /// Value of the setting
+    pub value : Option<Value>,
+    /// Fields this model does not type
+    pub extra : Map<String,Value>,
+}
+
+lossless!(Property { id : "id", value : "value" });
+
+impl Property {
+    /// Create a property
+    pub fn new(id : String, value : Value) -> Property {
+        Property { id : Some(id), value : Some(value), extra : Map::new() }
+    }
+}
+
+/// Transformation applied to the query results
+#[derive(Clone,Debug,Default)]
+pub struct Transformation {
+    /// Transformation, e.g. `organize` or `merge`
+    pub id : Option<String>,
+    /// Settings of the transformation
+    pub options : Option<Value>,
+    /// Is the transformation disabled?
+    pub disabled : Option<bool>,
+    /// Fields this model does not type
+    pub extra : Map<String,Value>,
+}
+
+lossless!(Transformation { id : "id", options : "options", disabled : "disabled" });
+
+impl Transformation {
+    /// Create a transformation
+    pub fn new(id : String, options : Value) -> Transformation {
+        Transformation { id : Some(id), options : Some(options), ..Transformation::default() }
+    }
+}
+
+/// Panel Model. Rows are panels of type `row`, a collapsed row holds its panels in
+/// `panels`, an expanded row is followed by them.
+/// # Example
+/// ```
+/// # use grafana_lib::community::panel::PanelModel;
+/// let json = serde_json::json!({
+///     "id" : 2, "type" : "stat", "title" : "Up",
+///     "gridPos" : { "h" : 4, "w" : 6, "x" : 0, "y" : 0 },
+///     "targets" : [{ "refId" : "A", "expr" : "up" }],
+///     "pluginVersion" : "10.4.1",
+/// });
+/// let mut panel : PanelModel = serde_json::from_value(json.clone()).unwrap();
+/// assert_eq!(panel.grid_pos.as_ref().map(|g| g.w), Some(6));
+/// panel.targets.as_mut().unwrap()[0].extra.insert(String::from("expr"), serde_json::json!("up == 0"));
+/// let written = serde_json::to_value(&panel).unwrap();
+/// assert_eq!(written["targets"][0]["expr"], "up == 0");
+/// assert_eq!(written["pluginVersion"], json["pluginVersion"]);
+/// ```
+#[derive(Clone,Debug,Default)]
+pub struct PanelModel {
+    /// Id, unique within the dashboard
+    pub id : Option<u64>,
+    /// Title
+    pub title : Option<String>,
+    /// Panel plugin, e.g. `timeseries`, `stat` or `row`
+    pub r#type : Option<String>,
+    /// Description shown in the panel's tooltip
+    pub description : Option<String>,
+    /// Position on the dashboard grid
+    pub grid_pos : Option<GridPos>,
+    /// Data source of the queries
+    pub datasource : Option<DataSourceRef>,
+    /// Queries
+    pub targets : Option<Vec<Target>>,
+    /// Field configuration
+    pub field_config : Option<FieldConfig>,
+    /// Settings specific to the panel plugin
+    pub options : Option<Map<String,Value>>,
+    /// Transformations of the query results
+    pub transformations : Option<Vec<Transformation>>,
+    /// Is the row collapsed?
+    pub collapsed : Option<bool>,
+    /// Panels of a collapsed row
+    pub panels : Option<Vec<PanelModel>>,
+    /// Fields this model does not type, e.g. `pluginVersion` or `libraryPanel`, kept so
+    /// saving writes them back unchanged
+    pub extra : Map<String,Value>,
+}
+
+lossless!(PanelModel {
+    id : "id",
+    title : "title",
+    r#type : "type",
+    description : "description",
+    grid_pos : "gridPos",
+    datasource : "datasource",
+    targets : "targets",
+    field_config : "fieldConfig",
+    options : "options",
+    transformations : "transformations",
+    collapsed : "collapsed",
+    panels : "panels",
+});
+
+impl PanelModel {
+    /// Is the panel a row?
+    pub fn is_row(&self) -> bool {
+        self.r#type.as_deref() == Some(ROW_TYPE)
+    }
+}
+
+impl fmt::Display for PanelModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut output = String::default();
+        output.push_str(format!("Title\t: {} [{}] - {}",
+            self.title.clone().unwrap_or_default(),
+            self.id.unwrap_or_default(),
+            self.r#type.clone().unwrap_or_default(),
+        ).as_str());
+        writeln!(f, "{output}")
+    }
+}
+
+/// Give panels, and the panels of collapsed rows, without an id or with the id of a panel
+/// before them one that is not used yet. Panels are visited in the order
+/// [`validate`](crate::community::layout::validate) reports duplicates in.
+pub(crate) fn number_panels(panels : &mut [PanelModel]) {
+    fn max_id(panels : &[PanelModel]) -> u64 {
+        panels.iter()
+            .map(|p| p.id.unwrap_or_default().max(p.panels.as_deref().map(max_id).unwrap_or_default()))
+            .max()
+            .unwrap_or_default()
+    }
+    fn number(panels : &mut [PanelModel], ids : &mut HashSet<u64>, next : &mut u64) {
+        for panel in panels.iter_mut() {
+            if !panel.id.is_some_and(|id| ids.insert(id)) {
+                *next += 1;
+                ids.insert(*next);
+                panel.id = Some(*next);
+            }
+        }
+        for nested in panels.iter_mut().filter_map(|p| p.panels.as_mut()) {
+            number(nested, ids, next);
+        }
+    }
+    let mut next = max_id(panels);
+    number(panels, &mut HashSet::new(), &mut next);
+}
+
+/// Builder for Panels
+/// # Example
+/// ```
+/// # use grafana_lib::community::panel::{GridPos,PanelBuilder,Target};
+/// let panel = PanelBuilder::new(String::from("Requests"))
+///     .with_type(String::from("timeseries"))
+///     .with_grid_pos(GridPos::new(0, 0, 12, 8))
+///     .with_target(Target::new(String::from("A")).with_query(String::from("expr"), serde_json::json!("rate(http_requests_total[5m])")))
+///     .with_option(String::from("legend"), serde_json::json!({ "showLegend" : true }))
+///     .build();
+/// assert_eq!(panel.targets.map(|t| t.len()), Some(1));
+/// ```
+#[derive(Clone,Debug,PartialEq)]
+pub struct PanelBuilder {
+    panel : PanelModel,
+}
+
+impl PanelBuilder {
+    /// Create a new PanelBuilder object
+    pub fn new(title : String) -> PanelBuilder {
+        PanelBuilder { panel : PanelModel { title : Some(title), ..PanelModel::default() } }
+    }
+
+    /// Create a builder of a row, expanded until panels are added with
+    /// [`PanelBuilder::with_panel`] and it is collapsed
+    pub fn row(title : String) -> PanelBuilder {
+        PanelBuilder::new(title)
+            .with_type(String::from(ROW_TYPE))
+            .with_collapsed(false)
+    }
+
+    /// Set type of panel
+    pub fn with_type(mut self, r#type : String) -> PanelBuilder {
+        self.panel.r#type = Some(r#type);
+        self
+    }
+
+    /// Set id of panel, by default the dashboard builder picks one
+    pub fn with_id(mut self, id : u64) -> PanelBuilder {
+        self.panel.id = Some(id);
+        self
+    }
+
+    /// Set description of panel
+    pub fn with_description(mut self, description : String) -> PanelBuilder {
+        self.panel.description = Some(description);
+        self
+    }
+
+    /// Set position on the dashboard grid
+    pub fn with_grid_pos(mut self, grid_pos : GridPos) -> PanelBuilder {
+        self.panel.grid_pos = Some(grid_pos);
+        self
+    }
+
+    /// Set data source of the queries
+    pub fn with_datasource(mut self, datasource : DataSourceRef) -> PanelBuilder {
+        self.panel.datasource = Some(datasource);
+        self
+    }
+
+    /// Add a query
+    pub fn with_target(mut self, target : Target) -> PanelBuilder {
+        self.panel.targets.get_or_insert_with(Vec::new).push(target);
+        self
+    }
+
+    /// Set field configuration
+    pub fn with_field_config(mut self, field_config : FieldConfig) -> PanelBuilder {
+        self.panel.field_config = Some(field_config);
+        self
+    }
+
+    /// Set a plugin specific option
+    pub fn with_option(mut self, key : String, value : Value) -> PanelBuilder {
+        self.panel.options.get_or_insert_with(Map::new).insert(key, value);
+        self
+    }
+
+    /// Add a transformation
+    pub fn with_transformation(mut self, transformation : Transformation) -> PanelBuilder {
+        self.panel.transformations.get_or_insert_with(Vec::new).push(transformation);
+        self
+    }
+
+    /// Set whether a row is collapsed
+    pub fn with_collapsed(mut self, collapsed : bool) -> PanelBuilder {
+        self.panel.collapsed = Some(collapsed);
+        self
+    }
+
+    /// Add a panel to a collapsed row
+    pub fn with_panel(mut self, panel : PanelModel) -> PanelBuilder {
+        self.panel.panels.get_or_insert_with(Vec::new).push(panel);
+        self.panel.collapsed = Some(true);
+        self
+    }
+
+    /// Build a Panel
+    pub fn build(self) -> PanelModel {
+        let mut panel = self.panel;
+        if panel.is_row() && panel.panels.is_none() {
+            panel.panels = Some(Vec::new());
+        }
+        panel
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_row_round_trip() {
+        let json = json!({
+            "type" : "row", "title" : "Nodes", "collapsed" : true, "id" : 9,
+            "gridPos" : { "h" : 1, "w" : 24, "x" : 0, "y" : 8 },
+            "panels" : [{ "type" : "stat", "id" : 10, "datasource" : "Graphite", "fieldConfig" : {
+                "defaults" : { "min" : 0, "max" : 1.5, "thresholds" : { "mode" : "absolute", "steps" : [{ "color" : "green", "value" : null }, { "color" : "red", "value" : 80 }] } },
+                "overrides" : [{ "matcher" : { "id" : "byName", "options" : "Time" }, "properties" : [{ "id" : "custom.hidden", "value" : true }] }],
+            } }],
+        });
+        let row : PanelModel = serde_json::from_value(json.clone()).unwrap();
+        assert!(row.is_row());
+        let nested = &row.panels.as_ref().unwrap()[0];
+        assert_eq!(nested.datasource, None);
+        let defaults = nested.field_config.as_ref().unwrap().defaults.as_ref().unwrap();
+        assert_eq!(defaults.max, Number::from_f64(1.5));
+        let steps = defaults.thresholds.as_ref().unwrap().steps.as_ref().unwrap();
+        assert_eq!(steps[0], ThresholdStep::base(String::from("green")));
+        assert_eq!(steps[1], ThresholdStep::new(String::from("red"), Number::from(80)));
+        assert_eq!(serde_json::to_value(&row).unwrap(), json);
+    }
+
+    #[test]
+    fn test_number_panels() {
+        let row = PanelBuilder::row(String::from("Row"))
+            .with_panel(PanelBuilder::new(String::from("Nested")).build())
+            .with_panel(PanelBuilder::new(String::from("Fixed")).with_id(7).build())
+            .build();
+        let mut panels = vec![PanelBuilder::new(String::from("First")).build(), row];
+        number_panels(&mut panels);
+        assert_eq!(panels[0].id, Some(8));
+        assert_eq!(panels[1].id, Some(9));
+        let nested : Vec<_> = panels[1].panels.iter().flatten().map(|p| p.id).collect();
+        assert_eq!(nested, vec![Some(10), Some(7)]);
+    }
+}
@@ -2,14 +2,17 @@
 
 use crate::common::error::GrafanaError;
 use crate::common::api::Api;
+use crate::common::capabilities::Capability;
 use crate::community::folder::FolderModel;
 use crate::community::dashboard::DashboardModel;
 
 use log::debug;
+use url::form_urlencoded;
 
 const SEARCH_PATH       : &str = "search";
 const SEARCH_DASHBOARD  : &str = "dash-db";
 const SEARCH_FOLDER     : &str = "dash-folder";
+const FOLDERS_PATH      : &str = "folders";
 
 /// Dashboard Search Results
 pub struct DashboardResult {
@@ -21,6 +24,29 @@ pub struct FolderResult {
     _id  : String,
 }
 
+/// Build search path for a given object type and optional query
+fn search_path(kind : &str, query : Option<String>) -> String {
+    let url = match query {
+        Some(q) => with_query(SEARCH_PATH, &[("type", kind), ("query", &q)]),
+        None    => with_query(SEARCH_PATH, &[("type", kind)]),
+    };
+    debug!("URL: {url}");
+    url
+}
+
+/// Build the path listing the children of a folder
+fn subfolders_path(parent_uid : &str) -> String {
+    with_query(FOLDERS_PATH, &[("parentUid", parent_uid)])
+}
+
+/// Append percent-encoded query parameters to a path
+fn with_query(path : &str, params : &[(&str,&str)]) -> String {
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish();
+    format!("{path}?{query}")
+}
+
 /// Search Structure
 pub struct Search {
     api : Api,
@@ -35,32 +61,49 @@ impl Search {
     }
     /// Search dashboards according to query string
     pub fn dashboard(&self, query : Option<String>) -> Result<Vec<DashboardModel>,GrafanaError> {
-        let url = match query {
-            Some(q) => format!("{}?type={}&query={}",SEARCH_PATH,SEARCH_DASHBOARD,q),
-            None    => format!("{}?type={}",SEARCH_PATH,SEARCH_DASHBOARD),
-        };
-        debug!("URL: {url}");
-        let body = self.api.get(url).unwrap();
-        let result = serde_json::from_str(body.as_str());
-        match result {
-            Ok(r) => Ok(r),
-            Err(e) => Err(GrafanaError::new(e.to_string(), String::from("-1")))
-        }
+        self.api.get_json(&search_path(SEARCH_DASHBOARD, query))
+    }
+
+    /// Search dashboards according to query string using async
+    pub async fn dashboard_async(&self, query : Option<String>) -> Result<Vec<DashboardModel>,GrafanaError> {
+        self.api.get_json_async(&search_path(SEARCH_DASHBOARD, query)).await
     }
 
     /// Folder Search using query string
     pub fn folder(&self, query : Option<String>) -> Result<Vec<FolderModel>,GrafanaError> {
-        let url = match query {
-            Some(q) => format!("{}?type={}&query={}",SEARCH_PATH,SEARCH_FOLDER,q),
-            None    => format!("{}?type={}",SEARCH_PATH,SEARCH_FOLDER),
-        };
-        debug!("URL: {url}");
-        let body = self.api.get(url).unwrap();
-        let result = serde_json::from_str(body.as_str());
-        match result {
-            Ok(r) => Ok(r),
-            Err(e) => Err(GrafanaError::new(e.to_string(), String::from("-1"))),
-        }
+        self.api.get_json(&search_path(SEARCH_FOLDER, query))
+    }
+
+    /// Folder Search using query string using async
+    pub async fn folder_async(&self, query : Option<String>) -> Result<Vec<FolderModel>,GrafanaError> {
+        self.api.get_json_async(&search_path(SEARCH_FOLDER, query)).await
+    }
+
+    /// Folders directly inside a parent folder, needs a server with nested folders
+    pub fn subfolders(&self, parent_uid : &str) -> Result<Vec<FolderModel>,GrafanaError> {
+        self.api.capabilities()?.require(Capability::NestedFolders)?;
+        self.api.get_json(&subfolders_path(parent_uid))
     }
-        
-}
\ No newline at end of file
+
+    /// Folders directly inside a parent folder using async
+    pub async fn subfolders_async(&self, parent_uid : &str) -> Result<Vec<FolderModel>,GrafanaError> {
+        self.api.capabilities_async().await?.require(Capability::NestedFolders)?;
+        self.api.get_json_async(&subfolders_path(parent_uid)).await
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_subfolders_path_encoded() {
+        assert_eq!(subfolders_path("ops"), "folders?parentUid=ops");
+        assert_eq!(subfolders_path("a&b #1+2"), "folders?parentUid=a%26b+%231%2B2");
+    }
+
+    #[test]
+    fn test_search_path_encoded() {
+        assert_eq!(search_path(SEARCH_FOLDER, None), "search?type=dash-folder");
+        assert_eq!(search_path(SEARCH_DASHBOARD, Some(String::from("a&type=dash-folder"))), "search?type=dash-db&query=a%26type%3Ddash-folder");
+    }
+}
@@ -0,0 +1,1324 @@
+//! Visualization Module
+//!
+//! Builders of the common panel plugins. Each builder starts from the options Grafana
+//! uses for a new panel of that type, exposes them as typed settings and builds a
+//! [`PanelModel`].
+//! # Example
+//! ```
+//! # use grafana_lib::community::panel::{Target,Thresholds};
+//! # use grafana_lib::community::visualization::{Calc,StatBuilder,StatColorMode,Unit};
+//! let panel = StatBuilder::new(String::from("CPU requests"))
+//!     .with_target(Target::new(String::from("A")).with_query(String::from("expr"), serde_json::json!("cluster:cpu_requests:ratio")))
+//!     .with_unit(Unit::PercentUnit)
+//!     .with_thresholds(Thresholds::absolute(String::from("green")).with_step(0.9, String::from("red")))
+//!     .with_reduce_calc(Calc::Mean)
+//!     .with_color_mode(StatColorMode::Background)
+//!     .build();
+//! assert_eq!(panel.r#type.as_deref(), Some("stat"));
+//! assert_eq!(panel.options.unwrap()["colorMode"], "background");
+//! ```
+use crate::community::panel::{DataSourceRef,FieldDefaults,GridPos,Matcher,Override,PanelModel,Property,Target,Thresholds,Transformation};
+
+use serde::{Serialize,Deserialize};
+use serde_json::{Map,Number,Value};
+use std::fmt;
+
+/// Unit of the values of a field
+#[derive(Clone,Debug,PartialEq)]
+pub enum Unit {
+    /// Plain number with SI suffix
+    Short,
+    /// No unit
+    None,
+    /// Percent, 0 to 100
+    Percent,
+    /// Percent, 0.0 to 1.0
+    PercentUnit,
+    /// Bytes, IEC multiples
+    Bytes,
+    /// Bytes, SI multiples
+    DecBytes,
+    /// Bits per second
+    BitsPerSecond,
+    /// Bytes per second
+    BytesPerSecond,
+    /// Seconds
+    Seconds,
+    /// Milliseconds
+    Milliseconds,
+    /// Requests per second
+    RequestsPerSecond,
+    /// Operations per second
+    OpsPerSecond,
+    /// ISO date and time
+    DateTime,
+    /// Any other Grafana unit id, e.g. `currencyEUR`
+    Custom(String),
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = match self {
+            Unit::Short => "short",
+            Unit::None => "none",
+            Unit::Percent => "percent",
+            Unit::PercentUnit => "percentunit",
+            Unit::Bytes => "bytes",
+            Unit::DecBytes => "decbytes",
+            Unit::BitsPerSecond => "bps",
+            Unit::BytesPerSecond => "Bps",
+            Unit::Seconds => "s",
+            Unit::Milliseconds => "ms",
+            Unit::RequestsPerSecond => "reqps",
+            Unit::OpsPerSecond => "ops",
+            Unit::DateTime => "dateTimeAsIso",
+            Unit::Custom(id) => id,
+        };
+        write!(f, "{id}")
+    }
+}
+
+/// Color scheme of the fields
+#[derive(Clone,Copy,Debug,PartialEq,Serialize,Deserialize)]
+pub enum FieldColorMode {
+    /// Color of the threshold step of the value
+    #[serde(rename = "thresholds")]
+    Thresholds,
+    /// A color per series from the classic palette
+    #[serde(rename = "palette-classic")]
+    PaletteClassic,
+    /// Shades of a single color
+    #[serde(rename = "shades")]
+    Shades,
+    /// Green, yellow, red by value
+    #[serde(rename = "continuous-GrYlRd")]
+    ContinuousGreenYellowRed,
+    /// Red, yellow, green by value
+    #[serde(rename = "continuous-RdYlGr")]
+    ContinuousRedYellowGreen,
+    /// Blues by value
+    #[serde(rename = "continuous-blues")]
+    ContinuousBlues,
+}
+
+/// Reducer calculating a single value from a series
+#[derive(Clone,Copy,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Calc {
+    /// Last value that is not null
+    LastNotNull,
+    /// Last value
+    Last,
+    /// First value that is not null
+    FirstNotNull,
+    /// First value
+    First,
+    /// Mean
+    Mean,
+    /// Maximum
+    Max,
+    /// Minimum
+    Min,
+    /// Sum
+    Sum,
+    /// Number of values
+    Count,
+    /// Cumulative change
+    Delta,
+    /// Maximum minus minimum
+    Range,
+}
+
+/// How a single value is calculated from each series
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReduceOptions {
+    /// Reducer, Grafana uses the first one
+    pub calcs : Vec<Calc>,
+    /// Fields to show, a regex; empty for numeric fields
+    pub fields : String,
+    /// Show every value instead of a reduced one
+    pub values : bool,
+}
+
+impl Default for ReduceOptions {
+    fn default() -> ReduceOptions {
+        ReduceOptions { calcs : vec![Calc::LastNotNull], fields : String::default(), values : false }
+    }
+}
+
+/// Layout of the values
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    /// Based on the panel's size
+    #[default]
+    Auto,
+    /// Side by side
+    Horizontal,
+    /// Stacked
+    Vertical,
+}
+
+/// Legend layout
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LegendDisplayMode {
+    /// Series names
+    #[default]
+    List,
+    /// Series names with calculated values
+    Table,
+    /// No legend
+    Hidden,
+}
+
+/// Legend position
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LegendPlacement {
+    /// Below the graph
+    #[default]
+    Bottom,
+    /// Right of the graph
+    Right,
+}
+
+/// Legend of a graph
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegendOptions {
+    /// Values calculated for each series in the legend
+    pub calcs : Vec<Calc>,
+    /// Layout
+    pub display_mode : LegendDisplayMode,
+    /// Position
+    pub placement : LegendPlacement,
+    /// Is the legend shown?
+    pub show_legend : bool,
+}
+
+impl Default for LegendOptions {
+    fn default() -> LegendOptions {
+        LegendOptions { calcs : Vec::new(), display_mode : LegendDisplayMode::List, placement : LegendPlacement::Bottom, show_legend : true }
+    }
+}
+
+/// Series shown in the tooltip
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TooltipMode {
+    /// The series under the cursor
+    #[default]
+    Single,
+    /// All series
+    Multi,
+    /// No tooltip
+    None,
+}
+
+/// Order of the series in the tooltip
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Order of the series
+    #[default]
+    None,
+    /// Increasing values
+    Asc,
+    /// Decreasing values
+    Desc,
+}
+
+/// Tooltip of a graph
+#[derive(Clone,Debug,Default,PartialEq,Serialize,Deserialize)]
+pub struct TooltipOptions {
+    /// Series shown
+    pub mode : TooltipMode,
+    /// Order of the series
+    pub sort : SortOrder,
+}
+
+/// How the points of a series are drawn
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DrawStyle {
+    /// Connected lines
+    #[default]
+    Line,
+    /// Bars
+    Bars,
+    /// Points
+    Points,
+}
+
+/// How series are stacked
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StackingMode {
+    /// Not stacked
+    #[default]
+    None,
+    /// Stacked values
+    Normal,
+    /// Stacked to 100%
+    Percent,
+}
+
+/// Stacking of series
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+pub struct Stacking {
+    /// Stacking mode
+    pub mode : StackingMode,
+    /// Series with the same group are stacked together
+    pub group : String,
+}
+
+impl Default for Stacking {
+    fn default() -> Stacking {
+        Stacking { mode : StackingMode::None, group : String::from("A") }
+    }
+}
+
+/// Settings of time series fields, `fieldConfig.defaults.custom`
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSeriesFieldOptions {
+    /// How points are drawn
+    pub draw_style : DrawStyle,
+    /// Width of lines in pixels
+    pub line_width : u32,
+    /// Opacity of the area below lines, 0 to 100
+    pub fill_opacity : u32,
+    /// Stacking of series
+    pub stacking : Stacking,
+}
+
+impl Default for TimeSeriesFieldOptions {
+    fn default() -> TimeSeriesFieldOptions {
+        TimeSeriesFieldOptions { draw_style : DrawStyle::Line, line_width : 1, fill_opacity : 0, stacking : Stacking::default() }
+    }
+}
+
+/// Options of time series panels
+#[derive(Clone,Debug,Default,PartialEq,Serialize,Deserialize)]
+pub struct TimeSeriesOptions {
+    /// Legend
+    pub legend : LegendOptions,
+    /// Tooltip
+    pub tooltip : TooltipOptions,
+}
+
+/// What the color of a stat applies to
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatColorMode {
+    /// The value
+    #[default]
+    Value,
+    /// The background, as a gradient
+    Background,
+    /// The background, as a solid color
+    BackgroundSolid,
+    /// Nothing
+    None,
+}
+
+/// Graph behind a stat
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphMode {
+    /// Sparkline area
+    #[default]
+    Area,
+    /// No graph
+    None,
+}
+
+/// Text shown by a stat
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatTextMode {
+    /// Value, and name when there are several values
+    #[default]
+    Auto,
+    /// Value
+    Value,
+    /// Value and name
+    ValueAndName,
+    /// Name
+    Name,
+    /// Nothing
+    None,
+}
+
+/// Alignment of stats
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JustifyMode {
+    /// Left, or centered without a graph
+    #[default]
+    Auto,
+    /// Centered
+    Center,
+}
+
+/// Options of stat panels
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatOptions {
+    /// What the color applies to
+    pub color_mode : StatColorMode,
+    /// Graph behind the value
+    pub graph_mode : GraphMode,
+    /// Alignment
+    pub justify_mode : JustifyMode,
+    /// Layout of several values
+    pub orientation : Orientation,
+    /// How the value is calculated
+    pub reduce_options : ReduceOptions,
+    /// Show the change since the start of the time range
+    pub show_percent_change : bool,
+    /// Text shown
+    pub text_mode : StatTextMode,
+    /// Name and value side by side in wide panels
+    pub wide_layout : bool,
+}
+
+impl Default for StatOptions {
+    fn default() -> StatOptions {
+        StatOptions {
+            color_mode : StatColorMode::Value,
+            graph_mode : GraphMode::Area,
+            justify_mode : JustifyMode::Auto,
+            orientation : Orientation::Auto,
+            reduce_options : ReduceOptions::default(),
+            show_percent_change : false,
+            text_mode : StatTextMode::Auto,
+            wide_layout : true,
+        }
+    }
+}
+
+/// Options of gauge panels
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GaugeOptions {
+    /// Minimum height of a gauge in pixels
+    pub min_viz_height : u32,
+    /// Minimum width of a gauge in pixels
+    pub min_viz_width : u32,
+    /// Layout of several gauges
+    pub orientation : Orientation,
+    /// How the value is calculated
+    pub reduce_options : ReduceOptions,
+    /// Show the threshold values
+    pub show_threshold_labels : bool,
+    /// Show the thresholds as a band around the gauge
+    pub show_threshold_markers : bool,
+}
+
+impl Default for GaugeOptions {
+    fn default() -> GaugeOptions {
+        GaugeOptions {
+            min_viz_height : 75,
+            min_viz_width : 75,
+            orientation : Orientation::Auto,
+            reduce_options : ReduceOptions::default(),
+            show_threshold_labels : false,
+            show_threshold_markers : true,
+        }
+    }
+}
+
+/// Style of bar gauges
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BarGaugeDisplayMode {
+    /// Gradient up to the value
+    #[default]
+    Gradient,
+    /// Retro LCD segments
+    Lcd,
+    /// Single color
+    Basic,
+}
+
+/// Options of bar gauge panels
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BarGaugeOptions {
+    /// Style of the bars
+    pub display_mode : BarGaugeDisplayMode,
+    /// Minimum height of a bar in pixels
+    pub min_viz_height : u32,
+    /// Minimum width of a bar in pixels
+    pub min_viz_width : u32,
+    /// Direction of the bars
+    pub orientation : Orientation,
+    /// How the value is calculated
+    pub reduce_options : ReduceOptions,
+    /// Show the unfilled part of the bars
+    pub show_unfilled : bool,
+}
+
+impl Default for BarGaugeOptions {
+    fn default() -> BarGaugeOptions {
+        BarGaugeOptions {
+            display_mode : BarGaugeDisplayMode::Gradient,
+            min_viz_height : 16,
+            min_viz_width : 8,
+            orientation : Orientation::Auto,
+            reduce_options : ReduceOptions::default(),
+            show_unfilled : true,
+        }
+    }
+}
+
+/// Height of table rows
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CellHeight {
+    /// Small
+    #[default]
+    Sm,
+    /// Medium
+    Md,
+    /// Large
+    Lg,
+}
+
+/// Footer of a table
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableFooter {
+    /// Show the number of rows instead of calculated values
+    pub count_rows : bool,
+    /// Fields calculated, a regex; empty for numeric fields
+    pub fields : String,
+    /// Calculation of each field
+    pub reducer : Vec<Calc>,
+    /// Is the footer shown?
+    pub show : bool,
+}
+
+impl Default for TableFooter {
+    fn default() -> TableFooter {
+        TableFooter { count_rows : false, fields : String::default(), reducer : vec![Calc::Sum], show : false }
+    }
+}
+
+/// Options of table panels
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableOptions {
+    /// Height of the rows
+    pub cell_height : CellHeight,
+    /// Footer
+    pub footer : TableFooter,
+    /// Is the header shown?
+    pub show_header : bool,
+}
+
+impl Default for TableOptions {
+    fn default() -> TableOptions {
+        TableOptions { cell_height : CellHeight::Sm, footer : TableFooter::default(), show_header : true }
+    }
+}
+
+/// Format of the content of text panels
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextMode {
+    /// Markdown
+    #[default]
+    Markdown,
+    /// HTML, sanitized by Grafana
+    Html,
+    /// Source code
+    Code,
+}
+
+/// Options of text panels
+#[derive(Clone,Debug,Default,PartialEq,Serialize,Deserialize)]
+pub struct TextOptions {
+    /// Format of the content
+    pub mode : TextMode,
+    /// Content, may use dashboard variables
+    pub content : String,
+}
+
+/// Removal of duplicate log lines
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupStrategy {
+    /// Keep all lines
+    #[default]
+    None,
+    /// Remove identical lines
+    Exact,
+    /// Remove lines only differing in numbers
+    Numbers,
+    /// Remove lines with the same letters and whitespace
+    Signature,
+}
+
+/// Order of log lines
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+pub enum LogsSortOrder {
+    /// Newest first
+    #[default]
+    Descending,
+    /// Oldest first
+    Ascending,
+}
+
+/// Options of logs panels
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogsOptions {
+    /// Removal of duplicate lines
+    pub dedup_strategy : DedupStrategy,
+    /// Lines can be expanded to show their labels and fields
+    pub enable_log_details : bool,
+    /// Pretty print JSON lines
+    pub prettify_log_message : bool,
+    /// Show the labels all lines share
+    pub show_common_labels : bool,
+    /// Show the labels of each line
+    pub show_labels : bool,
+    /// Show the time of each line
+    pub show_time : bool,
+    /// Order of the lines
+    pub sort_order : LogsSortOrder,
+    /// Wrap long lines
+    pub wrap_log_message : bool,
+}
+
+impl Default for LogsOptions {
+    fn default() -> LogsOptions {
+        LogsOptions {
+            dedup_strategy : DedupStrategy::None,
+            enable_log_details : true,
+            prettify_log_message : false,
+            show_common_labels : false,
+            show_labels : false,
+            show_time : false,
+            sort_order : LogsSortOrder::Descending,
+            wrap_log_message : false,
+        }
+    }
+}
+
+/// How heatmap cells are colored
+#[derive(Clone,Copy,Debug,Default,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeatmapColorMode {
+    /// Color scheme
+    #[default]
+    Scheme,
+    /// Opacity of a single color
+    Opacity,
+}
+
+/// Colors of a heatmap
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+pub struct HeatmapColor {
+    /// How cells are colored
+    pub mode : HeatmapColorMode,
+    /// Color scheme, e.g. `Oranges` or `Spectral`
+    pub scheme : String,
+    /// Color of opacity mode
+    pub fill : String,
+    /// Number of colors
+    pub steps : u32,
+    /// Reverse the scheme
+    pub reverse : bool,
+}
+
+impl Default for HeatmapColor {
+    fn default() -> HeatmapColor {
+        HeatmapColor { mode : HeatmapColorMode::Scheme, scheme : String::from("Oranges"), fill : String::from("dark-orange"), steps : 64, reverse : false }
+    }
+}
+
+/// Legend of a heatmap
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+pub struct HeatmapLegend {
+    /// Is the color scale shown?
+    pub show : bool,
+}
+
+impl Default for HeatmapLegend {
+    fn default() -> HeatmapLegend {
+        HeatmapLegend { show : true }
+    }
+}
+
+/// Y axis of a heatmap
+#[derive(Clone,Debug,Default,PartialEq,Serialize,Deserialize)]
+pub struct HeatmapAxis {
+    /// Unit of the buckets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit : Option<String>,
+    /// Highest bucket at the bottom
+    pub reverse : bool,
+}
+
+/// Options of heatmap panels
+#[derive(Clone,Debug,PartialEq,Serialize,Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapOptions {
+    /// Calculate buckets from series instead of using bucketed data
+    pub calculate : bool,
+    /// Gap between cells in pixels
+    pub cell_gap : u32,
+    /// Colors
+    pub color : HeatmapColor,
+    /// Legend
+    pub legend : HeatmapLegend,
+    /// Y axis
+    pub y_axis : HeatmapAxis,
+}
+
+impl Default for HeatmapOptions {
+    fn default() -> HeatmapOptions {
+        HeatmapOptions { calculate : false, cell_gap : 1, color : HeatmapColor::default(), legend : HeatmapLegend::default(), y_axis : HeatmapAxis::default() }
+    }
+}
+
+/// Panel with the options merged in
+fn finish<O : Serialize>(mut panel : PanelModel, options : &O) -> PanelModel {
+    if let Ok(Value::Object(options)) = serde_json::to_value(options) {
+        panel.options.get_or_insert_with(Map::new).extend(options);
+    }
+    panel
+}
+
+/// Field defaults of a panel, created when missing
+fn defaults(panel : &mut PanelModel) -> &mut FieldDefaults {
+    panel.field_config.get_or_insert_with(Default::default)
+        .defaults.get_or_insert_with(Default::default)
+}
+
+/// Empty panel of a plugin
+fn panel(title : String, r#type : &str) -> PanelModel {
+    PanelModel { title : Some(title), r#type : Some(String::from(r#type)), ..PanelModel::default() }
+}
+
+/// Implement the settings all visualization builders share
+macro_rules! common {
+    ($builder:ident) => {
+        impl $builder {
+            /// Set id of panel, by default the dashboard builder picks one
+            pub fn with_id(mut self, id : u64) -> $builder {
+                self.panel.id = Some(id);
+                self
+            }
+
+            /// Set description of panel
+            pub fn with_description(mut self, description : String) -> $builder {
+                self.panel.description = Some(description);
+                self
+            }
+
+            /// Set position on the dashboard grid
+            pub fn with_grid_pos(mut self, grid_pos : GridPos) -> $builder {
+                self.panel.grid_pos = Some(grid_pos);
+                self
+            }
+
+            /// Set data source of the queries
+            pub fn with_datasource(mut self, datasource : DataSourceRef) -> $builder {
+                self.panel.datasource = Some(datasource);
+                self
+            }
+
+            /// Add a query
+            pub fn with_target(mut self, target : Target) -> $builder {
+                self.panel.targets.get_or_insert_with(Vec::new).push(target);
+                self
+            }
+
+            /// Add a transformation
+            pub fn with_transformation(mut self, transformation : Transformation) -> $builder {
+                self.panel.transformations.get_or_insert_with(Vec::new).push(transformation);
+                self
+            }
+
+            /// Set unit of the values
+            pub fn with_unit(mut self, unit : Unit) -> $builder {
+                defaults(&mut self.panel).unit = Some(unit.to_string());
+                self
+            }
+
+            /// Set number of decimals shown
+            pub fn with_decimals(mut self, decimals : u32) -> $builder {
+                defaults(&mut self.panel).decimals = Some(decimals);
+                self
+            }
+
+            /// Set minimum of the value range, values that are not finite are ignored
+            pub fn with_min(mut self, min : f64) -> $builder {
+                defaults(&mut self.panel).min = Number::from_f64(min);
+                self
+            }
+
+            /// Set maximum of the value range, values that are not finite are ignored
+            pub fn with_max(mut self, max : f64) -> $builder {
+                defaults(&mut self.panel).max = Number::from_f64(max);
+                self
+            }
+
+            /// Set thresholds
+            pub fn with_thresholds(mut self, thresholds : Thresholds) -> $builder {
+                defaults(&mut self.panel).thresholds = Some(thresholds);
+                self
+            }
+
+            /// Set color scheme of the fields
+            pub fn with_color(mut self, mode : FieldColorMode) -> $builder {
+                let mut color = Map::new();
+                color.insert(String::from("mode"), serde_json::json!(mode));
+                defaults(&mut self.panel).color = Some(color);
+                self
+            }
+
+            /// Color all fields with a single color, e.g. `blue` or `#73BF69`
+            pub fn with_fixed_color(mut self, fixed : String) -> $builder {
+                let mut color = Map::new();
+                color.insert(String::from("mode"), Value::from("fixed"));
+                color.insert(String::from("fixedColor"), Value::from(fixed));
+                defaults(&mut self.panel).color = Some(color);
+                self
+            }
+
+            /// Override a setting for the field with a name, e.g. `custom.width` of a column
+            pub fn with_field_override(mut self, name : String, property : Property) -> $builder {
+                let field_config = self.panel.field_config.get_or_insert_with(Default::default);
+                let overrides = field_config.overrides.get_or_insert_with(Vec::new);
+                match overrides.iter_mut().find(|o| o.matcher == Some(Matcher::by_name(name.clone()))) {
+                    Some(o) => o.properties.get_or_insert_with(Vec::new).push(property),
+                    None => overrides.push(Override { matcher : Some(Matcher::by_name(name)), properties : Some(vec![property]), extra : Map::new() }),
+                }
+                self
+            }
+        }
+    };
+}
+
+/// Builder of time series panels
+#[derive(Clone,Debug,PartialEq)]
+pub struct TimeSeriesBuilder {
+    panel : PanelModel,
+    options : TimeSeriesOptions,
+    field : TimeSeriesFieldOptions,
+}
+
+common!(TimeSeriesBuilder);
+
+impl TimeSeriesBuilder {
+    /// Create a time series builder
+    pub fn new(title : String) -> TimeSeriesBuilder {
+        TimeSeriesBuilder { panel : panel(title, "timeseries"), options : TimeSeriesOptions::default(), field : TimeSeriesFieldOptions::default() }
+    }
+
+    /// Set all options
+    pub fn with_options(mut self, options : TimeSeriesOptions) -> TimeSeriesBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Set legend
+    pub fn with_legend(mut self, legend : LegendOptions) -> TimeSeriesBuilder {
+        self.options.legend = legend;
+        self
+    }
+
+    /// Set series shown in the tooltip
+    pub fn with_tooltip(mut self, mode : TooltipMode) -> TimeSeriesBuilder {
+        self.options.tooltip.mode = mode;
+        self
+    }
+
+    /// Set how points are drawn
+    pub fn with_draw_style(mut self, style : DrawStyle) -> TimeSeriesBuilder {
+        self.field.draw_style = style;
+        self
+    }
+
+    /// Set width of lines in pixels
+    pub fn with_line_width(mut self, width : u32) -> TimeSeriesBuilder {
+        self.field.line_width = width;
+        self
+    }
+
+    /// Set opacity of the area below lines, capped at 100
+    pub fn with_fill_opacity(mut self, opacity : u32) -> TimeSeriesBuilder {
+        self.field.fill_opacity = opacity.min(100);
+        self
+    }
+
+    /// Set stacking of series
+    pub fn with_stacking(mut self, mode : StackingMode) -> TimeSeriesBuilder {
+        self.field.stacking.mode = mode;
+        self
+    }
+
+    /// Build the panel
+    pub fn build(mut self) -> PanelModel {
+        if let Ok(Value::Object(field)) = serde_json::to_value(&self.field) {
+            defaults(&mut self.panel).custom.get_or_insert_with(Map::new).extend(field);
+        }
+        finish(self.panel, &self.options)
+    }
+}
+
+/// Builder of stat panels
+#[derive(Clone,Debug,PartialEq)]
+pub struct StatBuilder {
+    panel : PanelModel,
+    options : StatOptions,
+}
+
+common!(StatBuilder);
+
+impl StatBuilder {
+    /// Create a stat builder
+    pub fn new(title : String) -> StatBuilder {
+        StatBuilder { panel : panel(title, "stat"), options : StatOptions::default() }
+    }
+
+    /// Set all options
+    pub fn with_options(mut self, options : StatOptions) -> StatBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Set how the value is calculated
+    pub fn with_reduce_calc(mut self, calc : Calc) -> StatBuilder {
+        self.options.reduce_options.calcs = vec![calc];
+        self
+    }
+
+    /// Set what the color applies to
+    pub fn with_color_mode(mut self, mode : StatColorMode) -> StatBuilder {
+        self.options.color_mode = mode;
+        self
+    }
+
+    /// Set graph behind the value
+    pub fn with_graph_mode(mut self, mode : GraphMode) -> StatBuilder {
+        self.options.graph_mode = mode;
+        self
+    }
+
+    /// Set text shown
+    pub fn with_text_mode(mut self, mode : StatTextMode) -> StatBuilder {
+        self.options.text_mode = mode;
+        self
+    }
+
+    /// Set layout of several values
+    pub fn with_orientation(mut self, orientation : Orientation) -> StatBuilder {
+        self.options.orientation = orientation;
+        self
+    }
+
+    /// Build the panel
+    pub fn build(self) -> PanelModel {
+        finish(self.panel, &self.options)
+    }
+}
+
+/// Builder of gauge panels
+#[derive(Clone,Debug,PartialEq)]
+pub struct GaugeBuilder {
+    panel : PanelModel,
+    options : GaugeOptions,
+}
+
+common!(GaugeBuilder);
+
+impl GaugeBuilder {
+    /// Create a gauge builder
+    pub fn new(title : String) -> GaugeBuilder {
+        GaugeBuilder { panel : panel(title, "gauge"), options : GaugeOptions::default() }
+    }
+
+    /// Set all options
+    pub fn with_options(mut self, options : GaugeOptions) -> GaugeBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Set how the value is calculated
+    pub fn with_reduce_calc(mut self, calc : Calc) -> GaugeBuilder {
+        self.options.reduce_options.calcs = vec![calc];
+        self
+    }
+
+    /// Set layout of several gauges
+    pub fn with_orientation(mut self, orientation : Orientation) -> GaugeBuilder {
+        self.options.orientation = orientation;
+        self
+    }
+
+    /// Show the threshold values
+    pub fn with_threshold_labels(mut self, show : bool) -> GaugeBuilder {
+        self.options.show_threshold_labels = show;
+        self
+    }
+
+    /// Show the thresholds as a band around the gauge
+    pub fn with_threshold_markers(mut self, show : bool) -> GaugeBuilder {
+        self.options.show_threshold_markers = show;
+        self
+    }
+
+    /// Build the panel
+    pub fn build(self) -> PanelModel {
+        finish(self.panel, &self.options)
+    }
+}
+
+/// Builder of bar gauge panels
+#[derive(Clone,Debug,PartialEq)]
+pub struct BarGaugeBuilder {
+    panel : PanelModel,
+    options : BarGaugeOptions,
+}
+
+common!(BarGaugeBuilder);
+
+impl BarGaugeBuilder {
+    /// Create a bar gauge builder
+    pub fn new(title : String) -> BarGaugeBuilder {
+        BarGaugeBuilder { panel : panel(title, "bargauge"), options : BarGaugeOptions::default() }
+    }
+
+    /// Set all options
+    pub fn with_options(mut self, options : BarGaugeOptions) -> BarGaugeBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Set how the value is calculated
+    pub fn with_reduce_calc(mut self, calc : Calc) -> BarGaugeBuilder {
+        self.options.reduce_options.calcs = vec![calc];
+        self
+    }
+
+    /// Set direction of the bars
+    pub fn with_orientation(mut self, orientation : Orientation) -> BarGaugeBuilder {
+        self.options.orientation = orientation;
+        self
+    }
+
+    /// Set style of the bars
+    pub fn with_display_mode(mut self, mode : BarGaugeDisplayMode) -> BarGaugeBuilder {
+        self.options.display_mode = mode;
+        self
+    }
+
+    /// Show the unfilled part of the bars
+    pub fn with_show_unfilled(mut self, show : bool) -> BarGaugeBuilder {
+        self.options.show_unfilled = show;
+        self
+    }
+
+    /// Build the panel
+    pub fn build(self) -> PanelModel {
+        finish(self.panel, &self.options)
+    }
+}
+
+/// Builder of table panels
+/// # Example
+/// ```
+/// # use grafana_lib::community::visualization::TableBuilder;
+/// let panel = TableBuilder::new(String::from("Targets"))
+///     .with_column_width(String::from("instance"), 220)
+///     .with_hidden_column(String::from("Time"))
+///     .build();
+/// assert_eq!(panel.field_config.unwrap().overrides.map(|o| o.len()), Some(2));
+/// ```
+#[derive(Clone,Debug,PartialEq)]
+pub struct TableBuilder {
+    panel : PanelModel,
+    options : TableOptions,
+}
+
+common!(TableBuilder);
+
+impl TableBuilder {
+    /// Create a table builder
+    pub fn new(title : String) -> TableBuilder {
+        TableBuilder { panel : panel(title, "table"), options : TableOptions::default() }
+    }
+
+    /// Set all options
+    pub fn with_options(mut self, options : TableOptions) -> TableBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Set height of the rows
+    pub fn with_cell_height(mut self, height : CellHeight) -> TableBuilder {
+        self.options.cell_height = height;
+        self
+    }
+
+    /// Show the header
+    pub fn with_show_header(mut self, show : bool) -> TableBuilder {
+        self.options.show_header = show;
+        self
+    }
+
+    /// Show a footer with a calculation of each field
+    pub fn with_footer(mut self, calc : Calc) -> TableBuilder {
+        self.options.footer.show = true;
+        self.options.footer.reducer = vec![calc];
+        self
+    }
+
+    /// Allow filtering by the values of the columns
+    pub fn with_filterable(mut self, filterable : bool) -> TableBuilder {
+        defaults(&mut self.panel).custom.get_or_insert_with(Map::new).insert(String::from("filterable"), Value::from(filterable));
+        self
+    }
+
+    /// Set width of a column in pixels
+    pub fn with_column_width(self, name : String, width : u32) -> TableBuilder {
+        self.with_field_override(name, Property::new(String::from("custom.width"), Value::from(width)))
+    }
+
+    /// Hide a column
+    pub fn with_hidden_column(self, name : String) -> TableBuilder {
+        self.with_field_override(name, Property::new(String::from("custom.hidden"), Value::from(true)))
+    }
+
+    /// Build the panel
+    pub fn build(self) -> PanelModel {
+        finish(self.panel, &self.options)
+    }
+}
+
+/// Builder of text panels
+#[derive(Clone,Debug,PartialEq)]
+pub struct TextBuilder {
+    panel : PanelModel,
+    options : TextOptions,
+}
+
+common!(TextBuilder);
+
+impl TextBuilder {
+    /// Create a text builder
+    pub fn new(title : String) -> TextBuilder {
+        TextBuilder { panel : panel(title, "text"), options : TextOptions::default() }
+    }
+
+    /// Set markdown content
+    pub fn with_markdown(mut self, content : String) -> TextBuilder {
+        self.options = TextOptions { mode : TextMode::Markdown, content };
+        self
+    }
+
+    /// Set HTML content
+    pub fn with_html(mut self, content : String) -> TextBuilder {
+        self.options = TextOptions { mode : TextMode::Html, content };
+        self
+    }
+
+    /// Set all options
+    pub fn with_options(mut self, options : TextOptions) -> TextBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Build the panel
+    pub fn build(self) -> PanelModel {
+        finish(self.panel, &self.options)
+    }
+}
+
+/// Builder of logs panels
+#[derive(Clone,Debug,PartialEq)]
+pub struct LogsBuilder {
+    panel : PanelModel,
+    options : LogsOptions,
+}
+
+common!(LogsBuilder);
+
+impl LogsBuilder {
+    /// Create a logs builder
+    pub fn new(title : String) -> LogsBuilder {
+        LogsBuilder { panel : panel(title, "logs"), options : LogsOptions::default() }
+    }
+
+    /// Set all options
+    pub fn with_options(mut self, options : LogsOptions) -> LogsBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Show the time of each line
+    pub fn with_show_time(mut self, show : bool) -> LogsBuilder {
+        self.options.show_time = show;
+        self
+    }
+
+    /// Show the labels of each line
+    pub fn with_show_labels(mut self, show : bool) -> LogsBuilder {
+        self.options.show_labels = show;
+        self
+    }
+
+    /// Wrap long lines
+    pub fn with_wrap(mut self, wrap : bool) -> LogsBuilder {
+        self.options.wrap_log_message = wrap;
+        self
+    }
+
+    /// Set removal of duplicate lines
+    pub fn with_dedup(mut self, strategy : DedupStrategy) -> LogsBuilder {
+        self.options.dedup_strategy = strategy;
+        self
+    }
+
+    /// Set order of the lines
+    pub fn with_sort_order(mut self, order : LogsSortOrder) -> LogsBuilder {
+        self.options.sort_order = order;
+        self
+    }
+
+    /// Build the panel
+    pub fn build(self) -> PanelModel {
+        finish(self.panel, &self.options)
+    }
+}
+
+/// Builder of heatmap panels
+#[derive(Clone,Debug,PartialEq)]
+pub struct HeatmapBuilder {
+    panel : PanelModel,
+    options : HeatmapOptions,
+}
+
+common!(HeatmapBuilder);
+
+impl HeatmapBuilder {
+    /// Create a heatmap builder
+    pub fn new(title : String) -> HeatmapBuilder {
+        HeatmapBuilder { panel : panel(title, "heatmap"), options : HeatmapOptions::default() }
+    }
+
+    /// Set all options
+    pub fn with_options(mut self, options : HeatmapOptions) -> HeatmapBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Calculate buckets from series instead of using bucketed data
+    pub fn with_calculate(mut self, calculate : bool) -> HeatmapBuilder {
+        self.options.calculate = calculate;
+        self
+    }
+
+    /// Set color scheme, e.g. `Spectral`
+    pub fn with_scheme(mut self, scheme : String) -> HeatmapBuilder {
+        self.options.color.mode = HeatmapColorMode::Scheme;
+        self.options.color.scheme = scheme;
+        self
+    }
+
+    /// Set gap between cells in pixels
+    pub fn with_cell_gap(mut self, gap : u32) -> HeatmapBuilder {
+        self.options.cell_gap = gap;
+        self
+    }
+
+    /// Set unit of the buckets
+    pub fn with_y_unit(mut self, unit : Unit) -> HeatmapBuilder {
+        self.options.y_axis.unit = Some(unit.to_string());
+        self
+    }
+
+    /// Build the panel
+    pub fn build(self) -> PanelModel {
+        finish(self.panel, &self.options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_defaults_match_grafana() {
+        let stat = StatBuilder::new(String::from("CPU requests")).build();
+        assert_eq!(Value::Object(stat.options.unwrap()), json!({
+            "colorMode" : "value", "graphMode" : "area", "justifyMode" : "auto", "orientation" : "auto",
+            "reduceOptions" : { "calcs" : ["lastNotNull"], "fields" : "", "values" : false },
+            "showPercentChange" : false, "textMode" : "auto", "wideLayout" : true,
+        }));
+        let gauge = GaugeBuilder::new(String::from("Memory used")).build();
+        assert_eq!(Value::Object(gauge.options.unwrap()), json!({
+            "minVizHeight" : 75, "minVizWidth" : 75, "orientation" : "auto",
+            "reduceOptions" : { "calcs" : ["lastNotNull"], "fields" : "", "values" : false },
+            "showThresholdLabels" : false, "showThresholdMarkers" : true,
+        }));
+    }
+
+    #[test]
+    fn test_time_series() {
+        let panel = TimeSeriesBuilder::new(String::from("Requests"))
+            .with_unit(Unit::RequestsPerSecond)
+            .with_min(0.0)
+            .with_color(FieldColorMode::PaletteClassic)
+            .with_draw_style(DrawStyle::Bars)
+            .with_stacking(StackingMode::Normal)
+            .with_fill_opacity(250)
+            .with_tooltip(TooltipMode::Multi)
+            .build();
+        let json = serde_json::to_value(&panel).unwrap();
+        assert_eq!(json["type"], "timeseries");
+        assert_eq!(json["fieldConfig"]["defaults"], json!({
+            "unit" : "reqps", "min" : 0.0, "color" : { "mode" : "palette-classic" },
+            "custom" : { "drawStyle" : "bars", "lineWidth" : 1, "fillOpacity" : 100, "stacking" : { "mode" : "normal", "group" : "A" } },
+        }));
+        assert_eq!(json["options"]["tooltip"], json!({ "mode" : "multi", "sort" : "none" }));
+        assert_eq!(json["options"]["legend"]["displayMode"], "list");
+    }
+
+    #[test]
+    fn test_field_override() {
+        let panel = TableBuilder::new(String::from("Targets"))
+            .with_column_width(String::from("instance"), 220)
+            .with_hidden_column(String::from("instance"))
+            .build();
+        let json = serde_json::to_value(&panel).unwrap();
+        assert_eq!(json["fieldConfig"]["overrides"], json!([{
+            "matcher" : { "id" : "byName", "options" : "instance" },
+            "properties" : [{ "id" : "custom.width", "value" : 220 }, { "id" : "custom.hidden", "value" : true }],
+        }]));
+    }
+
+    #[test]
+    fn test_panels_round_trip() {
+        let panels = vec![
+            LogsBuilder::new(String::from("Logs")).with_dedup(DedupStrategy::Signature).with_sort_order(LogsSortOrder::Ascending).build(),
+            HeatmapBuilder::new(String::from("Latency")).with_y_unit(Unit::Seconds).build(),
+            BarGaugeBuilder::new(String::from("Disks")).with_display_mode(BarGaugeDisplayMode::Lcd).build(),
+            TextBuilder::new(String::from("Notes")).with_html(String::from("<b>on call</b>")).build(),
+        ];
+        for panel in panels {
+            let json = serde_json::to_value(&panel).unwrap();
+            let read : PanelModel = serde_json::from_value(json.clone()).unwrap();
+            assert_eq!(read, panel);
+        }
+    }
+}
@@ -8,6 +8,7 @@
 #![warn(missing_docs)]
 
 /// Common modules
+pub mod async_client;
 pub mod client;
 pub mod common;
 
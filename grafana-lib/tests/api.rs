@@ -0,0 +1,72 @@
+//! Typed JSON helpers on Api, blocking and async
+mod common;
+
+use common::{Reply,StubServer};
+use grafana_lib::common::api::Api;
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::error::GrafanaError;
+use serde_json::{json,Value};
+
+fn api(server : &StubServer) -> Api {
+    Api::new(server.url(),Auth::Anonymous)
+}
+
+#[test]
+fn test_verbs_blocking() {
+    let server = StubServer::start(vec![
+        Reply::json(r#"{"id":1}"#),
+        Reply::json(r#"{"id":2}"#),
+        Reply::json(r#"{"id":3}"#),
+        Reply::json(r#"{"id":4}"#),
+        Reply::json(""),
+    ]);
+    let api = api(&server);
+    let body = json!({"title":"Ops"});
+    let get : Value = api.get_json("folders/abc").unwrap();
+    let post : Value = api.post_json("folders", &body).unwrap();
+    let put : Value = api.put_json("folders/abc", &body).unwrap();
+    let patch : Value = api.patch_json("folders/abc", &body).unwrap();
+    api.delete::<()>("folders/abc").unwrap();
+    assert_eq!((get["id"].clone(),post["id"].clone(),put["id"].clone(),patch["id"].clone()),(json!(1),json!(2),json!(3),json!(4)));
+
+    let requests = server.requests();
+    let seen : Vec<(&str,&str)> = requests.iter().map(|r| (r.method.as_str(),r.path.as_str())).collect();
+    assert_eq!(seen, vec![
+        ("GET","/api/folders/abc"),
+        ("POST","/api/folders"),
+        ("PUT","/api/folders/abc"),
+        ("PATCH","/api/folders/abc"),
+        ("DELETE","/api/folders/abc"),
+    ]);
+    assert_eq!(requests[1].header("content-type"), Some("application/json"));
+    assert_eq!(serde_json::from_str::<Value>(&requests[1].body).unwrap(), body);
+}
+
+#[test]
+fn test_status_checked() {
+    let server = StubServer::start(vec![Reply::status(412, r#"{"message":"version-mismatch","status":"version-mismatch"}"#)]);
+    let result = api(&server).post_json::<_,Value>("dashboards/db", &json!({}));
+    assert!(matches!(result, Err(GrafanaError::VersionMismatch(_))));
+}
+
+#[tokio::test]
+async fn test_verbs_async() {
+    let server = StubServer::start(vec![
+        Reply::json(r#"[1,2]"#),
+        Reply::json(r#"{"id":2}"#),
+        Reply::json(r#"{"id":3}"#),
+        Reply::json(r#"{"id":4}"#),
+        Reply::status(404, r#"{"message":"Folder not found"}"#),
+    ]);
+    let api = api(&server);
+    let body = json!({"title":"Ops"});
+    let get : Vec<u8> = api.get_json_async("folders").await.unwrap();
+    assert_eq!(get, vec![1,2]);
+    let _ : Value = api.post_json_async("folders", &body).await.unwrap();
+    let _ : Value = api.put_json_async("folders/abc", &body).await.unwrap();
+    let _ : Value = api.patch_json_async("folders/abc", &body).await.unwrap();
+    let deleted = api.delete_async::<Value>("folders/abc").await;
+    assert!(matches!(deleted, Err(GrafanaError::NotFound(_))));
+    let methods : Vec<String> = server.requests().into_iter().map(|r| r.method).collect();
+    assert_eq!(methods, vec!["GET","POST","PUT","PATCH","DELETE"]);
+}
@@ -0,0 +1,54 @@
+//! Async client against the stub server
+mod common;
+
+use common::{Reply,StubServer};
+use grafana_lib::async_client::AsyncClient;
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::error::GrafanaError;
+
+fn client(server : &StubServer) -> AsyncClient {
+    AsyncClient::new(server.url()).with_auth(Auth::bearer(String::from("glsa_token")))
+}
+
+#[tokio::test]
+async fn test_search_async() {
+    let server = StubServer::start(vec![
+        Reply::json(r#"[{"uid":"abc","title":"Home"}]"#),
+        Reply::json(r#"[{"id":3,"uid":"ops","title":"Ops","isStarred":false}]"#),
+    ]);
+    let client = client(&server);
+    let dashboards = client.search().dashboard_async(None).await.unwrap();
+    let folders = client.search().folder_async(Some(String::from("Ops"))).await.unwrap();
+    assert_eq!(dashboards[0].title, Some(String::from("Home")));
+    assert_eq!(folders[0].title, "Ops");
+    let paths : Vec<String> = server.requests().into_iter().map(|r| r.path).collect();
+    assert_eq!(paths, vec!["/api/search?type=dash-db","/api/search?type=dash-folder&query=Ops"]);
+}
+
+#[tokio::test]
+async fn test_modules_async() {
+    let server = StubServer::start(vec![
+        Reply::json(r#"{"meta":{"folderTitle":"General"},"dashboard":{"uid":"abc","title":"Home","version":3}}"#),
+        Reply::json(r#"[{"dashboardId":1,"panelId":2,"text":"deploy"}]"#),
+        Reply::json(r#"[{"name":"Prometheus","type":"prometheus"}]"#),
+        Reply::json(r#"[{"uid":"r1","title":"High CPU","ruleGroup":"node"}]"#),
+        Reply::json(r#"[{"uid":"c1","name":"oncall","type":"email"}]"#),
+    ]);
+    let client = client(&server);
+    let dashboard = client.dashboard().get_async(String::from("abc")).await.unwrap();
+    assert_eq!(dashboard.dashboard.version, Some(3));
+    assert_eq!(client.annotations().list_async(None,Some(1)).await.unwrap().len(), 1);
+    assert_eq!(client.data_source().get_async(None).await.unwrap()[0].name, "Prometheus");
+    assert_eq!(client.alerting_provisioning().alert_rule().list_async().await.unwrap()[0].title, "High CPU");
+    assert_eq!(client.alerting_provisioning().contact_point().list_async().await.unwrap()[0].name, "oncall");
+    let requests = server.requests();
+    assert_eq!(requests.len(), 5);
+    requests.iter().for_each(|r| assert_eq!(r.header("authorization"), Some("Bearer glsa_token")));
+}
+
+#[tokio::test]
+async fn test_errors_async() {
+    let server = StubServer::start(vec![Reply::status(404, r#"{"message":"Dashboard not found"}"#)]);
+    let result = client(&server).dashboard().get_async(String::from("missing")).await;
+    assert!(matches!(result, Err(GrafanaError::NotFound(_))));
+}
@@ -0,0 +1,123 @@
+//! Check credentials reach the server on blocking and async requests
+mod common;
+
+use common::{Reply,StubServer};
+use grafana_lib::client::Client;
+use grafana_lib::common::api::Api;
+use grafana_lib::common::auth::{Auth,TokenSource};
+use grafana_lib::common::error::GrafanaError;
+use reqwest::Method;
+
+fn blocking_header(auth : Auth) -> Option<String> {
+    let server = StubServer::start(vec![Reply::json("[]")]);
+    let _result = Client::new(server.url()).with_auth(auth).search().dashboard(None);
+    server.requests()[0].header("authorization").map(String::from)
+}
+
+#[test]
+fn test_bearer_blocking() {
+    assert_eq!(blocking_header(Auth::bearer(String::from("glsa_token"))), Some(String::from("Bearer glsa_token")));
+}
+
+#[test]
+fn test_basic_blocking() {
+    // base64("user:pass")
+    assert_eq!(blocking_header(Auth::basic(String::from("user"),String::from("pass"))), Some(String::from("Basic dXNlcjpwYXNz")));
+}
+
+#[test]
+fn test_organization_basic() {
+    let server = StubServer::start(vec![Reply::json(r#"{"orgId":2,"message":"Organization created"}"#)]);
+    let client = Client::new(server.url()).with_auth(Auth::bearer(String::from("glsa_token")));
+    let organization = client.organization()
+        .create(String::from("MyOrg"))
+        .with_user(String::from("user"))
+        .with_pass(String::from("pass"));
+    assert!(!format!("{organization:?}").contains("\"pass\""), "{organization:?}");
+    let created = organization.send().unwrap();
+    assert_eq!(created.to_string(), "Organization created [2]");
+    let request = &server.requests()[0];
+    assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/api/orgs"));
+    assert_eq!(request.header("authorization"), Some("Basic dXNlcjpwYXNz"));
+    assert_eq!(request.body, r#"{"name":"MyOrg"}"#);
+
+    let result = client.organization().create(String::from("MyOrg")).with_user(String::from("user")).send();
+    assert!(matches!(result, Err(GrafanaError::Config(_))), "{result:?}");
+}
+
+#[test]
+fn test_anonymous_blocking() {
+    assert_eq!(blocking_header(Auth::Anonymous), None);
+}
+
+#[test]
+fn test_every_verb_blocking() {
+    let methods = [Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE];
+    let server = StubServer::start(methods.iter().map(|_| Reply::json("{}")).collect());
+    let api = Api::new(server.url(),Auth::bearer(String::from("glsa_token")));
+    for method in methods.iter() {
+        api.execute(api.request(method.clone(), "dashboards/uid/abc")).unwrap();
+    }
+    let requests = server.requests();
+    assert_eq!(requests.len(), methods.len());
+    requests.iter().for_each(|r| assert_eq!(r.header("authorization"), Some("Bearer glsa_token"), "{}", r.method));
+}
+
+#[tokio::test]
+async fn test_bearer_async() {
+    let server = StubServer::start(vec![Reply::json("[]"),Reply::json("{}")]);
+    let api = Api::new(server.url(),Auth::bearer(String::from("glsa_token")));
+    api.get_async(String::from("search")).await.unwrap();
+    api.execute_async(api.request(Method::DELETE, "dashboards/uid/abc")).await.unwrap();
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    requests.iter().for_each(|r| assert_eq!(r.header("authorization"), Some("Bearer glsa_token")));
+}
+
+#[tokio::test]
+async fn test_basic_async() {
+    let server = StubServer::start(vec![Reply::json("{}")]);
+    let api = Api::new(server.url(),Auth::basic(String::from("user"),String::from("pass")));
+    api.post_json_async::<_,serde_json::Value>("orgs", &serde_json::json!({"name":"MyOrg"})).await.unwrap();
+    assert_eq!(server.requests()[0].header("authorization"), Some("Basic dXNlcjpwYXNz"));
+}
+
+#[test]
+fn test_token_file_rotation() {
+    let path = std::env::temp_dir().join(format!("grafana-lib-token-{}",std::process::id()));
+    std::fs::write(&path, "glsa_old\n").unwrap();
+    let server = StubServer::start(vec![Reply::json("{}"), Reply::status(401, "{}"), Reply::json("{}")]);
+    let api = Api::new(server.url(),Auth::Source(TokenSource::file(&path)));
+    api.get(String::from("user")).unwrap();
+    // The cached token is still used after the file changes, until the server rejects it
+    std::fs::write(&path, "glsa_new").unwrap();
+    assert!(matches!(api.get(String::from("user")), Err(GrafanaError::Unauthorized(_))));
+    api.get(String::from("user")).unwrap();
+    let headers : Vec<_> = server.requests().iter().map(|r| r.header("authorization").map(String::from)).collect();
+    assert_eq!(headers, vec![Some(String::from("Bearer glsa_old")), Some(String::from("Bearer glsa_old")), Some(String::from("Bearer glsa_new"))]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_token_source_error() {
+    let server = StubServer::start(vec![]);
+    let api = Api::new(server.url(),Auth::Source(TokenSource::file("/nonexistent/grafana-token")));
+    assert!(matches!(api.get(String::from("user")), Err(GrafanaError::Config(_))));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn test_debug_hides_credentials() {
+    let api = Api::new(String::from("http://localhost:3000"),Auth::basic(String::from("admin"),String::from("s3cret")));
+    assert!(!format!("{api:?}").contains("s3cret"));
+    assert!(!serde_json::to_string(&api).unwrap().contains("s3cret"));
+}
+
+#[test]
+fn test_serialized_api_is_not_reused_as_credentials() {
+    let api = Api::new(String::from("http://localhost:3000"),Auth::bearer(String::from("glsa_token")));
+    let json = serde_json::to_string(&api).unwrap();
+    assert!(serde_json::from_str::<Api>(&json).is_err());
+    let anonymous = serde_json::to_string(&Api::new(String::from("http://localhost:3000"),Auth::Anonymous)).unwrap();
+    assert!(serde_json::from_str::<Api>(&anonymous).is_ok());
+}
@@ -0,0 +1,94 @@
+//! ClientBuilder validation and transport settings
+mod common;
+
+use std::time::{Duration,Instant};
+
+use common::{Reply,StubServer};
+use grafana_lib::client::{Client,ClientBuilder};
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::error::GrafanaError;
+
+#[test]
+fn test_trailing_slashes() {
+    let server = StubServer::start(vec![Reply::json("[]")]);
+    let client = Client::builder(&format!("{}///",server.url()))
+        .with_auth(Auth::Anonymous)
+        .build()
+        .unwrap();
+    client.search().dashboard(None).unwrap();
+    assert_eq!(server.requests()[0].path, "/api/search?type=dash-db");
+}
+
+#[test]
+fn test_sub_path() {
+    let server = StubServer::start(vec![Reply::json("[]")]);
+    let client = Client::builder(&format!("{}/grafana/",server.url()))
+        .with_auth(Auth::Anonymous)
+        .build()
+        .unwrap();
+    client.search().dashboard(None).unwrap();
+    assert_eq!(server.requests()[0].path, "/grafana/api/search?type=dash-db");
+}
+
+#[test]
+fn test_invalid_url() {
+    for url in ["localhost:3000", "ftp://localhost", "not a url", "http://localhost:3000/?orgId=1"] {
+        let result = ClientBuilder::new(url).with_auth(Auth::Anonymous).build();
+        assert!(matches!(result, Err(GrafanaError::Config(_))), "{url} should be rejected");
+    }
+}
+
+#[test]
+fn test_missing_credential() {
+    let result = ClientBuilder::new("http://localhost:3000").build();
+    assert!(matches!(result, Err(GrafanaError::Config(_))));
+}
+
+#[test]
+fn test_headers() {
+    let server = StubServer::start(vec![Reply::json("[]"),Reply::json("[]")]);
+    let client = ClientBuilder::new(&server.url())
+        .with_token(String::from("glsa_token"))
+        .with_org(4)
+        .build()
+        .unwrap();
+    client.search().dashboard(None).unwrap();
+    let custom = ClientBuilder::new(&server.url())
+        .with_auth(Auth::Anonymous)
+        .with_user_agent(String::from("backup-job/1.0"))
+        .build()
+        .unwrap();
+    custom.search().dashboard(None).unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].header("authorization"), Some("Bearer glsa_token"));
+    assert_eq!(requests[0].header("x-grafana-org-id"), Some("4"));
+    assert_eq!(requests[0].header("user-agent"), Some(concat!("grafana-lib/",env!("CARGO_PKG_VERSION"))));
+    assert_eq!(requests[1].header("user-agent"), Some("backup-job/1.0"));
+    assert_eq!(requests[1].header("x-grafana-org-id"), None);
+}
+
+#[test]
+fn test_timeout() {
+    let server = StubServer::start(vec![Reply::Hang(Duration::from_secs(5))]);
+    let client = ClientBuilder::new(&server.url())
+        .with_auth(Auth::Anonymous)
+        .with_timeout(Duration::from_millis(200))
+        .build()
+        .unwrap();
+    let start = Instant::now();
+    let result = client.search().dashboard(None);
+    assert!(matches!(result, Err(GrafanaError::Transport(_))));
+    assert!(start.elapsed() < Duration::from_secs(4));
+}
+
+#[tokio::test]
+async fn test_build_async() {
+    let server = StubServer::start(vec![Reply::json("[]")]);
+    let client = ClientBuilder::new(&server.url())
+        .with_token(String::from("glsa_token"))
+        .build_async()
+        .unwrap();
+    client.search().dashboard_async(None).await.unwrap();
+    assert_eq!(server.requests()[0].header("authorization"), Some("Bearer glsa_token"));
+}
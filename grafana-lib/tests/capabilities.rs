@@ -0,0 +1,91 @@
+//! Health, frontend settings and capability detection
+mod common;
+
+use common::{Reply,StubServer};
+use grafana_lib::async_client::AsyncClient;
+use grafana_lib::client::Client;
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::capabilities::{Capability,Version};
+use grafana_lib::common::error::GrafanaError;
+use grafana_lib::common::retry::RetryPolicy;
+use grafana_mock::MockServer;
+
+fn client(url : &str) -> Client {
+    Client::builder(url).with_auth(Auth::Anonymous).build().unwrap()
+}
+
+#[test]
+fn test_health() {
+    let server = MockServer::start().with_version("10.4.2").require_token("glsa_test");
+    let health = client(&server.url()).health().unwrap();
+    assert!(health.is_ok());
+    assert_eq!(health.version.as_deref(), Some("10.4.2"));
+    assert_eq!(health.commit.as_deref(), Some("mock"));
+}
+
+#[test]
+fn test_health_database_failing() {
+    let server = StubServer::start(vec![Reply::status(503, r#"{"commit":"abc","database":"failing","version":"10.4.0"}"#)]);
+    let health = client(&server.url()).health().unwrap();
+    assert!(!health.is_ok());
+    assert_eq!(health.database, "failing");
+}
+
+#[test]
+fn test_health_not_retried() {
+    let server = StubServer::start(vec![Reply::status(503, r#"{"database":"failing"}"#), Reply::json(r#"{"database":"ok"}"#)]);
+    let client = Client::builder(&server.url()).with_auth(Auth::Anonymous).with_retry(RetryPolicy::default()).build().unwrap();
+    assert!(!client.health().unwrap().is_ok());
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn test_frontend_settings() {
+    let server = MockServer::start().with_version("11.3.0").with_feature_toggle("nestedFolders", true).with_feature_toggle("publicDashboards", false);
+    let settings = client(&server.url()).frontend_settings().unwrap();
+    assert_eq!(settings.build_info.version, "11.3.0");
+    assert_eq!(settings.build_info.edition, "Open Source");
+    assert_eq!(settings.enabled_toggles(), vec!["nestedFolders"]);
+}
+
+#[test]
+fn test_capabilities_detected_once() {
+    let server = MockServer::start().with_version("12.0.1");
+    let client = client(&server.url());
+    let capabilities = client.capabilities().unwrap();
+    assert_eq!(capabilities.version, Some(Version::new(12, 0, 1)));
+    assert!(capabilities.supports(Capability::NestedFolders));
+    client.for_org(2).capabilities().unwrap();
+    let settings_requests = server.requests().iter().filter(|r| r.path == "/api/frontend/settings").count();
+    assert_eq!(settings_requests, 1);
+}
+
+#[test]
+fn test_subfolders_unsupported() {
+    let server = MockServer::start().with_version("10.4.0");
+    server.add_folder("ops", "Operations");
+    let result = client(&server.url()).search().subfolders("ops");
+    assert!(matches!(result, Err(GrafanaError::Unsupported(_))), "{result:?}");
+    assert!(server.requests().iter().all(|r| r.path != "/api/folders"));
+}
+
+#[test]
+fn test_subfolders() {
+    let server = MockServer::start().with_version("10.4.0").with_feature_toggle("nestedFolders", true);
+    server.add_folder("ops", "Operations");
+    server.add_subfolder("db", "Databases", "ops");
+    server.add_folder("team", "Team");
+    let children = client(&server.url()).search().subfolders("ops").unwrap();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].title, "Databases");
+}
+
+#[tokio::test]
+async fn test_capabilities_async() {
+    let server = MockServer::start().with_version("11.0.0");
+    server.add_folder("ops", "Operations");
+    let client = AsyncClient::new(server.url()).with_auth(Auth::Anonymous);
+    assert!(client.other().capabilities_async().await.unwrap().supports(Capability::NestedFolders));
+    assert!(client.search().subfolders_async("ops").await.unwrap().is_empty());
+    assert_eq!(client.other().health_async().await.unwrap().version.as_deref(), Some("11.0.0"));
+}
@@ -0,0 +1,77 @@
+//! Record and replay through cassette files
+mod common;
+
+use common::{Reply,StubServer};
+use grafana_lib::client::{Client,ClientBuilder};
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::cassette::{Cassette,REDACTED};
+use grafana_lib::common::config::Config;
+use grafana_lib::common::error::GrafanaError;
+use grafana_lib::common::transport::ReqwestTransport;
+use serde_json::{json,Value};
+
+fn client(url : &str, cassette : Cassette) -> Client {
+    ClientBuilder::new(url)
+        .with_token(String::from("glsa_secret_token"))
+        .with_transport(cassette)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_record_then_replay() {
+    let server = StubServer::start(vec![
+        Reply::json(r#"[{"id":1,"uid":"abc","title":"Home","type":"dash-db"}]"#),
+        Reply::json(r#"{"id":2,"name":"prometheus","basicAuthPassword":"hunter2","secureJsonFields":{"basicAuthPassword":true}}"#),
+        Reply::status(404, r#"{"message":"Dashboard not found"}"#),
+    ]);
+    let path = std::env::temp_dir().join(format!("grafana-lib-cassette-{}.json",std::process::id()));
+    let recorder = Cassette::record(&path, ReqwestTransport::new(Config::new(server.url())));
+    let recording = client(&server.url(), recorder.clone());
+    recording.search().dashboard(None).unwrap();
+    recording.api.post_json::<_,Value>("datasources", &json!({"name":"prometheus","secureJsonData":{"password":"hunter2"}})).unwrap();
+    assert!(recording.dashboard().get(String::from("missing")).is_err());
+
+    // Nothing secret reaches the cassette
+    let payload = std::fs::read_to_string(&path).unwrap();
+    assert!(!payload.contains("hunter2") && !payload.contains("glsa_secret_token"), "{payload}");
+    let interactions = recorder.interactions();
+    assert_eq!(interactions.len(), 3);
+    assert_eq!(interactions[1].body, Some(json!({"name":"prometheus","secureJsonData":REDACTED})));
+    assert!(interactions[1].response.contains(REDACTED));
+
+    // Replay against a host that does not exist
+    let replaying = client("http://grafana.invalid", Cassette::replay(&path).unwrap());
+    assert_eq!(replaying.search().dashboard(None).unwrap()[0].title.as_deref(), Some("Home"));
+    replaying.api.post_json::<_,Value>("datasources", &json!({})).unwrap();
+    let err = replaying.dashboard().get(String::from("missing")).unwrap_err();
+    assert!(matches!(err, GrafanaError::NotFound(_)));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_replay_fails_on_unmatched() {
+    let cassette = Cassette::replay(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/search.json")).unwrap();
+    let client = client("http://grafana.invalid", cassette);
+    assert!(matches!(client.dashboard().get(String::from("abc")), Err(GrafanaError::Transport(_))));
+    client.search().dashboard(None).unwrap();
+    // Each interaction is served once
+    assert!(matches!(client.search().dashboard(None), Err(GrafanaError::Transport(_))));
+}
+
+#[tokio::test]
+async fn test_replay_async() {
+    let cassette = Cassette::replay(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/cassettes/dashboard.json")).unwrap();
+    let client = ClientBuilder::new("http://grafana.invalid")
+        .with_auth(Auth::Anonymous)
+        .with_transport(cassette)
+        .build_async()
+        .unwrap();
+    let home = client.dashboard().get_async(String::from("cIBgcSjkk")).await.unwrap();
+    assert_eq!(home.dashboard.uid.as_deref(), Some("cIBgcSjkk"));
+}
+
+#[test]
+fn test_missing_cassette() {
+    assert!(matches!(Cassette::replay("does/not/exist.json"), Err(GrafanaError::Config(_))));
+}
@@ -0,0 +1,36 @@
+//! Reusing a single client across modules and threads
+mod common;
+
+use common::{Reply,StubServer};
+use grafana_lib::client::Client;
+
+#[test]
+fn test_client_is_reusable() {
+    let server = StubServer::start(vec![
+        Reply::json(r#"[{"uid":"abc","title":"Home"}]"#),
+        Reply::json(r#"{"meta":{},"dashboard":{"uid":"abc","title":"Home"}}"#),
+        Reply::json(r#"[{"id":3,"uid":"ops","title":"Ops","isStarred":false}]"#),
+        Reply::json(r#"[]"#),
+    ]);
+    let client = Client::new(server.url());
+    let found = client.search().dashboard(None).unwrap();
+    for d in found {
+        client.dashboard().get(d.uid.unwrap()).unwrap();
+    }
+    client.search().folder(None).unwrap();
+    client.data_source().get(None).unwrap();
+    assert_eq!(server.requests().len(), 4);
+}
+
+#[test]
+fn test_client_shared_between_threads() {
+    fn assert_send_sync<T : Send + Sync>() {}
+    assert_send_sync::<Client>();
+
+    let server = StubServer::start(vec![Reply::json("[]"),Reply::json("[]")]);
+    let client = Client::new(server.url());
+    let clone = client.clone();
+    std::thread::spawn(move || clone.search().dashboard(None).unwrap()).join().unwrap();
+    client.search().dashboard(None).unwrap();
+    assert_eq!(server.requests().len(), 2);
+}
@@ -0,0 +1,187 @@
+//! Stub HTTP server used by the integration tests
+//!
+//! Serves a scripted sequence of replies, one per connection, and records every request
+//! it receives so tests can assert on paths and headers.
+#![allow(dead_code)]
+
+pub mod socks;
+pub mod tls;
+
+use std::collections::HashMap;
+use std::io::{BufRead,BufReader,Read,Write};
+use std::net::TcpListener;
+use std::sync::{Arc,Mutex};
+use std::sync::atomic::{AtomicUsize,Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Scripted reply from the stub server
+#[derive(Clone,Debug)]
+pub enum Reply {
+    /// Respond with a status code, content type and body
+    Status(u16, &'static str, String),
+    /// Respond with a status code and a `Retry-After` header
+    RetryAfter(u16, &'static str),
+    /// Close the connection without responding
+    Reset,
+    /// Hold the connection open without responding for a while, then close it
+    Hang(Duration),
+}
+
+impl Reply {
+    /// 200 response with a JSON body
+    pub fn json(body : &str) -> Reply {
+        Reply::Status(200, "application/json", body.to_string())
+    }
+    /// Response with arbitrary status and JSON body
+    pub fn status(status : u16, body : &str) -> Reply {
+        Reply::Status(status, "application/json", body.to_string())
+    }
+    /// Response with arbitrary status and HTML body
+    pub fn html(status : u16, body : &str) -> Reply {
+        Reply::Status(status, "text/html", body.to_string())
+    }
+}
+
+/// A request as seen by the stub server
+#[derive(Clone,Debug,Default)]
+pub struct Recorded {
+    /// HTTP method
+    pub method : String,
+    /// Path including query string
+    pub path : String,
+    /// Headers, keys lower-cased
+    pub headers : HashMap<String,String>,
+    /// Request body
+    pub body : String,
+}
+
+impl Recorded {
+    /// Look up a header by (case-insensitive) name
+    pub fn header(&self, name : &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(|h| h.as_str())
+    }
+}
+
+/// Stub server listening on an ephemeral local port
+pub struct StubServer {
+    port : u16,
+    requests : Arc<Mutex<Vec<Recorded>>>,
+    peak : Arc<AtomicUsize>,
+}
+
+impl StubServer {
+    /// Start a server that serves `replies` in order, then 404 for anything further
+    pub fn start(replies : Vec<Reply>) -> StubServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Could not bind stub server");
+        let port = listener.local_addr().expect("No local address").port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            let mut replies = replies.into_iter();
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let reply = replies.next().unwrap_or(Reply::status(404, r#"{"message":"Not found"}"#));
+                handle(stream, reply, &recorded);
+            }
+        });
+        StubServer { port, requests, peak : Arc::default() }
+    }
+
+    /// Start a server that handles connections concurrently, holding each one for `delay`
+    /// before sending `reply`, and tracks the peak number of connections in flight
+    pub fn concurrent(reply : Reply, delay : Duration) -> StubServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Could not bind stub server");
+        let port = listener.local_addr().expect("No local address").port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (recorded, max) = (requests.clone(), peak.clone());
+        thread::spawn(move || {
+            let active = Arc::new(AtomicUsize::new(0));
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let (reply, recorded, active, max) = (reply.clone(), recorded.clone(), active.clone(), max.clone());
+                thread::spawn(move || {
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    max.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(delay);
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    handle(stream, reply, &recorded);
+                });
+            }
+        });
+        StubServer { port, requests, peak }
+    }
+
+    /// Base URL of the server, without a trailing slash
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}",self.port)
+    }
+
+    /// Peak number of connections in flight, only tracked by [`StubServer::concurrent`]
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+
+    /// All requests received so far
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Read a single request from a stream, record it and write the reply
+pub(crate) fn handle<S : Read + Write>(stream : S, reply : Reply, recorded : &Mutex<Vec<Recorded>>) {
+    let mut reader = BufReader::new(stream);
+    let mut request = Recorded::default();
+    let mut line = String::new();
+    if reader.read_line(&mut line).is_err() {
+        return;
+    }
+    if line.is_empty() {
+        return;
+    }
+    let mut parts = line.split_whitespace();
+    request.method = parts.next().unwrap_or_default().to_string();
+    request.path = parts.next().unwrap_or_default().to_string();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((k,v)) = line.split_once(':') {
+            request.headers.insert(k.trim().to_lowercase(), v.trim().to_string());
+        }
+    }
+    let length = request.header("content-length").and_then(|l| l.parse().ok()).unwrap_or(0);
+    let mut body = vec![0; length];
+    if reader.read_exact(&mut body).is_ok() {
+        request.body = String::from_utf8_lossy(&body).to_string();
+    }
+    recorded.lock().unwrap().push(request);
+
+    let mut stream = reader.into_inner();
+    match reply {
+        Reply::Reset => {},
+        Reply::Hang(d) => thread::sleep(d),
+        Reply::Status(status, content_type, body) => {
+            let response = format!(
+                "HTTP/1.1 {} Stub\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                content_type,
+                body.len(),
+                body,
+            );
+            let _ = stream.write_all(response.as_bytes());
+            let _ = stream.flush();
+        },
+        Reply::RetryAfter(status, after) => {
+            let response = format!(
+                "HTTP/1.1 {} Stub\r\nRetry-After: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status,
+                after,
+            );
+            let _ = stream.write_all(response.as_bytes());
+            let _ = stream.flush();
+        },
+    }
+}
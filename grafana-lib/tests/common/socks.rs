@@ -0,0 +1,89 @@
+//! Minimal SOCKS5 proxy supporting unauthenticated CONNECT, used to test proxy routing
+
+use std::io::{self,Read,Write};
+use std::net::{Ipv4Addr,Ipv6Addr,TcpListener,TcpStream};
+use std::sync::{Arc,Mutex};
+use std::thread;
+
+/// SOCKS5 proxy listening on an ephemeral local port
+pub struct SocksProxy {
+    port : u16,
+    targets : Arc<Mutex<Vec<String>>>,
+}
+
+impl SocksProxy {
+    /// Start the proxy
+    pub fn start() -> SocksProxy {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let targets = Arc::new(Mutex::new(Vec::new()));
+        let recorded = targets.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let recorded = recorded.clone();
+                thread::spawn(move || {
+                    let _ = connect(stream, &recorded);
+                });
+            }
+        });
+        SocksProxy { port, targets }
+    }
+
+    /// Proxy URL
+    pub fn url(&self) -> String {
+        format!("socks5://127.0.0.1:{}",self.port)
+    }
+
+    /// Targets the proxy has been asked to connect to, as host:port
+    pub fn targets(&self) -> Vec<String> {
+        self.targets.lock().unwrap().clone()
+    }
+}
+
+fn connect(mut client : TcpStream, recorded : &Mutex<Vec<String>>) -> io::Result<()> {
+    // Greeting, accept "no authentication"
+    let mut header = [0u8; 2];
+    client.read_exact(&mut header)?;
+    let mut methods = vec![0u8; header[1] as usize];
+    client.read_exact(&mut methods)?;
+    client.write_all(&[5, 0])?;
+
+    // CONNECT request
+    let mut request = [0u8; 4];
+    client.read_exact(&mut request)?;
+    let host = match request[3] {
+        1 => {
+            let mut ip = [0u8; 4];
+            client.read_exact(&mut ip)?;
+            Ipv4Addr::from(ip).to_string()
+        },
+        3 => {
+            let mut len = [0u8; 1];
+            client.read_exact(&mut len)?;
+            let mut name = vec![0u8; len[0] as usize];
+            client.read_exact(&mut name)?;
+            String::from_utf8_lossy(&name).to_string()
+        },
+        _ => {
+            let mut ip = [0u8; 16];
+            client.read_exact(&mut ip)?;
+            Ipv6Addr::from(ip).to_string()
+        },
+    };
+    let mut port = [0u8; 2];
+    client.read_exact(&mut port)?;
+    let target = format!("{}:{}",host,u16::from_be_bytes(port));
+    recorded.lock().unwrap().push(target.clone());
+
+    let upstream = TcpStream::connect(&target)?;
+    client.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])?;
+
+    // Pipe data in both directions until either side closes
+    let (mut client_read, mut upstream_write) = (client.try_clone()?, upstream.try_clone()?);
+    let forward = thread::spawn(move || io::copy(&mut client_read, &mut upstream_write));
+    let (mut upstream_read, mut client_write) = (upstream, client);
+    io::copy(&mut upstream_read, &mut client_write)?;
+    let _ = forward.join();
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! TLS flavour of the stub server, using a throwaway CA generated per test
+
+use std::fs;
+use std::net::{IpAddr,Ipv4Addr,TcpListener};
+use std::path::PathBuf;
+use std::sync::{Arc,Mutex};
+use std::thread;
+
+use rcgen::{BasicConstraints,CertificateParams,DnType,IsCa,KeyPair,SanType};
+use rustls::pki_types::{CertificateDer,PrivateKeyDer,PrivatePkcs8KeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore,ServerConfig,ServerConnection,StreamOwned};
+
+use super::{handle,Recorded,Reply};
+
+/// PEM files for a CA, a server certificate and a client certificate, written to a temp dir
+pub struct Pki {
+    /// Directory holding the files
+    pub dir : PathBuf,
+    ca : rcgen::Certificate,
+    server : (rcgen::Certificate, KeyPair),
+}
+
+impl Pki {
+    /// Generate a CA and sign server and client certificates with it
+    pub fn generate(name : &str) -> Pki {
+        let dir = std::env::temp_dir().join(format!("grafana-lib-{}-{}",name,std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params.distinguished_name.push(DnType::CommonName, "Test CA");
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let mut server_params = CertificateParams::new(vec![String::from("localhost")]).unwrap();
+        server_params.subject_alt_names.push(SanType::IpAddress(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        let server = server_params.signed_by(&server_key, &ca, &ca_key).unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let mut client_params = CertificateParams::new(Vec::new()).unwrap();
+        client_params.distinguished_name.push(DnType::CommonName, "grafana-lib");
+        let client = client_params.signed_by(&client_key, &ca, &ca_key).unwrap();
+
+        fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+        fs::write(dir.join("client.pem"), client.pem()).unwrap();
+        fs::write(dir.join("client.key"), client_key.serialize_pem()).unwrap();
+        Pki { dir, ca, server : (server, server_key) }
+    }
+
+    /// CA bundle path
+    pub fn ca_cert(&self) -> PathBuf {
+        self.dir.join("ca.pem")
+    }
+
+    /// Client certificate path
+    pub fn client_cert(&self) -> PathBuf {
+        self.dir.join("client.pem")
+    }
+
+    /// Client key path
+    pub fn client_key(&self) -> PathBuf {
+        self.dir.join("client.key")
+    }
+
+    fn server_config(&self, require_client_cert : bool) -> ServerConfig {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap();
+        let builder = match require_client_cert {
+            true => {
+                let mut roots = RootCertStore::empty();
+                roots.add(self.ca.der().clone()).unwrap();
+                let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                    .build()
+                    .unwrap();
+                builder.with_client_cert_verifier(verifier)
+            },
+            false => builder.with_no_client_auth(),
+        };
+        let cert : CertificateDer = self.server.0.der().clone();
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(self.server.1.serialize_der()));
+        builder.with_single_cert(vec![cert], key).unwrap()
+    }
+}
+
+/// Stub server speaking TLS with a certificate issued by a [`Pki`]
+pub struct TlsStubServer {
+    port : u16,
+    requests : Arc<Mutex<Vec<Recorded>>>,
+}
+
+impl TlsStubServer {
+    /// Start a TLS server answering every request with `reply`
+    pub fn start(pki : &Pki, require_client_cert : bool, reply : Reply) -> TlsStubServer {
+        let config = Arc::new(pki.server_config(require_client_cert));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let Ok(conn) = ServerConnection::new(config.clone()) else { continue };
+                handle(StreamOwned::new(conn, stream), reply.clone(), &recorded);
+            }
+        });
+        TlsStubServer { port, requests }
+    }
+
+    /// Base URL of the server
+    pub fn url(&self) -> String {
+        format!("https://127.0.0.1:{}",self.port)
+    }
+
+    /// All requests received so far
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.requests.lock().unwrap().clone()
+    }
+}
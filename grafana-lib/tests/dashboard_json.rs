@@ -0,0 +1,197 @@
+//! Dashboards survive a decode and encode unchanged
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use grafana_lib::community::dashboard::DashboardModel;
+use grafana_lib::community::panel::{GridPos,PanelModel};
+use quickcheck::{Arbitrary,Gen,QuickCheck};
+use serde_json::{Map,Value};
+
+/// Fields the models type, most likely to be mishandled
+const TYPED_KEYS : [&str; 18] = [
+    "id", "uid", "panels", "title", "tags", "timezone", "schemaVersion", "refresh", "version", "type",
+    "description", "gridPos", "datasource", "targets", "fieldConfig", "options", "transformations", "collapsed",
+];
+
+/// Exported dashboards checked into `tests/dashboards`
+fn corpus() -> Vec<(PathBuf, String)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/dashboards");
+    let mut files : Vec<_> = fs::read_dir(dir).unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .map(|p| { let text = fs::read_to_string(&p).unwrap(); (p, text) })
+        .collect();
+    files.sort();
+    assert!(files.len() >= 5, "corpus is missing");
+    files
+}
+
+fn round_trip(value : &Value) -> Value {
+    let model : DashboardModel = serde_json::from_value(value.clone()).unwrap();
+    serde_json::to_value(&model).unwrap()
+}
+
+#[test]
+fn test_corpus_round_trip() {
+    for (path, text) in corpus() {
+        let original : Value = serde_json::from_str(&text).unwrap();
+        let model : DashboardModel = serde_json::from_str(&text).unwrap();
+        let written = serde_json::to_string(&model).unwrap();
+        // Same keys in the same order, only the whitespace differs
+        assert_eq!(written, serde_json::to_string(&original).unwrap(), "{}", path.display());
+        assert_eq!(model.panels.map(|p| p.len()), original["panels"].as_array().map(|p| p.len()), "{}", path.display());
+    }
+}
+
+#[test]
+fn test_corpus_edit() {
+    for (path, text) in corpus() {
+        let mut original : Value = serde_json::from_str(&text).unwrap();
+        let mut model : DashboardModel = serde_json::from_str(&text).unwrap();
+        model.title = Some(String::from("Edited"));
+        model.version = Some(1000);
+        original["title"] = Value::from("Edited");
+        original["version"] = Value::from(1000);
+        assert_eq!(serde_json::to_value(&model).unwrap(), original, "{}", path.display());
+    }
+}
+
+#[test]
+fn test_corpus_panels_typed() {
+    for (path, text) in corpus() {
+        let original : Value = serde_json::from_str(&text).unwrap();
+        let model : DashboardModel = serde_json::from_str(&text).unwrap();
+        for (panel, json) in model.panels.unwrap().iter().zip(original["panels"].as_array().unwrap()) {
+            let grid : GridPos = serde_json::from_value(json["gridPos"].clone()).unwrap();
+            assert_eq!(panel.grid_pos.as_ref(), Some(&grid), "{}", path.display());
+            let targets = json["targets"].as_array().map(|t| t.len());
+            assert_eq!(panel.targets.as_ref().map(|t| t.len()), targets, "{}", path.display());
+            for (target, json) in panel.targets.iter().flatten().zip(json["targets"].as_array().into_iter().flatten()) {
+                assert_eq!(target.ref_id.as_deref(), json["refId"].as_str(), "{}", path.display());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_corpus_panel_edit() {
+    let (_, text) = corpus().into_iter().find(|(p, _)| p.ends_with("k8s-cluster.json")).unwrap();
+    let mut original : Value = serde_json::from_str(&text).unwrap();
+    let mut model : DashboardModel = serde_json::from_str(&text).unwrap();
+    let panel : &mut PanelModel = &mut model.panels.as_mut().unwrap()[0];
+    panel.grid_pos.as_mut().unwrap().w = 12;
+    panel.targets.as_mut().unwrap()[0].extra.insert(String::from("expr"), Value::from("up"));
+    let defaults = panel.field_config.as_mut().unwrap().defaults.as_mut().unwrap();
+    defaults.unit = Some(String::from("percent"));
+    original["panels"][0]["gridPos"]["w"] = Value::from(12);
+    original["panels"][0]["targets"][0]["expr"] = Value::from("up");
+    original["panels"][0]["fieldConfig"]["defaults"]["unit"] = Value::from("percent");
+    assert_eq!(serde_json::to_value(&model).unwrap(), original);
+}
+
+#[test]
+fn test_corpus_layout_valid() {
+    for (path, text) in corpus() {
+        let model : DashboardModel = serde_json::from_str(&text).unwrap();
+        let issues : Vec<_> = model.validate_layout().iter().map(|i| i.to_string()).collect();
+        assert_eq!(issues, Vec::<String>::new(), "{}", path.display());
+    }
+}
+
+/// Arbitrary JSON value of limited depth
+#[derive(Clone,Debug)]
+struct Json(Value);
+
+impl Json {
+    fn generate(g : &mut Gen, depth : u8) -> Value {
+        let kinds = if depth == 0 { 5 } else { 7 };
+        match u8::arbitrary(g) % kinds {
+            0 => Value::Null,
+            1 => Value::Bool(bool::arbitrary(g)),
+            2 => match bool::arbitrary(g) {
+                true => Value::from(i64::arbitrary(g)),
+                false => Value::from(u64::arbitrary(g)),
+            },
+            3 => serde_json::Number::from_f64(f64::arbitrary(g)).map(Value::Number).unwrap_or(Value::Null),
+            4 => Value::String(String::arbitrary(g)),
+            5 => Value::Array((0..u8::arbitrary(g) % 4).map(|_| Json::generate(g, depth - 1)).collect()),
+            _ => Value::Object((0..u8::arbitrary(g) % 4).map(|_| (Key::arbitrary(g).0, Json::generate(g, depth - 1))).collect()),
+        }
+    }
+}
+
+impl Arbitrary for Json {
+    fn arbitrary(g : &mut Gen) -> Json {
+        Json(Json::generate(g, 3))
+    }
+}
+
+/// Object key, often one of the typed fields
+#[derive(Clone,Debug)]
+struct Key(String);
+
+impl Arbitrary for Key {
+    fn arbitrary(g : &mut Gen) -> Key {
+        match bool::arbitrary(g) {
+            true => Key(g.choose(&TYPED_KEYS).unwrap().to_string()),
+            false => Key(String::arbitrary(g)),
+        }
+    }
+}
+
+/// Replacement of a field of the dashboard or of one of its panels
+#[derive(Clone,Debug)]
+struct Edit {
+    panel : Option<u8>,
+    key : Key,
+    value : Json,
+}
+
+impl Arbitrary for Edit {
+    fn arbitrary(g : &mut Gen) -> Edit {
+        Edit { panel : Option::arbitrary(g), key : Key::arbitrary(g), value : Json::arbitrary(g) }
+    }
+}
+
+fn apply(dashboard : &mut Map<String,Value>, edit : Edit) {
+    let target = match (edit.panel, dashboard.get_mut("panels").and_then(Value::as_array_mut)) {
+        (Some(i), Some(panels)) if !panels.is_empty() => {
+            let len = panels.len();
+            match panels[i as usize % len].as_object_mut() {
+                Some(panel) => panel,
+                None => return,
+            }
+        },
+        _ => dashboard,
+    };
+    target.insert(edit.key.0, edit.value.0);
+}
+
+/// Any corpus dashboard with arbitrary fields added or replaced is written back unchanged
+fn edited_round_trip(index : usize, edits : Vec<Edit>) -> bool {
+    static CORPUS : OnceLock<Vec<Value>> = OnceLock::new();
+    let corpus = CORPUS.get_or_init(|| corpus().iter().map(|(_, text)| serde_json::from_str(text).unwrap()).collect());
+    let Value::Object(mut dashboard) = corpus[index % corpus.len()].clone() else { return false };
+    edits.into_iter().for_each(|e| apply(&mut dashboard, e));
+    let dashboard = Value::Object(dashboard);
+    round_trip(&dashboard) == dashboard
+}
+
+#[test]
+fn test_malformed_panel_kept() {
+    let dashboard = serde_json::json!({
+        "title" : "Broken",
+        "panels" : [{ "id" : 1, "title" : "CPU" }, "Memory"],
+    });
+    let model : DashboardModel = serde_json::from_value(dashboard.clone()).unwrap();
+    assert_eq!(model.title.as_deref(), Some("Broken"));
+    assert_eq!(model.panels, None);
+    assert_eq!(model.extra.get("panels"), dashboard.get("panels"));
+    assert_eq!(round_trip(&dashboard), dashboard);
+}
+
+#[test]
+fn test_arbitrary_fields_round_trip() {
+    QuickCheck::new().tests(500).quickcheck(edited_round_trip as fn(usize, Vec<Edit>) -> bool);
+}
@@ -0,0 +1,89 @@
+//! Client-side rate and concurrency limits
+mod common;
+
+use std::thread;
+use std::time::{Duration,Instant};
+
+use common::{Reply,StubServer};
+use grafana_lib::client::{Client,ClientBuilder};
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::error::GrafanaError;
+
+fn builder(server : &StubServer) -> ClientBuilder {
+    Client::builder(&server.url()).with_auth(Auth::Anonymous)
+}
+
+#[test]
+fn test_rate_limit() {
+    let server = StubServer::concurrent(Reply::json("[]"), Duration::ZERO);
+    let client = builder(&server).with_rate_limit(20.0, 2).build().unwrap();
+    let start = Instant::now();
+    for _ in 0..6 {
+        client.search().dashboard(None).unwrap();
+    }
+    // Two requests from the burst, then four more at 50ms intervals
+    assert!(start.elapsed() >= Duration::from_millis(200), "{:?}", start.elapsed());
+}
+
+#[test]
+fn test_rate_limit_invalid() {
+    let server = StubServer::start(vec![]);
+    for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let result = builder(&server).with_rate_limit(rate, 2).build();
+        assert!(matches!(result, Err(GrafanaError::Config(_))), "{rate} should be rejected");
+    }
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn test_rate_limit_shared_across_clones() {
+    let server = StubServer::concurrent(Reply::json("[]"), Duration::ZERO);
+    let client = builder(&server).with_rate_limit(20.0, 1).build().unwrap();
+    let start = Instant::now();
+    let handles : Vec<_> = (0..5).map(|_| {
+        let client = client.clone();
+        thread::spawn(move || client.search().dashboard(None).unwrap())
+    }).collect();
+    handles.into_iter().for_each(|h| { h.join().unwrap(); });
+    assert!(start.elapsed() >= Duration::from_millis(200), "{:?}", start.elapsed());
+}
+
+#[test]
+fn test_max_in_flight() {
+    let server = StubServer::concurrent(Reply::json("[]"), Duration::from_millis(100));
+    let client = builder(&server).with_max_in_flight(2).build().unwrap();
+    let handles : Vec<_> = (0..6).map(|_| {
+        let client = client.clone();
+        thread::spawn(move || client.search().dashboard(None).unwrap())
+    }).collect();
+    handles.into_iter().for_each(|h| { h.join().unwrap(); });
+    assert_eq!(server.requests().len(), 6);
+    assert_eq!(server.peak(), 2);
+}
+
+#[tokio::test]
+async fn test_max_in_flight_async() {
+    let server = StubServer::concurrent(Reply::json("[]"), Duration::from_millis(100));
+    let client = builder(&server).with_max_in_flight(1).build_async().unwrap();
+    let (search, other) = (client.search(), client.clone());
+    let other = other.search();
+    let (a, b, c) = tokio::join!(
+        search.dashboard_async(None),
+        search.folder_async(None),
+        other.dashboard_async(None),
+    );
+    assert!(a.is_ok() && b.is_ok() && c.is_ok());
+    assert_eq!(server.peak(), 1);
+}
+
+#[test]
+fn test_limited_futures_are_send() {
+    fn assert_send<T : Send>(_ : T) {}
+    let client = ClientBuilder::new("http://localhost:3000")
+        .with_auth(Auth::Anonymous)
+        .with_max_in_flight(1)
+        .build_async()
+        .unwrap();
+    let search = client.search();
+    assert_send(search.dashboard_async(None));
+}
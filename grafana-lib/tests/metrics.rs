@@ -0,0 +1,60 @@
+//! Request metrics and their Prometheus rendering
+mod common;
+
+use std::time::Duration;
+
+use common::{Reply,StubServer};
+use grafana_lib::client::Client;
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::metrics::Metrics;
+use grafana_lib::common::retry::RetryPolicy;
+use grafana_mock::MockServer;
+use reqwest::Method;
+use serde_json::json;
+
+#[test]
+fn test_requests_by_route_and_status() {
+    let server = MockServer::start();
+    server.add_dashboard(json!({ "uid" : "home", "title" : "Home" }), None);
+    let metrics = Metrics::new();
+    let client = Client::builder(&server.url())
+        .with_auth(Auth::Anonymous)
+        .with_metrics(metrics.clone())
+        .build()
+        .unwrap();
+    client.dashboard().get(String::from("home")).unwrap();
+    client.search().dashboard(Some(String::from("home"))).unwrap();
+    client.search().folder(None).unwrap();
+    let _ = client.dashboard().get(String::from("missing"));
+
+    assert_eq!(metrics.requests(&Method::GET, "/api/dashboards/uid/{uid}", "2xx"), 1);
+    assert_eq!(metrics.requests(&Method::GET, "/api/dashboards/uid/{uid}", "4xx"), 1);
+    assert_eq!(metrics.requests(&Method::GET, "/api/search", "2xx"), 2);
+    let text = metrics.render();
+    assert!(text.contains("# TYPE grafana_client_requests_total counter\n"), "{text}");
+    assert!(text.contains("grafana_client_requests_total{method=\"GET\",route=\"/api/search\",status=\"2xx\"} 2\n"), "{text}");
+    assert!(text.contains("grafana_client_request_duration_seconds_count{method=\"GET\",route=\"/api/search\"} 2\n"), "{text}");
+    assert!(!text.contains("missing"));
+}
+
+#[test]
+fn test_retries_and_errors() {
+    let server = StubServer::start(vec![Reply::status(503, "{}"), Reply::status(503, "{}"), Reply::json("[]")]);
+    let metrics = Metrics::new();
+    let client = Client::builder(&server.url())
+        .with_auth(Auth::Anonymous)
+        .with_retry(RetryPolicy::default().with_backoff(Duration::from_millis(5), Duration::from_millis(5)).with_max_attempts(3))
+        .with_metrics(metrics.clone())
+        .build()
+        .unwrap();
+    client.data_source().get(None).unwrap();
+    let broken = StubServer::start(vec![Reply::Reset]);
+    let client = Client::builder(&broken.url()).with_auth(Auth::Anonymous).with_metrics(metrics.clone()).build().unwrap();
+    let _ = client.annotations().list(None, None);
+
+    assert_eq!(metrics.retries(&Method::GET, "/api/datasources"), 2);
+    assert_eq!(metrics.requests(&Method::GET, "/api/datasources", "2xx"), 1);
+    let text = metrics.render();
+    assert!(text.contains("grafana_client_retries_total{method=\"GET\",route=\"/api/datasources\"} 2\n"), "{text}");
+    assert!(text.contains("route=\"/api/annotations\",status=\"error\"}"), "{text}");
+}
@@ -0,0 +1,237 @@
+//! End to end tests against the in-memory Grafana server
+use grafana_lib::async_client::AsyncClient;
+use grafana_lib::client::Client;
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::error::GrafanaError;
+use grafana_lib::community::dashboard::DashboardBuilder;
+use grafana_lib::community::folder::FolderModel;
+use grafana_mock::MockServer;
+use serde_json::json;
+
+fn seeded() -> MockServer {
+    let server = MockServer::start();
+    let ops = server.add_folder("ops", "Operations");
+    server.add_folder("team", "Team");
+    server.add_dashboard(json!({
+        "uid" : "node",
+        "title" : "Node Exporter",
+        "tags" : ["linux"],
+        "schemaVersion" : 39,
+        "panels" : [{ "id" : 1, "title" : "CPU", "type" : "timeseries" }],
+    }), Some(&ops));
+    server.add_dashboard(json!({ "uid" : "home", "title" : "Home" }), None);
+    server
+}
+
+fn client(server : &MockServer) -> Client {
+    Client::builder(&server.url()).with_auth(Auth::Anonymous).build().unwrap()
+}
+
+#[test]
+fn test_search() {
+    let server = seeded();
+    let client = client(&server);
+    let dashboards = client.search().dashboard(None).unwrap();
+    let titles : Vec<_> = dashboards.iter().filter_map(|d| d.title.as_deref()).collect();
+    assert_eq!(titles, vec!["Home", "Node Exporter"]);
+
+    let filtered = client.search().dashboard(Some(String::from("node"))).unwrap();
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].uid.as_deref(), Some("node"));
+
+    let folders = client.search().folder(None).unwrap();
+    let titles : Vec<_> = folders.iter().map(|f| f.title.as_str()).collect();
+    assert_eq!(titles, vec!["Operations", "Team"]);
+}
+
+#[test]
+fn test_dashboard_get() {
+    let server = seeded();
+    let dashboard = client(&server).dashboard().get(String::from("node")).unwrap();
+    assert_eq!(dashboard.dashboard.title.as_deref(), Some("Node Exporter"));
+    assert_eq!(dashboard.dashboard.version, Some(1));
+    assert_eq!(dashboard.dashboard.panels.map(|p| p.len()), Some(1));
+    assert_eq!(dashboard.meta.folder_title.as_deref(), Some("Operations"));
+}
+
+#[test]
+fn test_dashboard_not_found() {
+    let server = seeded();
+    let result = client(&server).dashboard().get(String::from("missing"));
+    assert!(matches!(result, Err(GrafanaError::NotFound(_))), "{result:?}");
+}
+
+#[test]
+fn test_version_counter() {
+    let server = seeded();
+    server.add_dashboard(json!({ "uid" : "node", "title" : "Node Exporter", "version" : 1 }), Some("ops"));
+    let dashboard = client(&server).dashboard().get(String::from("node")).unwrap();
+    assert_eq!(dashboard.dashboard.version, Some(2));
+}
+
+#[test]
+fn test_data_sources() {
+    let server = MockServer::start();
+    server.add_data_source(json!({ "name" : "Prometheus", "type" : "prometheus", "url" : "http://prometheus:9090" }));
+    server.add_data_source(json!({ "name" : "Loki", "type" : "loki" }));
+    let data_sources = client(&server).data_source().get(None).unwrap();
+    let names : Vec<_> = data_sources.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(names, vec!["Prometheus", "Loki"]);
+    assert_eq!(data_sources[0].url.as_deref(), Some("http://prometheus:9090"));
+}
+
+#[test]
+fn test_annotations() {
+    let server = seeded();
+    server.add_annotation(json!({ "dashboardUID" : "node", "panelId" : 1, "text" : "deploy", "time" : 2 }));
+    server.add_annotation(json!({ "text" : "outage", "time" : 1 }));
+    let client = client(&server);
+    assert_eq!(client.annotations().list(None, None).unwrap().len(), 2);
+    assert_eq!(client.annotations().list(Some(1), None).unwrap().len(), 1);
+    let id = server.dashboard("node").unwrap()["id"].as_u64().unwrap() as u16;
+    let annotations = client.annotations().list(None, Some(id)).unwrap();
+    assert_eq!(annotations.len(), 1);
+    assert!(annotations[0].to_string().contains("deploy"));
+}
+
+#[test]
+fn test_alerting_provisioning() {
+    let server = seeded();
+    server.add_alert_rule(json!({ "title" : "High CPU", "folderUID" : "ops", "ruleGroup" : "node" }));
+    server.add_contact_point(json!({ "name" : "On call", "type" : "email", "settings" : { "addresses" : "oncall@example.com" } }));
+    let client = client(&server);
+    let rules = client.alerting_provisioning().alert_rule().list().unwrap();
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0].folder_uid.as_deref(), Some("ops"));
+    assert_eq!(rules[0].rule_group.as_deref(), Some("node"));
+    let contact_points = client.alerting_provisioning().contact_point().list().unwrap();
+    assert_eq!(contact_points[0].r#type, "email");
+}
+
+#[test]
+fn test_token_required() {
+    let server = seeded().require_token("glsa_test");
+    let anonymous = client(&server).search().dashboard(None);
+    assert!(matches!(anonymous, Err(GrafanaError::Unauthorized(_))), "{anonymous:?}");
+    let client = Client::builder(&server.url()).with_token(String::from("glsa_test")).build().unwrap();
+    assert_eq!(client.search().dashboard(None).unwrap().len(), 2);
+}
+
+#[test]
+fn test_org_header() {
+    let server = seeded();
+    client(&server).for_org(4).search().dashboard(None).unwrap();
+    let requests = server.requests();
+    assert_eq!(requests[0].header("x-grafana-org-id"), Some("4"));
+    assert_eq!(requests[0].param("type"), Some("dash-db"));
+}
+
+#[tokio::test]
+async fn test_async() {
+    let server = seeded();
+    let client = AsyncClient::new(server.url()).with_auth(Auth::Anonymous);
+    let dashboards = client.search().dashboard_async(None).await.unwrap();
+    assert_eq!(dashboards.len(), 2);
+    let dashboard = client.dashboard().get_async(String::from("home")).await.unwrap();
+    assert_eq!(dashboard.dashboard.uid.as_deref(), Some("home"));
+}
+
+#[test]
+fn test_dashboard_save() {
+    let server = seeded();
+    let saved = client(&server).dashboard()
+        .create(DashboardBuilder::new(String::from("Disk")).build())
+        .with_folder_uid(String::from("ops"))
+        .with_message(String::from("first version"))
+        .send()
+        .unwrap();
+    assert_eq!((saved.version, saved.status.as_str()), (1, "success"));
+    assert_eq!(saved.url, format!("/d/{}/disk",saved.uid));
+    let body = server.requests().last().unwrap().json().unwrap();
+    assert_eq!((body["folderUid"].as_str(), body["message"].as_str()), (Some("ops"), Some("first version")));
+    let stored = client(&server).dashboard().get(saved.uid.clone()).unwrap();
+    assert_eq!(stored.meta.folder_title.as_deref(), Some("Operations"));
+
+    let clash = client(&server).dashboard()
+        .create(DashboardBuilder::new(String::from("Disk")).build())
+        .with_folder_uid(String::from("ops"))
+        .send();
+    assert!(matches!(clash, Err(GrafanaError::NameExists(_))), "{clash:?}");
+}
+
+#[test]
+fn test_dashboard_update() {
+    let server = seeded();
+    let client = client(&server);
+    let mut first = client.dashboard().get(String::from("home")).unwrap().dashboard;
+    let mut second = client.dashboard().get(String::from("home")).unwrap().dashboard;
+    first.title = Some(String::from("Home v2"));
+    let saved = client.dashboard().create(first).send().unwrap();
+    assert_eq!((saved.uid.as_str(), saved.version), ("home", 2));
+
+    second.title = Some(String::from("Home v3"));
+    let stale = client.dashboard().create(second).send();
+    assert!(matches!(stale, Err(GrafanaError::VersionMismatch(_))), "{stale:?}");
+
+    let mut third = client.dashboard().get(String::from("home")).unwrap().dashboard;
+    third.version = Some(1);
+    let saved = client.dashboard().create(third).with_overwrite(true).send().unwrap();
+    assert_eq!(saved.version, 3);
+}
+
+#[test]
+fn test_folder_save() {
+    let server = seeded();
+    let client = client(&server);
+    assert_eq!(client.search().dashboard(None).unwrap().len(), 2);
+    let mut model = FolderModel::new(String::from("Databases"));
+    model.parent_uid = Some(String::from("ops"));
+    let created = client.folder().create(model).send().unwrap();
+    let uid = created.uid.clone().unwrap();
+    assert_eq!((created.parent_uid.as_deref(), created.version), (Some("ops"), Some(1)));
+
+    let mut stale = client.folder().get(uid.clone()).unwrap();
+    let mut renamed = created.clone();
+    renamed.title = String::from("Storage");
+    let saved = client.folder().update(uid.clone(), renamed).send().unwrap();
+    assert_eq!((saved.title.as_str(), saved.version), ("Storage", Some(2)));
+
+    stale.title = String::from("Stale");
+    let result = client.folder().update(uid.clone(), stale.clone()).send();
+    assert!(matches!(result, Err(GrafanaError::VersionMismatch(_))), "{result:?}");
+    let saved = client.folder().update(uid.clone(), stale).with_overwrite(true).send().unwrap();
+    assert_eq!(client.folder().get(uid).unwrap(), saved);
+
+    let clash = client.folder().create(FolderModel::new(String::from("Team"))).send();
+    assert!(matches!(clash, Err(GrafanaError::Conflict(_))), "{clash:?}");
+}
+
+#[tokio::test]
+async fn test_dashboard_save_async() {
+    let server = seeded();
+    let client = AsyncClient::new(server.url()).with_auth(Auth::Anonymous);
+    let stale = client.dashboard().get_async(String::from("node")).await.unwrap().dashboard;
+    client.dashboard().create(client.dashboard().get_async(String::from("node")).await.unwrap().dashboard).send_async().await.unwrap();
+    let result = client.dashboard().create(stale).send_async().await;
+    let Err(GrafanaError::VersionMismatch(body)) = result else { panic!("expected a version mismatch, got {result:?}") };
+    assert_eq!(body.message.as_deref(), Some("The dashboard has been changed by someone else"));
+}
+
+#[test]
+fn test_dashboard_save_keeps_unknown_fields() {
+    let server = MockServer::start();
+    let templating = json!({ "list" : [{ "name" : "node", "type" : "query", "query" : "label_values(up, instance)" }] });
+    server.add_dashboard(json!({
+        "uid" : "node",
+        "title" : "Node",
+        "templating" : templating,
+        "panels" : [{ "id" : 1, "title" : "CPU", "type" : "timeseries", "fieldConfig" : { "defaults" : { "unit" : "percent" } } }],
+    }), None);
+    let mut model = client(&server).dashboard().get(String::from("node")).unwrap().dashboard;
+    model.title = Some(String::from("Node v2"));
+    client(&server).dashboard().create(model).send().unwrap();
+    let stored = server.dashboard("node").unwrap();
+    assert_eq!(stored["title"], "Node v2");
+    assert_eq!(stored["templating"], templating);
+    assert_eq!(stored["panels"][0]["fieldConfig"]["defaults"]["unit"], "percent");
+}
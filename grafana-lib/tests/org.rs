@@ -0,0 +1,59 @@
+//! Organisation scoping via X-Grafana-Org-Id
+mod common;
+
+use common::{Reply,StubServer};
+use grafana_lib::async_client::AsyncClient;
+use grafana_lib::client::Client;
+use grafana_lib::common::auth::Auth;
+
+const ORG_HEADER : &str = "x-grafana-org-id";
+
+fn client(server : &StubServer) -> Client {
+    Client::builder(&server.url()).with_auth(Auth::Anonymous).build().unwrap()
+}
+
+#[test]
+fn test_org_applied_to_every_module() {
+    let server = StubServer::start(vec![
+        Reply::json("[]"),
+        Reply::json(r#"{"dashboard":{"title":"x"},"meta":{}}"#),
+        Reply::json("[]"),
+        Reply::json("[]"),
+        Reply::json("[]"),
+        Reply::json("[]"),
+    ]);
+    let client = client(&server).with_org(3);
+    let _ = client.search().dashboard(None);
+    let _ = client.dashboard().get(String::from("abc"));
+    let _ = client.data_source().get(None);
+    let _ = client.annotations().list(None, None);
+    let _ = client.alerting_provisioning().alert_rule().list();
+    let _ = client.alerting_provisioning().contact_point().list();
+    let requests = server.requests();
+    assert_eq!(requests.len(), 6);
+    for request in requests {
+        assert_eq!(request.header(ORG_HEADER), Some("3"), "{}", request.path);
+    }
+}
+
+#[test]
+fn test_org_views() {
+    let server = StubServer::start(vec![Reply::json("[]"), Reply::json("[]"), Reply::json("[]")]);
+    let client = client(&server);
+    client.for_org(1).search().dashboard(None).unwrap();
+    client.for_org(2).search().dashboard(None).unwrap();
+    client.search().dashboard(None).unwrap();
+    let requests = server.requests();
+    assert_eq!(requests[0].header(ORG_HEADER), Some("1"));
+    assert_eq!(requests[1].header(ORG_HEADER), Some("2"));
+    assert_eq!(requests[2].header(ORG_HEADER), None);
+    assert_eq!(client.for_org(2).api.org_id(), Some(2));
+}
+
+#[tokio::test]
+async fn test_org_async() {
+    let server = StubServer::start(vec![Reply::json("[]")]);
+    let client = AsyncClient::new(server.url()).with_auth(Auth::Anonymous);
+    client.for_org(5).search().dashboard_async(None).await.unwrap();
+    assert_eq!(server.requests()[0].header(ORG_HEADER), Some("5"));
+}
@@ -0,0 +1,82 @@
+//! Feed malformed responses into each endpoint and check failures surface as errors
+mod common;
+
+use common::{Reply,StubServer};
+use grafana_lib::client::Client;
+use grafana_lib::common::error::GrafanaError;
+
+type Endpoint = fn(String) -> Result<(),GrafanaError>;
+
+/// Every read endpoint in the library, discarding the successful result
+fn endpoints() -> Vec<(&'static str, Endpoint)> {
+    vec![
+        ("search.dashboard", |url| Client::new(url).search().dashboard(None).map(|_| ())),
+        ("search.folder", |url| Client::new(url).search().folder(Some(String::from("ops"))).map(|_| ())),
+        ("dashboard.get", |url| Client::new(url).dashboard().get(String::from("abc")).map(|_| ())),
+        ("annotations.list", |url| Client::new(url).annotations().list(Some(5),Some(1)).map(|_| ())),
+        ("data_source.get", |url| Client::new(url).data_source().get(None).map(|_| ())),
+        ("alert_rule.list", |url| Client::new(url).alerting_provisioning().alert_rule().list().map(|_| ())),
+        ("contact_point.list", |url| Client::new(url).alerting_provisioning().contact_point().list().map(|_| ())),
+    ]
+}
+
+fn assert_error(reply : Reply, check : fn(&GrafanaError) -> bool) {
+    for (name, endpoint) in endpoints() {
+        let server = StubServer::start(vec![reply.clone()]);
+        match endpoint(server.url()) {
+            Ok(_) => panic!("{name}: expected an error for {reply:?}"),
+            Err(e) => assert!(check(&e), "{name}: unexpected error {e:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_malformed_json() {
+    assert_error(Reply::json(r#"{"title": "broken"#), |e| matches!(e, GrafanaError::Decode { .. }));
+}
+
+#[test]
+fn test_empty_body() {
+    assert_error(Reply::json(""), |e| matches!(e, GrafanaError::Decode { .. }));
+}
+
+#[test]
+fn test_html_success_page() {
+    assert_error(Reply::html(200, "<html><body>Login</body></html>"), |e| matches!(e, GrafanaError::Decode { .. }));
+}
+
+#[test]
+fn test_html_error_page() {
+    assert_error(Reply::html(502, "<html><body>Bad Gateway</body></html>"), |e| {
+        e.status() == Some(502) && e.body().and_then(|b| b.message.clone()).is_some()
+    });
+}
+
+#[test]
+fn test_not_found() {
+    assert_error(Reply::status(404, r#"{"message":"Dashboard not found"}"#), |e| matches!(e, GrafanaError::NotFound(_)));
+}
+
+#[test]
+fn test_connection_reset() {
+    assert_error(Reply::Reset, |e| matches!(e, GrafanaError::Transport(_)));
+}
+
+#[test]
+fn test_connection_refused() {
+    // Bind then drop a listener to find a port nothing is listening on
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    for (name, endpoint) in endpoints() {
+        let result = endpoint(format!("http://127.0.0.1:{port}"));
+        assert!(matches!(result, Err(GrafanaError::Transport(_))), "{name}: expected transport error");
+    }
+}
+
+#[test]
+fn test_search_dashboard_ok() {
+    let server = StubServer::start(vec![Reply::json(r#"[{"id":1,"uid":"abc","title":"Home"}]"#)]);
+    let result = Client::new(server.url()).search().dashboard(Some(String::from("Home"))).unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].uid, Some(String::from("abc")));
+    assert_eq!(server.requests()[0].path, "/api/search?type=dash-db&query=Home");
+}
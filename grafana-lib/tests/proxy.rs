@@ -0,0 +1,82 @@
+//! Proxy routing for blocking and async transports
+mod common;
+
+use common::{Reply,StubServer};
+use common::socks::SocksProxy;
+use grafana_lib::client::ClientBuilder;
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::error::GrafanaError;
+
+fn builder(url : &str) -> ClientBuilder {
+    ClientBuilder::new(url).with_auth(Auth::Anonymous)
+}
+
+#[test]
+fn test_http_proxy_with_credentials() {
+    let proxy = StubServer::start(vec![Reply::json("[]")]);
+    let client = builder("http://grafana.invalid:3000")
+        .with_proxy(proxy.url().replace("http://","http://user:pass@"))
+        .build()
+        .unwrap();
+    client.search().dashboard(None).unwrap();
+    let requests = proxy.requests();
+    assert_eq!(requests[0].path, "http://grafana.invalid:3000/api/search?type=dash-db");
+    assert_eq!(requests[0].header("proxy-authorization"), Some("Basic dXNlcjpwYXNz"));
+}
+
+#[test]
+fn test_no_proxy_bypass() {
+    let proxy = StubServer::start(vec![Reply::json("[]")]);
+    let target = StubServer::start(vec![Reply::json("[]")]);
+    let client = builder(&target.url())
+        .with_proxy(proxy.url())
+        .with_no_proxy(String::from("localhost,127.0.0.1"))
+        .build()
+        .unwrap();
+    client.search().dashboard(None).unwrap();
+    assert_eq!(target.requests().len(), 1);
+    assert!(proxy.requests().is_empty());
+}
+
+#[test]
+fn test_socks_proxy() {
+    let proxy = SocksProxy::start();
+    let target = StubServer::start(vec![Reply::json("[]")]);
+    let client = builder(&target.url())
+        .with_proxy(proxy.url())
+        .build()
+        .unwrap();
+    client.search().dashboard(None).unwrap();
+    assert_eq!(target.requests().len(), 1);
+    assert_eq!(proxy.targets(), vec![target.url().replace("http://","")]);
+}
+
+#[tokio::test]
+async fn test_http_proxy_async() {
+    let proxy = StubServer::start(vec![Reply::json("[]")]);
+    let client = builder("http://grafana.invalid:3000")
+        .with_proxy(proxy.url())
+        .build_async()
+        .unwrap();
+    client.search().dashboard_async(None).await.unwrap();
+    assert_eq!(proxy.requests()[0].path, "http://grafana.invalid:3000/api/search?type=dash-db");
+}
+
+#[test]
+fn test_proxy_from_environment() {
+    // Only this test relies on the environment, the others set an explicit proxy which
+    // disables environment lookup
+    let proxy = StubServer::start(vec![Reply::json("[]")]);
+    std::env::set_var("HTTP_PROXY", proxy.url());
+    let client = builder("http://grafana.invalid:3000").build().unwrap();
+    let result = client.search().dashboard(None);
+    std::env::remove_var("HTTP_PROXY");
+    result.unwrap();
+    assert_eq!(proxy.requests()[0].path, "http://grafana.invalid:3000/api/search?type=dash-db");
+}
+
+#[test]
+fn test_invalid_proxy() {
+    let result = builder("http://localhost:3000").with_proxy(String::from("not a proxy")).build();
+    assert!(matches!(result, Err(GrafanaError::Config(_))));
+}
@@ -0,0 +1,121 @@
+//! Automatic retries against scripted status sequences
+mod common;
+
+use std::time::{Duration,Instant};
+
+use common::{Reply,StubServer};
+use grafana_lib::client::{Client,ClientBuilder};
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::error::GrafanaError;
+use grafana_lib::common::retry::RetryPolicy;
+use reqwest::Method;
+use serde_json::Value;
+
+fn policy() -> RetryPolicy {
+    RetryPolicy::default().with_backoff(Duration::from_millis(10), Duration::from_millis(50))
+}
+
+fn builder(server : &StubServer) -> ClientBuilder {
+    Client::builder(&server.url()).with_auth(Auth::Anonymous)
+}
+
+fn client(server : &StubServer) -> Client {
+    builder(server).with_retry(policy()).build().unwrap()
+}
+
+#[test]
+fn test_retry_until_success() {
+    let server = StubServer::start(vec![
+        Reply::status(503, "{}"),
+        Reply::html(502, "<html>Bad Gateway</html>"),
+        Reply::json("[]"),
+    ]);
+    client(&server).search().dashboard(None).unwrap();
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn test_give_up_after_max_attempts() {
+    let server = StubServer::start(vec![
+        Reply::status(503, "{}"),
+        Reply::status(503, "{}"),
+        Reply::status(502, "{}"),
+        Reply::json("[]"),
+    ]);
+    let err = client(&server).search().dashboard(None).unwrap_err();
+    assert_eq!(err.status(), Some(502));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn test_no_policy_sends_once() {
+    let server = StubServer::start(vec![Reply::status(503, "{}"), Reply::json("[]")]);
+    let client = builder(&server).build().unwrap();
+    assert_eq!(client.search().dashboard(None).unwrap_err().status(), Some(503));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn test_other_status_not_retried() {
+    let server = StubServer::start(vec![Reply::status(404, "{}"), Reply::json("[]")]);
+    let err = client(&server).search().dashboard(None).unwrap_err();
+    assert!(matches!(err, GrafanaError::NotFound(_)));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn test_connection_failure_retried() {
+    let server = StubServer::start(vec![Reply::Reset, Reply::json("[]")]);
+    client(&server).search().dashboard(None).unwrap();
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn test_post_not_retried_by_default() {
+    let server = StubServer::start(vec![Reply::status(503, "{}"), Reply::json("{}")]);
+    let result = client(&server).api.post_json::<_,Value>("dashboards/db", &Value::Null);
+    assert_eq!(result.unwrap_err().status(), Some(503));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn test_post_retried_with_override() {
+    let server = StubServer::start(vec![Reply::status(503, "{}"), Reply::json("{}")]);
+    let api = client(&server).api.with_retry(policy().with_method(Method::POST));
+    api.post_json::<_,Value>("dashboards/db", &Value::Null).unwrap();
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].body, requests[1].body);
+}
+
+#[test]
+fn test_retry_after() {
+    let server = StubServer::start(vec![Reply::RetryAfter(429, "1"), Reply::json("[]")]);
+    let client = builder(&server)
+        .with_retry(policy().with_backoff(Duration::from_millis(10), Duration::from_secs(5)))
+        .build()
+        .unwrap();
+    let start = Instant::now();
+    client.search().dashboard(None).unwrap();
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn test_retry_after_too_long() {
+    let server = StubServer::start(vec![Reply::RetryAfter(429, "3600"), Reply::json("[]")]);
+    let client = builder(&server).with_retry(policy()).build().unwrap();
+    let start = Instant::now();
+    let result = client.search().dashboard(None);
+    assert!(matches!(result, Err(GrafanaError::Http { status : 429, .. })), "{result:?}");
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_retry_async() {
+    let server = StubServer::start(vec![Reply::status(504, "{}"), Reply::json("[]")]);
+    let client = builder(&server).with_retry(policy()).build_async().unwrap();
+    client.search().dashboard_async(None).await.unwrap();
+    assert_eq!(server.requests().len(), 2);
+}
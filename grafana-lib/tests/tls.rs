@@ -0,0 +1,70 @@
+//! TLS settings against a local rustls server with a throwaway CA
+mod common;
+
+use common::Reply;
+use common::tls::{Pki,TlsStubServer};
+use grafana_lib::client::ClientBuilder;
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::error::GrafanaError;
+
+fn builder(server : &TlsStubServer) -> ClientBuilder {
+    ClientBuilder::new(&server.url()).with_auth(Auth::Anonymous)
+}
+
+#[test]
+fn test_untrusted_ca_rejected() {
+    let pki = Pki::generate("untrusted");
+    let server = TlsStubServer::start(&pki, false, Reply::json("[]"));
+    let result = builder(&server).build().unwrap().search().dashboard(None);
+    assert!(matches!(result, Err(GrafanaError::Transport(_))));
+}
+
+#[test]
+fn test_custom_ca() {
+    let pki = Pki::generate("custom-ca");
+    let server = TlsStubServer::start(&pki, false, Reply::json("[]"));
+    let client = builder(&server).with_ca_cert(pki.ca_cert()).build().unwrap();
+    client.search().dashboard(None).unwrap();
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn test_insecure() {
+    let pki = Pki::generate("insecure");
+    let server = TlsStubServer::start(&pki, false, Reply::json("[]"));
+    let client = builder(&server).with_insecure(true).build().unwrap();
+    client.search().dashboard(None).unwrap();
+}
+
+#[test]
+fn test_client_certificate() {
+    let pki = Pki::generate("mtls");
+    let server = TlsStubServer::start(&pki, true, Reply::json("[]"));
+    let without = builder(&server).with_ca_cert(pki.ca_cert()).build().unwrap();
+    assert!(without.search().dashboard(None).is_err());
+
+    let with = builder(&server)
+        .with_ca_cert(pki.ca_cert())
+        .with_client_cert(pki.client_cert(), pki.client_key())
+        .build()
+        .unwrap();
+    with.search().dashboard(None).unwrap();
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_custom_ca_async() {
+    let pki = Pki::generate("custom-ca-async");
+    let server = TlsStubServer::start(&pki, false, Reply::json("[]"));
+    let client = builder(&server).with_ca_cert(pki.ca_cert()).build_async().unwrap();
+    client.search().dashboard_async(None).await.unwrap();
+}
+
+#[test]
+fn test_missing_files() {
+    let result = ClientBuilder::new("https://localhost:3000")
+        .with_auth(Auth::Anonymous)
+        .with_ca_cert(std::path::PathBuf::from("/nonexistent/ca.pem"))
+        .build();
+    assert!(matches!(result, Err(GrafanaError::Config(_))));
+}
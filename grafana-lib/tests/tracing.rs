@@ -0,0 +1,139 @@
+//! Per-request spans with the tracing feature
+mod common;
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc,Mutex};
+use std::time::Duration;
+
+use common::{Reply,StubServer};
+use grafana_lib::async_client::AsyncClient;
+use grafana_lib::client::Client;
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::retry::RetryPolicy;
+use tracing::field::{Field,Visit};
+use tracing::span::{Attributes,Id,Record};
+use tracing::{Event,Metadata,Subscriber};
+
+const TOKEN : &str = "glsa_secret_token";
+
+/// Span name and the fields recorded on it
+type Captured = (String, HashMap<String,String>);
+
+/// Subscriber keeping every span and its fields in memory
+#[derive(Clone,Default)]
+struct Recorder {
+    spans : Arc<Mutex<Vec<Captured>>>,
+}
+
+struct Fields<'a>(&'a mut HashMap<String,String>);
+
+impl Visit for Fields<'_> {
+    fn record_debug(&mut self, field : &Field, value : &dyn Debug) {
+        self.0.insert(field.name().to_string(), format!("{value:?}"));
+    }
+    fn record_str(&mut self, field : &Field, value : &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _ : &Metadata<'_>) -> bool {
+        true
+    }
+    fn new_span(&self, span : &Attributes<'_>) -> Id {
+        let mut fields = HashMap::new();
+        span.record(&mut Fields(&mut fields));
+        let mut spans = self.spans.lock().unwrap();
+        spans.push((span.metadata().name().to_string(), fields));
+        Id::from_u64(spans.len() as u64)
+    }
+    fn record(&self, id : &Id, values : &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        values.record(&mut Fields(&mut spans[id.into_u64() as usize - 1].1));
+    }
+    fn record_follows_from(&self, _ : &Id, _ : &Id) {}
+    fn event(&self, _ : &Event<'_>) {}
+    fn enter(&self, _ : &Id) {}
+    fn exit(&self, _ : &Id) {}
+}
+
+impl Recorder {
+    fn spans(&self) -> Vec<Captured> {
+        self.spans.lock().unwrap().clone()
+    }
+}
+
+fn client(server : &StubServer) -> Client {
+    Client::builder(&server.url())
+        .with_token(String::from(TOKEN))
+        .with_org(2)
+        .with_retry(RetryPolicy::default().with_backoff(Duration::from_millis(10), Duration::from_millis(10)))
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_span_per_call() {
+    let server = StubServer::start(vec![
+        Reply::status(503, "{}"),
+        Reply::json(r#"{"dashboard":{"title":"Home"},"meta":{}}"#),
+    ]);
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        client(&server).dashboard().get(String::from("cIBgcSjkk")).unwrap();
+    });
+    let spans = recorder.spans();
+    assert_eq!(spans.len(), 1);
+    let (name, fields) = &spans[0];
+    assert_eq!(name, "grafana.request");
+    assert_eq!(fields["http.request.method"], "GET");
+    assert_eq!(fields["http.route"], "/api/dashboards/uid/{uid}");
+    assert_eq!(fields["grafana.org_id"], "2");
+    assert_eq!(fields["http.response.status_code"], "200");
+    assert_eq!(fields["retries"], "1");
+    assert!(fields.contains_key("latency_ms"));
+    assert!(!fields.contains_key("error"));
+}
+
+#[test]
+fn test_span_never_records_credentials() {
+    let server = StubServer::start(vec![Reply::status(401, r#"{"message":"invalid API key"}"#)]);
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let _ = client(&server).search().dashboard(Some(String::from(TOKEN)));
+    });
+    let spans = recorder.spans();
+    assert_eq!(spans[0].1["http.route"], "/api/search");
+    assert_eq!(spans[0].1["http.response.status_code"], "401");
+    for (_, fields) in spans {
+        assert!(fields.values().all(|v| !v.contains(TOKEN)), "{fields:?}");
+    }
+}
+
+#[test]
+fn test_span_records_transport_error() {
+    let server = StubServer::start(vec![Reply::Reset]);
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let client = Client::builder(&server.url()).with_auth(Auth::Anonymous).build().unwrap();
+        let _ = client.search().folder(None);
+    });
+    let (_, fields) = &recorder.spans()[0];
+    assert!(fields.contains_key("error"), "{fields:?}");
+    assert!(!fields.contains_key("http.response.status_code"));
+    assert!(!fields.contains_key("grafana.org_id"));
+}
+
+#[tokio::test]
+async fn test_span_async() {
+    let server = StubServer::start(vec![Reply::json("[]")]);
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+    let client = AsyncClient::new(server.url()).for_org(7);
+    client.alerting_provisioning().alert_rule().list_async().await.unwrap();
+    let (_, fields) = &recorder.spans()[0];
+    assert_eq!(fields["http.route"], "/api/v1/provisioning/alert-rules");
+    assert_eq!(fields["grafana.org_id"], "7");
+    assert_eq!(fields["retries"], "0");
+}
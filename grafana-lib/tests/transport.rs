@@ -0,0 +1,111 @@
+//! Pluggable transports and the in-memory mock
+use std::sync::{Arc,Mutex};
+
+use grafana_lib::client::{Client,ClientBuilder};
+use grafana_lib::common::auth::Auth;
+use grafana_lib::common::error::GrafanaError;
+use grafana_lib::common::mock::MockTransport;
+use grafana_lib::common::retry::RetryPolicy;
+use grafana_lib::common::transport::{Request,Response,Transport};
+use reqwest::Method;
+use reqwest::header::RETRY_AFTER;
+use serde_json::{json,Value};
+
+fn client(mock : &MockTransport) -> Client {
+    ClientBuilder::new("http://grafana.test")
+        .with_token(String::from("glsa_token"))
+        .with_org(2)
+        .with_transport(mock.clone())
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_canned_responses() {
+    let mock = MockTransport::new();
+    mock.on(Method::GET, "/api/search", Response::json(r#"[{"id":1,"uid":"abc","title":"Home","type":"dash-db"}]"#));
+    mock.on(Method::GET, "/api/dashboards/uid/missing", Response::new(404, r#"{"message":"Dashboard not found"}"#));
+    let client = client(&mock);
+    assert_eq!(client.search().dashboard(None).unwrap().len(), 1);
+    let err = client.dashboard().get(String::from("missing")).unwrap_err();
+    assert!(matches!(err, GrafanaError::NotFound(_)));
+    let requests = mock.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].path(), "/api/search?type=dash-db");
+    assert_eq!(requests[0].header("authorization"), Some("Bearer glsa_token"));
+    assert_eq!(requests[0].header("x-grafana-org-id"), Some("2"));
+}
+
+#[test]
+fn test_request_body() {
+    let mock = MockTransport::new();
+    mock.on(Method::POST, "/api/orgs", Response::json(r#"{"orgId":3}"#));
+    let result : Value = client(&mock).api.post_json("orgs", &json!({"name":"MyOrg"})).unwrap();
+    assert_eq!(result["orgId"], 3);
+    let request = &mock.requests_to(Method::POST, "/api/orgs")[0];
+    assert_eq!(request.header("content-type"), Some("application/json"));
+    assert_eq!(serde_json::from_slice::<Value>(request.body.as_deref().unwrap()).unwrap(), json!({"name":"MyOrg"}));
+}
+
+#[test]
+fn test_unmatched_request() {
+    let mock = MockTransport::new();
+    mock.on(Method::GET, "/api/search?type=dash-folder", Response::json("[]"));
+    let err = client(&mock).search().dashboard(None).unwrap_err();
+    assert!(matches!(err, GrafanaError::Transport(_)));
+    mock.assert_requested(Method::GET, "/api/search");
+}
+
+#[test]
+fn test_response_sequence_with_retries() {
+    let mock = MockTransport::new();
+    mock.on(Method::GET, "/api/search", Response::new(503, "").with_header(RETRY_AFTER, "0"))
+        .on(Method::GET, "/api/search", Response::json("[]"));
+    let client = ClientBuilder::new("http://grafana.test")
+        .with_auth(Auth::Anonymous)
+        .with_retry(RetryPolicy::default())
+        .with_transport(mock.clone())
+        .build()
+        .unwrap();
+    client.search().dashboard(None).unwrap();
+    client.search().dashboard(None).unwrap();
+    assert_eq!(mock.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn test_mock_async() {
+    let mock = MockTransport::new();
+    mock.on(Method::GET, "/api/search", Response::json("[]"));
+    let client = ClientBuilder::new("http://grafana.test")
+        .with_auth(Auth::Anonymous)
+        .with_transport(mock.clone())
+        .build_async()
+        .unwrap();
+    client.search().folder_async(None).await.unwrap();
+    mock.assert_requested(Method::GET, "/api/search?type=dash-folder");
+}
+
+/// Transport that answers every request itself, standing in for another HTTP stack
+#[derive(Debug,Default)]
+struct Echo {
+    seen : Mutex<Vec<String>>,
+}
+
+impl Transport for Echo {
+    fn send(&self, request : Request) -> Result<Response,GrafanaError> {
+        self.seen.lock().unwrap().push(request.url.clone());
+        Ok(Response::json("[]"))
+    }
+}
+
+#[test]
+fn test_custom_transport() {
+    let echo = Arc::new(Echo::default());
+    let client = ClientBuilder::new("http://grafana.test/sub")
+        .with_auth(Auth::Anonymous)
+        .with_transport(echo.clone())
+        .build()
+        .unwrap();
+    client.search().dashboard(None).unwrap();
+    assert_eq!(*echo.seen.lock().unwrap(), vec![String::from("http://grafana.test/sub/api/search?type=dash-db")]);
+}
@@ -0,0 +1,200 @@
+//! Minimal HTTP/1.1 handling, one request per connection
+
+use std::collections::HashMap;
+use std::io::{self,BufRead,BufReader,Read,Write};
+
+use serde_json::Value;
+
+/// A request received by the server
+#[derive(Clone,Debug,Default)]
+pub struct Request {
+    /// HTTP method
+    pub method : String,
+    /// Path without the query string
+    pub path : String,
+    /// Decoded query parameters, repeated keys keep every value
+    pub query : Vec<(String,String)>,
+    /// Headers, keys lower-cased
+    pub headers : HashMap<String,String>,
+    /// Request body
+    pub body : String,
+}
+
+impl Request {
+    /// Look up a header by (case-insensitive) name
+    pub fn header(&self, name : &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(|h| h.as_str())
+    }
+
+    /// First value of a query parameter
+    pub fn param(&self, name : &str) -> Option<&str> {
+        self.query.iter().find(|(k,_)| k == name).map(|(_,v)| v.as_str())
+    }
+
+    /// Every value of a query parameter, splitting comma separated lists
+    pub fn params(&self, name : &str) -> Vec<&str> {
+        self.query.iter()
+            .filter(|(k,_)| k == name)
+            .flat_map(|(_,v)| v.split(','))
+            .filter(|v| !v.is_empty())
+            .collect()
+    }
+
+    /// Body parsed as JSON, `null` when empty
+    pub fn json(&self) -> Result<Value,serde_json::Error> {
+        match self.body.trim().is_empty() {
+            true => Ok(Value::Null),
+            false => serde_json::from_str(&self.body),
+        }
+    }
+
+    /// Read a request from a stream, None when the client closed the connection
+    pub(crate) fn read<S : Read>(stream : S) -> io::Result<Option<Request>> {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let mut parts = line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let mut request = Request {
+            method,
+            path : decode(path),
+            query : query.split('&')
+                .filter(|p| !p.is_empty())
+                .map(|p| {
+                    let (k,v) = p.split_once('=').unwrap_or((p, ""));
+                    (decode(k), decode(v))
+                })
+                .collect(),
+            ..Default::default()
+        };
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((k,v)) = line.split_once(':') {
+                request.headers.insert(k.trim().to_lowercase(), v.trim().to_string());
+            }
+        }
+        let length = request.header("content-length").and_then(|l| l.parse().ok()).unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        request.body = String::from_utf8_lossy(&body).to_string();
+        Ok(Some(request))
+    }
+}
+
+/// A response to send back
+#[derive(Clone,Debug,PartialEq)]
+pub struct Response {
+    /// HTTP status code
+    pub status : u16,
+    /// JSON body, omitted when `null`
+    pub body : Value,
+}
+
+impl Response {
+    /// Response with a JSON body
+    pub fn new(status : u16, body : Value) -> Response {
+        Response { status, body }
+    }
+
+    /// 200 response with a JSON body
+    pub fn ok(body : Value) -> Response {
+        Response::new(200, body)
+    }
+
+    /// Error response shaped like Grafana's, with an optional status string such as
+    /// `version-mismatch`
+    pub fn error(status : u16, message : &str, kind : Option<&str>) -> Response {
+        let mut body = serde_json::json!({ "message" : message });
+        if let Some(k) = kind {
+            body["status"] = Value::from(k);
+        }
+        Response::new(status, body)
+    }
+
+    /// Write the response, closing the connection afterwards
+    pub(crate) fn write<S : Write>(&self, mut stream : S) -> io::Result<()> {
+        let body = match self.body {
+            Value::Null => String::new(),
+            ref b => b.to_string(),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason(self.status),
+            body.len(),
+            body,
+        )?;
+        stream.flush()
+    }
+}
+
+/// Percent-decode a path segment or query component
+fn decode(s : &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    },
+                    None => out.push(b'%'),
+                }
+            },
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Reason phrase for the status codes the mock uses
+fn reason(status : u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        412 => "Precondition Failed",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_request() {
+        let raw = "GET /api/search?type=dash-db&query=My%20Dash&tag=a&tag=b HTTP/1.1\r\nHost: x\r\nX-Grafana-Org-Id: 2\r\n\r\n";
+        let request = Request::read(raw.as_bytes()).unwrap().unwrap();
+        assert_eq!(request.path, "/api/search");
+        assert_eq!(request.param("query"), Some("My Dash"));
+        assert_eq!(request.params("tag"), vec!["a", "b"]);
+        assert_eq!(request.header("x-grafana-org-id"), Some("2"));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("a+b%2Fc%"), "a b/c%");
+    }
+}
@@ -0,0 +1,180 @@
+//! Fake Grafana server for tests
+//!
+//! [`MockServer`] listens on an ephemeral local port and implements the subset of the
+//! Grafana HTTP API covered by `grafana-lib`: search, dashboards by uid, folders, data
+//! sources, annotations and alerting provisioning. State is kept in memory, objects get
+//! ids, uids and version counters like they would in Grafana, and errors use Grafana's
+//! status codes and JSON bodies.
+//!
+//! # Example
+//! ```
+//! # use grafana_mock::MockServer;
+//! # use serde_json::json;
+//! let server = MockServer::start();
+//! let folder = server.add_folder("ops", "Operations");
+//! let uid = server.add_dashboard(json!({ "title" : "Node Exporter" }), Some(&folder));
+//! assert_eq!(server.dashboard(&uid).unwrap()["version"], 1);
+//! println!("Grafana running at {}", server.url());
+//! ```
+
+pub mod http;
+mod state;
+
+use std::io;
+use std::net::{SocketAddr,TcpListener,TcpStream};
+use std::sync::{Arc,Mutex,MutexGuard};
+use std::sync::atomic::{AtomicBool,Ordering};
+use std::thread::{self,JoinHandle};
+
+use log::{debug,error};
+use serde_json::{json,Value};
+
+use crate::http::{Request,Response};
+use crate::state::State;
+
+pub use crate::state::GRAFANA_VERSION;
+
+/// In-memory Grafana instance, stopped when dropped
+#[derive(Debug)]
+pub struct MockServer {
+    addr : SocketAddr,
+    state : Arc<Mutex<State>>,
+    stop : Arc<AtomicBool>,
+    handle : Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Start a server on an ephemeral port of 127.0.0.1
+    pub fn start() -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock Grafana server");
+        let addr = listener.local_addr().expect("mock Grafana server address");
+        let state = Arc::new(Mutex::new(State::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let state = state.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let state = state.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = serve(stream, &state) {
+                            error!("Mock Grafana connection failed : {e}");
+                        }
+                    });
+                }
+            })
+        };
+        debug!("Mock Grafana listening on {addr}");
+        MockServer { addr, state, stop, handle : Some(handle) }
+    }
+
+    /// Only accept requests carrying `Authorization: Bearer <token>`, answering others with
+    /// 401. The health endpoint stays open.
+    pub fn require_token(self, token : &str) -> MockServer {
+        self.lock().token = Some(token.to_string());
+        self
+    }
+
+    /// Report another Grafana version from the health and frontend settings endpoints
+    pub fn with_version(self, version : &str) -> MockServer {
+        self.lock().version = Some(version.to_string());
+        self
+    }
+
+    /// Report a feature toggle as enabled or explicitly disabled in the frontend settings
+    pub fn with_feature_toggle(self, name : &str, enabled : bool) -> MockServer {
+        self.lock().feature_toggles.insert(name.to_string(), Value::Bool(enabled));
+        self
+    }
+
+    /// Base URL of the server, e.g. `http://127.0.0.1:41234`
+    pub fn url(&self) -> String {
+        format!("http://{}",self.addr)
+    }
+
+    /// Create a folder and return its uid
+    pub fn add_folder(&self, uid : &str, title : &str) -> String {
+        let response = self.lock().create_folder(json!({ "uid" : uid, "title" : title }));
+        created(response, "folder")["uid"].as_str().unwrap_or_default().to_string()
+    }
+
+    /// Create a folder inside another folder and return its uid
+    pub fn add_subfolder(&self, uid : &str, title : &str, parent_uid : &str) -> String {
+        let response = self.lock().create_folder(json!({ "uid" : uid, "title" : title, "parentUid" : parent_uid }));
+        created(response, "folder")["uid"].as_str().unwrap_or_default().to_string()
+    }
+
+    /// Save a dashboard model, optionally in a folder, and return its uid
+    pub fn add_dashboard(&self, dashboard : Value, folder_uid : Option<&str>) -> String {
+        let body = json!({ "dashboard" : dashboard, "folderUid" : folder_uid, "overwrite" : true });
+        let response = self.lock().save_dashboard(body);
+        created(response, "dashboard")["uid"].as_str().unwrap_or_default().to_string()
+    }
+
+    /// Create a data source and return the stored definition
+    pub fn add_data_source(&self, data_source : Value) -> Value {
+        created(self.lock().create_data_source(data_source), "data source")["datasource"].clone()
+    }
+
+    /// Create an annotation and return its id
+    pub fn add_annotation(&self, annotation : Value) -> u64 {
+        created(self.lock().create_annotation(annotation), "annotation")["id"].as_u64().unwrap_or_default()
+    }
+
+    /// Provision an alert rule and return the stored rule
+    pub fn add_alert_rule(&self, rule : Value) -> Value {
+        created(self.lock().create_alert_rule(rule), "alert rule")
+    }
+
+    /// Provision a contact point and return the stored contact point
+    pub fn add_contact_point(&self, contact_point : Value) -> Value {
+        created(self.lock().create_contact_point(contact_point), "contact point")
+    }
+
+    /// Current JSON model of a dashboard
+    pub fn dashboard(&self, uid : &str) -> Option<Value> {
+        self.lock().dashboard_model(uid)
+    }
+
+    /// Requests received so far, in order
+    pub fn requests(&self) -> Vec<Request> {
+        self.lock().requests.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_,State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Answer a single request on a connection
+fn serve(stream : TcpStream, state : &Mutex<State>) -> io::Result<()> {
+    let Some(request) = Request::read(&stream)? else {
+        return Ok(());
+    };
+    debug!("Mock Grafana {} {}",request.method,request.path);
+    let response = state.lock().unwrap_or_else(|e| e.into_inner()).handle(&request);
+    response.write(&stream)
+}
+
+/// Body of a seeding response, panicking with Grafana's message when it failed
+fn created(response : Response, kind : &str) -> Value {
+    if response.status >= 300 {
+        panic!("could not add {kind} : {} {}",response.status,response.body);
+    }
+    response.body
+}
@@ -0,0 +1,756 @@
+//! In-memory Grafana state and request routing
+//!
+//! Each handler mirrors the behaviour of the real endpoint closely enough for client tests:
+//! ids and uids are assigned on create, versions are bumped on every save and checked on
+//! update, and failures use the same status codes and error bodies as Grafana.
+
+use serde_json::{json,Map,Value};
+
+use crate::http::{Request,Response};
+
+/// Version reported by the health endpoint
+pub const GRAFANA_VERSION : &str = "10.4.0";
+
+/// Timestamp used for created and updated fields, fixed so responses are deterministic
+const TIMESTAMP : &str = "2024-01-01T00:00:00Z";
+
+/// A folder
+#[derive(Clone,Debug)]
+struct Folder {
+    id : u64,
+    uid : String,
+    title : String,
+    parent_uid : Option<String>,
+    version : u64,
+}
+
+/// A saved dashboard, the JSON model carries its own id, uid, title and version
+#[derive(Clone,Debug)]
+struct StoredDashboard {
+    folder_uid : Option<String>,
+    model : Value,
+}
+
+impl StoredDashboard {
+    fn uid(&self) -> &str {
+        self.model["uid"].as_str().unwrap_or_default()
+    }
+    fn title(&self) -> &str {
+        self.model["title"].as_str().unwrap_or_default()
+    }
+    fn version(&self) -> u64 {
+        self.model["version"].as_u64().unwrap_or_default()
+    }
+    fn id(&self) -> u64 {
+        self.model["id"].as_u64().unwrap_or_default()
+    }
+}
+
+/// Everything the fake Grafana instance knows about
+#[derive(Debug,Default)]
+pub(crate) struct State {
+    pub(crate) token : Option<String>,
+    pub(crate) version : Option<String>,
+    pub(crate) feature_toggles : Map<String,Value>,
+    pub(crate) requests : Vec<Request>,
+    next_id : u64,
+    folders : Vec<Folder>,
+    dashboards : Vec<StoredDashboard>,
+    data_sources : Vec<Value>,
+    annotations : Vec<Value>,
+    alert_rules : Vec<Value>,
+    contact_points : Vec<Value>,
+}
+
+impl State {
+    /// Route a request to its handler
+    pub(crate) fn handle(&mut self, request : &Request) -> Response {
+        self.requests.push(request.clone());
+        let segments : Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        let method = request.method.as_str();
+        if segments[..] != ["api", "health"] {
+            if let Some(denied) = self.authorize(request) {
+                return denied;
+            }
+        }
+        let body = match request.json() {
+            Ok(b) => b,
+            Err(e) => return Response::error(400, &format!("bad request data : {e}"), None),
+        };
+        match (method, &segments[..]) {
+            ("GET", ["api", "health"]) => Response::ok(json!({ "commit" : "mock", "database" : "ok", "version" : self.version() })),
+            ("GET", ["api", "frontend", "settings"]) => self.frontend_settings(),
+            ("GET", ["api", "search"]) => self.search(request),
+            ("POST", ["api", "dashboards", "db"]) => self.save_dashboard(body),
+            ("GET", ["api", "dashboards", "uid", uid]) => self.get_dashboard(uid),
+            ("DELETE", ["api", "dashboards", "uid", uid]) => self.delete_dashboard(uid),
+            ("GET", ["api", "folders"]) => self.list_folders(request),
+            ("POST", ["api", "folders"]) => self.create_folder(body),
+            ("GET", ["api", "folders", uid]) => self.get_folder(uid),
+            ("PUT", ["api", "folders", uid]) => self.update_folder(uid, body),
+            ("DELETE", ["api", "folders", uid]) => self.delete_folder(uid),
+            ("GET", ["api", "datasources"]) => Response::ok(Value::from(self.data_sources.clone())),
+            ("POST", ["api", "datasources"]) => self.create_data_source(body),
+            ("GET", ["api", "datasources", "uid", uid]) => self.get_data_source("uid", uid),
+            ("GET", ["api", "datasources", "name", name]) => self.get_data_source("name", name),
+            ("PUT", ["api", "datasources", "uid", uid]) => self.update_data_source(uid, body),
+            ("DELETE", ["api", "datasources", "uid", uid]) => self.delete_data_source(uid),
+            ("GET", ["api", "annotations"]) => self.list_annotations(request),
+            ("POST", ["api", "annotations"]) => self.create_annotation(body),
+            ("GET", ["api", "v1", "provisioning", "alert-rules"]) => Response::ok(Value::from(self.alert_rules.clone())),
+            ("POST", ["api", "v1", "provisioning", "alert-rules"]) => self.create_alert_rule(body),
+            ("GET", ["api", "v1", "provisioning", "alert-rules", uid]) => self.get_alert_rule(uid),
+            ("PUT", ["api", "v1", "provisioning", "alert-rules", uid]) => self.update_alert_rule(uid, body),
+            ("DELETE", ["api", "v1", "provisioning", "alert-rules", uid]) => self.delete_alert_rule(uid),
+            ("GET", ["api", "v1", "provisioning", "contact-points"]) => self.list_contact_points(request),
+            ("POST", ["api", "v1", "provisioning", "contact-points"]) => self.create_contact_point(body),
+            ("PUT", ["api", "v1", "provisioning", "contact-points", uid]) => self.update_contact_point(uid, body),
+            ("DELETE", ["api", "v1", "provisioning", "contact-points", uid]) => self.delete_contact_point(uid),
+            _ => Response::error(404, "Not found", None),
+        }
+    }
+
+    /// Reject requests without the expected bearer token, when one is required
+    fn authorize(&self, request : &Request) -> Option<Response> {
+        let token = self.token.as_ref()?;
+        match request.header("authorization") == Some(format!("Bearer {token}").as_str()) {
+            true => None,
+            false => Some(Response::new(401, json!({
+                "message" : "invalid API key",
+                "messageId" : "api-key.invalid",
+                "statusCode" : 401,
+                "traceID" : "",
+            }))),
+        }
+    }
+
+    fn version(&self) -> &str {
+        self.version.as_deref().unwrap_or(GRAFANA_VERSION)
+    }
+
+    fn frontend_settings(&self) -> Response {
+        Response::ok(json!({
+            "appUrl" : "http://localhost:3000/",
+            "appSubUrl" : "",
+            "defaultDatasource" : self.data_sources.iter().find(|d| d["isDefault"] == true).map(|d| d["name"].clone()),
+            "buildInfo" : {
+                "version" : self.version(),
+                "commit" : "mock",
+                "edition" : "Open Source",
+                "env" : "production",
+                "buildstamp" : 1_704_067_200,
+                "latestVersion" : "",
+                "hasUpdate" : false,
+                "hideVersion" : false,
+            },
+            "featureToggles" : self.feature_toggles,
+        }))
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Deterministic uid for a new object
+    fn next_uid(&mut self, prefix : char) -> (u64,String) {
+        let id = self.next_id();
+        (id, format!("{prefix}{id:08}"))
+    }
+
+    // Search
+
+    fn search(&self, request : &Request) -> Response {
+        let kind = request.param("type");
+        let query = request.param("query").map(|q| q.to_lowercase());
+        let tags = request.params("tag");
+        let folder_uids = request.params("folderUIDs");
+        let dashboard_uids = request.params("dashboardUIDs");
+        let limit = request.param("limit").and_then(|l| l.parse().ok()).unwrap_or(1000);
+        let matches = |title : &str| query.as_ref().map(|q| title.to_lowercase().contains(q)).unwrap_or(true);
+
+        let mut hits = Vec::new();
+        if kind.is_none() || kind == Some("dash-folder") {
+            for f in self.folders.iter().filter(|f| matches(&f.title) && tags.is_empty() && dashboard_uids.is_empty()) {
+                hits.push(json!({
+                    "id" : f.id,
+                    "uid" : f.uid,
+                    "title" : f.title,
+                    "uri" : format!("db/{}",slug(&f.title)),
+                    "url" : folder_url(f),
+                    "slug" : "",
+                    "type" : "dash-folder",
+                    "tags" : [],
+                    "isStarred" : false,
+                    "sortMeta" : 0,
+                }));
+            }
+        }
+        if kind.is_none() || kind == Some("dash-db") {
+            for d in self.dashboards.iter() {
+                let dashboard_tags : Vec<&str> = d.model["tags"].as_array()
+                    .map(|t| t.iter().filter_map(|t| t.as_str()).collect())
+                    .unwrap_or_default();
+                if !matches(d.title())
+                    || !tags.iter().all(|t| dashboard_tags.contains(t))
+                    || (!folder_uids.is_empty() && !folder_uids.contains(&d.folder_uid.as_deref().unwrap_or("general")))
+                    || (!dashboard_uids.is_empty() && !dashboard_uids.contains(&d.uid())) {
+                    continue;
+                }
+                let mut hit = json!({
+                    "id" : d.id(),
+                    "uid" : d.uid(),
+                    "title" : d.title(),
+                    "uri" : format!("db/{}",slug(d.title())),
+                    "url" : dashboard_url(d),
+                    "slug" : "",
+                    "type" : "dash-db",
+                    "tags" : dashboard_tags,
+                    "isStarred" : false,
+                    "sortMeta" : 0,
+                });
+                if let Some(f) = d.folder_uid.as_ref().and_then(|uid| self.folder(uid)) {
+                    hit["folderId"] = json!(f.id);
+                    hit["folderUid"] = json!(f.uid);
+                    hit["folderTitle"] = json!(f.title);
+                    hit["folderUrl"] = json!(folder_url(f));
+                }
+                hits.push(hit);
+            }
+        }
+        hits.sort_by_key(|h| h["title"].as_str().unwrap_or_default().to_lowercase());
+        hits.truncate(limit);
+        Response::ok(Value::from(hits))
+    }
+
+    // Dashboards
+
+    pub(crate) fn save_dashboard(&mut self, body : Value) -> Response {
+        let Some(mut model) = body.get("dashboard").filter(|d| d.is_object()).cloned() else {
+            return Response::error(400, "bad request data", None);
+        };
+        let title = model["title"].as_str().unwrap_or_default().trim().to_string();
+        if title.is_empty() {
+            return Response::error(400, "Dashboard title cannot be empty", Some("empty-name"));
+        }
+        let overwrite = body["overwrite"].as_bool().unwrap_or(false);
+        let folder_uid = match body["folderUid"].as_str().filter(|u| !u.is_empty()) {
+            Some(uid) => match self.folder(uid) {
+                Some(f) => Some(f.uid.clone()),
+                None => return Response::error(400, "folder not found", Some("not-found")),
+            },
+            None => match body["folderId"].as_u64().filter(|id| *id != 0) {
+                Some(id) => match self.folders.iter().find(|f| f.id == id) {
+                    Some(f) => Some(f.uid.clone()),
+                    None => return Response::error(400, "folder not found", Some("not-found")),
+                },
+                None => None,
+            },
+        };
+
+        let uid = model["uid"].as_str().filter(|u| !u.is_empty()).map(String::from);
+        let existing = uid.as_ref()
+            .and_then(|uid| self.dashboards.iter().position(|d| d.uid() == uid))
+            .or_else(|| model["id"].as_u64().and_then(|id| self.dashboards.iter().position(|d| d.id() == id)));
+        if existing.is_none() && model["id"].as_u64().is_some() && uid.is_none() {
+            return Response::error(404, "Dashboard not found", Some("not-found"));
+        }
+
+        // Another dashboard in the same folder with the same title
+        let clash = self.dashboards.iter().enumerate().find(|(i,d)| {
+            Some(*i) != existing && d.folder_uid == folder_uid && d.title().eq_ignore_ascii_case(&title)
+        }).map(|(i,_)| i);
+        if let Some(i) = clash {
+            if !overwrite {
+                return Response::error(412, "A dashboard with the same name in the folder already exists", Some("name-exists"));
+            }
+            self.dashboards.remove(i);
+        }
+        let existing = existing.map(|e| match clash {
+            Some(c) if c < e => e - 1,
+            _ => e,
+        });
+
+        let (id, uid, version) = match existing {
+            Some(i) => {
+                let stored = &self.dashboards[i];
+                if !overwrite && model["version"].as_u64() != Some(stored.version()) {
+                    return Response::error(412, "The dashboard has been changed by someone else", Some("version-mismatch"));
+                }
+                (stored.id(), stored.uid().to_string(), stored.version() + 1)
+            },
+            None => {
+                let (id, generated) = self.next_uid('d');
+                (id, uid.unwrap_or(generated), 1)
+            },
+        };
+        model["id"] = json!(id);
+        model["uid"] = json!(uid);
+        model["version"] = json!(version);
+        let stored = StoredDashboard { folder_uid : folder_uid.clone(), model };
+        let url = dashboard_url(&stored);
+        match existing {
+            Some(i) => self.dashboards[i] = stored,
+            None => self.dashboards.push(stored),
+        }
+        Response::ok(json!({
+            "id" : id,
+            "uid" : uid,
+            "url" : url,
+            "status" : "success",
+            "version" : version,
+            "slug" : slug(&title),
+            "folderUid" : folder_uid.unwrap_or_default(),
+        }))
+    }
+
+    fn get_dashboard(&self, uid : &str) -> Response {
+        let Some(d) = self.dashboard(uid) else {
+            return Response::error(404, "Dashboard not found", Some("not-found"));
+        };
+        let folder = d.folder_uid.as_ref().and_then(|uid| self.folder(uid));
+        Response::ok(json!({
+            "dashboard" : d.model,
+            "meta" : {
+                "type" : "db",
+                "canSave" : true,
+                "canEdit" : true,
+                "canAdmin" : true,
+                "canStar" : true,
+                "canDelete" : true,
+                "slug" : slug(d.title()),
+                "url" : dashboard_url(d),
+                "expires" : "0001-01-01T00:00:00Z",
+                "created" : TIMESTAMP,
+                "updated" : TIMESTAMP,
+                "updatedBy" : "admin",
+                "createdBy" : "admin",
+                "version" : d.version(),
+                "hasAcl" : false,
+                "isFolder" : false,
+                "folderId" : folder.map(|f| f.id).unwrap_or_default(),
+                "folderUid" : folder.map(|f| f.uid.clone()).unwrap_or_default(),
+                "folderTitle" : folder.map(|f| f.title.clone()).unwrap_or(String::from("General")),
+                "folderUrl" : folder.map(folder_url).unwrap_or_default(),
+                "provisioned" : false,
+                "provisionedExternalId" : "",
+            },
+        }))
+    }
+
+    fn delete_dashboard(&mut self, uid : &str) -> Response {
+        let Some(i) = self.dashboards.iter().position(|d| d.uid() == uid) else {
+            return Response::error(404, "Dashboard not found", Some("not-found"));
+        };
+        let d = self.dashboards.remove(i);
+        Response::ok(json!({
+            "title" : d.title(),
+            "message" : format!("Dashboard {} deleted",d.title()),
+            "id" : d.id(),
+        }))
+    }
+
+    fn dashboard(&self, uid : &str) -> Option<&StoredDashboard> {
+        self.dashboards.iter().find(|d| d.uid() == uid)
+    }
+
+    pub(crate) fn dashboard_model(&self, uid : &str) -> Option<Value> {
+        self.dashboard(uid).map(|d| d.model.clone())
+    }
+
+    // Folders
+
+    fn folder(&self, uid : &str) -> Option<&Folder> {
+        self.folders.iter().find(|f| f.uid == uid)
+    }
+
+    fn folder_json(f : &Folder) -> Value {
+        let mut folder = json!({
+            "id" : f.id,
+            "uid" : f.uid,
+            "title" : f.title,
+            "url" : folder_url(f),
+            "hasAcl" : false,
+            "canSave" : true,
+            "canEdit" : true,
+            "canAdmin" : true,
+            "canDelete" : true,
+            "createdBy" : "admin",
+            "created" : TIMESTAMP,
+            "updatedBy" : "admin",
+            "updated" : TIMESTAMP,
+            "version" : f.version,
+        });
+        if let Some(parent) = &f.parent_uid {
+            folder["parentUid"] = json!(parent);
+        }
+        folder
+    }
+
+    /// Folders inside `parentUid`, or at the top level without it
+    fn list_folders(&self, request : &Request) -> Response {
+        let parent = request.param("parentUid");
+        Response::ok(self.folders.iter()
+            .filter(|f| f.parent_uid.as_deref() == parent)
+            .map(|f| json!({ "id" : f.id, "uid" : f.uid, "title" : f.title }))
+            .collect())
+    }
+
+    fn get_folder(&self, uid : &str) -> Response {
+        match self.folder(uid) {
+            Some(f) => Response::ok(State::folder_json(f)),
+            None => Response::error(404, "folder not found", Some("not-found")),
+        }
+    }
+
+    pub(crate) fn create_folder(&mut self, body : Value) -> Response {
+        let title = body["title"].as_str().unwrap_or_default().trim().to_string();
+        if title.is_empty() {
+            return Response::error(400, "folder title cannot be empty", None);
+        }
+        if self.folders.iter().any(|f| f.title.eq_ignore_ascii_case(&title)) {
+            return Response::error(409, "a folder with the same name already exists", None);
+        }
+        let uid = body["uid"].as_str().filter(|u| !u.is_empty()).map(String::from);
+        if let Some(uid) = &uid {
+            if self.folder(uid).is_some() {
+                return Response::error(409, "a folder with the same uid already exists", None);
+            }
+        }
+        let parent_uid = body["parentUid"].as_str().filter(|u| !u.is_empty()).map(String::from);
+        if parent_uid.as_ref().is_some_and(|p| self.folder(p).is_none()) {
+            return Response::error(400, "parent folder not found", None);
+        }
+        let (id, generated) = self.next_uid('f');
+        let folder = Folder { id, uid : uid.unwrap_or(generated), title, parent_uid, version : 1 };
+        let response = Response::ok(State::folder_json(&folder));
+        self.folders.push(folder);
+        response
+    }
+
+    fn update_folder(&mut self, uid : &str, body : Value) -> Response {
+        let Some(i) = self.folders.iter().position(|f| f.uid == uid) else {
+            return Response::error(404, "folder not found", Some("not-found"));
+        };
+        let overwrite = body["overwrite"].as_bool().unwrap_or(false);
+        if !overwrite && body["version"].as_u64() != Some(self.folders[i].version) {
+            return Response::error(412, "the folder has been changed by someone else", Some("version-mismatch"));
+        }
+        if let Some(title) = body["title"].as_str().filter(|t| !t.trim().is_empty()) {
+            if self.folders.iter().any(|f| f.uid != uid && f.title.eq_ignore_ascii_case(title)) {
+                return Response::error(409, "a folder with the same name already exists", None);
+            }
+            self.folders[i].title = title.trim().to_string();
+        }
+        self.folders[i].version += 1;
+        Response::ok(State::folder_json(&self.folders[i]))
+    }
+
+    fn delete_folder(&mut self, uid : &str) -> Response {
+        let Some(i) = self.folders.iter().position(|f| f.uid == uid) else {
+            return Response::error(404, "folder not found", Some("not-found"));
+        };
+        let f = self.folders.remove(i);
+        self.dashboards.retain(|d| d.folder_uid.as_deref() != Some(uid));
+        self.alert_rules.retain(|r| r["folderUID"].as_str() != Some(uid));
+        Response::ok(json!({ "title" : f.title, "message" : format!("Folder {} deleted",f.title), "id" : f.id }))
+    }
+
+    // Data sources
+
+    pub(crate) fn create_data_source(&mut self, body : Value) -> Response {
+        let Value::Object(mut ds) = body else {
+            return Response::error(400, "bad request data", None);
+        };
+        let name = ds.get("name").and_then(|n| n.as_str()).unwrap_or_default().trim().to_string();
+        if name.is_empty() {
+            return Response::error(400, "data source name cannot be empty", None);
+        }
+        if self.data_sources.iter().any(|d| d["name"] == name.as_str()) {
+            return Response::error(409, "data source with the same name already exists", None);
+        }
+        let (id, generated) = self.next_uid('s');
+        let uid = ds.get("uid").and_then(|u| u.as_str()).filter(|u| !u.is_empty()).map(String::from).unwrap_or(generated);
+        ds.insert(String::from("id"), json!(id));
+        ds.insert(String::from("uid"), json!(uid));
+        ds.insert(String::from("orgId"), json!(1));
+        ds.insert(String::from("version"), json!(1));
+        for (key, default) in [("access", json!("proxy")), ("url", json!("")), ("basicAuth", json!(false)), ("isDefault", json!(false)), ("readOnly", json!(false)), ("jsonData", json!({}))] {
+            ds.entry(key).or_insert(default);
+        }
+        let ds = redact_secure(ds);
+        self.data_sources.push(ds.clone());
+        Response::ok(json!({ "datasource" : ds, "id" : id, "message" : "Datasource added", "name" : name }))
+    }
+
+    fn get_data_source(&self, field : &str, value : &str) -> Response {
+        match self.data_sources.iter().find(|d| d[field] == value) {
+            Some(d) => Response::ok(d.clone()),
+            None => Response::error(404, "Data source not found", None),
+        }
+    }
+
+    fn update_data_source(&mut self, uid : &str, body : Value) -> Response {
+        let Some(i) = self.data_sources.iter().position(|d| d["uid"] == uid) else {
+            return Response::error(404, "Data source not found", None);
+        };
+        let Value::Object(mut ds) = body else {
+            return Response::error(400, "bad request data", None);
+        };
+        let current = &self.data_sources[i];
+        if let Some(version) = ds.get("version").and_then(|v| v.as_u64()) {
+            if Some(version) != current["version"].as_u64() {
+                return Response::error(409, "Datasource has already been updated by someone else. Please reload and try again", None);
+            }
+        }
+        let name = ds.get("name").and_then(|n| n.as_str()).unwrap_or_default().to_string();
+        ds.insert(String::from("id"), current["id"].clone());
+        ds.insert(String::from("uid"), json!(uid));
+        ds.insert(String::from("orgId"), json!(1));
+        ds.insert(String::from("version"), json!(current["version"].as_u64().unwrap_or_default() + 1));
+        let ds = redact_secure(ds);
+        self.data_sources[i] = ds.clone();
+        Response::ok(json!({ "datasource" : ds, "id" : ds["id"], "message" : "Datasource updated", "name" : name }))
+    }
+
+    fn delete_data_source(&mut self, uid : &str) -> Response {
+        let Some(i) = self.data_sources.iter().position(|d| d["uid"] == uid) else {
+            return Response::error(404, "Data source not found", None);
+        };
+        let ds = self.data_sources.remove(i);
+        Response::ok(json!({ "message" : "Data source deleted", "id" : ds["id"] }))
+    }
+
+    // Annotations
+
+    fn list_annotations(&self, request : &Request) -> Response {
+        let limit = request.param("limit").and_then(|l| l.parse().ok()).unwrap_or(100);
+        let dashboard_id = request.param("dashboardId").and_then(|d| d.parse::<u64>().ok());
+        let dashboard_uid = request.param("dashboardUID");
+        let tags = request.params("tags");
+        let mut annotations : Vec<Value> = self.annotations.iter()
+            .filter(|a| dashboard_id.map(|id| a["dashboardId"] == id).unwrap_or(true))
+            .filter(|a| dashboard_uid.map(|uid| a["dashboardUID"] == uid).unwrap_or(true))
+            .filter(|a| tags.iter().all(|t| a["tags"].as_array().map(|ts| ts.iter().any(|x| x == t)).unwrap_or(false)))
+            .cloned()
+            .collect();
+        annotations.sort_by_key(|a| std::cmp::Reverse(a["time"].as_u64().unwrap_or_default()));
+        annotations.truncate(limit);
+        Response::ok(Value::from(annotations))
+    }
+
+    pub(crate) fn create_annotation(&mut self, body : Value) -> Response {
+        let Value::Object(mut a) = body else {
+            return Response::error(400, "bad request data", None);
+        };
+        if a.get("text").and_then(|t| t.as_str()).unwrap_or_default().is_empty() {
+            return Response::error(400, "Failed to save annotation", None);
+        }
+        let dashboard = match a.get("dashboardUID").and_then(|u| u.as_str()) {
+            Some(uid) => match self.dashboard(uid) {
+                Some(d) => Some((d.id(), d.uid().to_string())),
+                None => return Response::error(404, "Dashboard not found", Some("not-found")),
+            },
+            None => None,
+        };
+        let id = self.next_id();
+        let time = a.get("time").and_then(|t| t.as_u64()).unwrap_or(1_704_067_200_000);
+        a.insert(String::from("id"), json!(id));
+        a.insert(String::from("alertId"), json!(0));
+        a.insert(String::from("dashboardId"), json!(dashboard.as_ref().map(|d| d.0).or(a.get("dashboardId").and_then(|d| d.as_u64())).unwrap_or_default()));
+        a.insert(String::from("dashboardUID"), json!(dashboard.map(|d| d.1)));
+        a.entry("panelId").or_insert(json!(0));
+        a.entry("tags").or_insert(json!([]));
+        a.insert(String::from("time"), json!(time));
+        a.entry("timeEnd").or_insert(json!(time));
+        a.insert(String::from("login"), json!("admin"));
+        self.annotations.push(Value::Object(a));
+        Response::ok(json!({ "message" : "Annotation added", "id" : id }))
+    }
+
+    // Alerting provisioning
+
+    pub(crate) fn create_alert_rule(&mut self, body : Value) -> Response {
+        let Value::Object(mut rule) = body else {
+            return Response::error(400, "bad request data", None);
+        };
+        for field in ["title", "folderUID", "ruleGroup"] {
+            if rule.get(field).and_then(|v| v.as_str()).unwrap_or_default().is_empty() {
+                return Response::error(400, &format!("invalid alert rule: {field} is required"), None);
+            }
+        }
+        let folder_uid = rule["folderUID"].as_str().unwrap_or_default();
+        if self.folder(folder_uid).is_none() {
+            return Response::error(400, "invalid alert rule: folder does not exist", None);
+        }
+        let (id, generated) = self.next_uid('r');
+        let uid = rule.get("uid").and_then(|u| u.as_str()).filter(|u| !u.is_empty()).map(String::from).unwrap_or(generated);
+        if self.alert_rules.iter().any(|r| r["uid"] == uid.as_str()) {
+            return Response::error(409, "a rule with the same uid already exists", None);
+        }
+        rule.insert(String::from("id"), json!(id));
+        rule.insert(String::from("uid"), json!(uid));
+        rule.insert(String::from("orgID"), json!(1));
+        rule.insert(String::from("updated"), json!(TIMESTAMP));
+        rule.insert(String::from("provenance"), json!("api"));
+        for (key, default) in [("condition", json!("A")), ("data", json!([])), ("noDataState", json!("NoData")), ("execErrState", json!("Error")), ("for", json!("0s")), ("labels", json!({})), ("annotations", json!({})), ("isPaused", json!(false))] {
+            rule.entry(key).or_insert(default);
+        }
+        let rule = Value::Object(rule);
+        self.alert_rules.push(rule.clone());
+        Response::new(201, rule)
+    }
+
+    fn get_alert_rule(&self, uid : &str) -> Response {
+        match self.alert_rules.iter().find(|r| r["uid"] == uid) {
+            Some(r) => Response::ok(r.clone()),
+            None => Response::error(404, "rule not found", None),
+        }
+    }
+
+    fn update_alert_rule(&mut self, uid : &str, body : Value) -> Response {
+        let Some(i) = self.alert_rules.iter().position(|r| r["uid"] == uid) else {
+            return Response::error(404, "rule not found", None);
+        };
+        let Value::Object(mut rule) = body else {
+            return Response::error(400, "bad request data", None);
+        };
+        rule.insert(String::from("id"), self.alert_rules[i]["id"].clone());
+        rule.insert(String::from("uid"), json!(uid));
+        rule.insert(String::from("orgID"), json!(1));
+        rule.insert(String::from("updated"), json!(TIMESTAMP));
+        rule.insert(String::from("provenance"), json!("api"));
+        self.alert_rules[i] = Value::Object(rule);
+        Response::ok(self.alert_rules[i].clone())
+    }
+
+    fn delete_alert_rule(&mut self, uid : &str) -> Response {
+        self.alert_rules.retain(|r| r["uid"] != uid);
+        Response::new(204, Value::Null)
+    }
+
+    fn list_contact_points(&self, request : &Request) -> Response {
+        let name = request.param("name");
+        Response::ok(self.contact_points.iter()
+            .filter(|c| name.map(|n| c["name"] == n).unwrap_or(true))
+            .cloned()
+            .collect())
+    }
+
+    pub(crate) fn create_contact_point(&mut self, body : Value) -> Response {
+        let Value::Object(mut point) = body else {
+            return Response::error(400, "bad request data", None);
+        };
+        for field in ["name", "type"] {
+            if point.get(field).and_then(|v| v.as_str()).unwrap_or_default().is_empty() {
+                return Response::error(400, &format!("invalid contact point: {field} is required"), None);
+            }
+        }
+        let (_, generated) = self.next_uid('c');
+        let uid = point.get("uid").and_then(|u| u.as_str()).filter(|u| !u.is_empty()).map(String::from).unwrap_or(generated);
+        point.insert(String::from("uid"), json!(uid));
+        point.insert(String::from("provenance"), json!("api"));
+        point.entry("settings").or_insert(json!({}));
+        point.entry("disableResolveMessage").or_insert(json!(false));
+        let point = Value::Object(point);
+        self.contact_points.push(point.clone());
+        Response::new(202, point)
+    }
+
+    fn update_contact_point(&mut self, uid : &str, body : Value) -> Response {
+        let Some(i) = self.contact_points.iter().position(|c| c["uid"] == uid) else {
+            return Response::error(404, "contact point not found", None);
+        };
+        let Value::Object(mut point) = body else {
+            return Response::error(400, "bad request data", None);
+        };
+        point.insert(String::from("uid"), json!(uid));
+        point.insert(String::from("provenance"), json!("api"));
+        self.contact_points[i] = Value::Object(point);
+        Response::new(202, json!({ "message" : "contactpoint updated" }))
+    }
+
+    fn delete_contact_point(&mut self, uid : &str) -> Response {
+        self.contact_points.retain(|c| c["uid"] != uid);
+        Response::new(202, json!({ "message" : "contactpoint deleted" }))
+    }
+}
+
+/// Move secure settings out of a data source, leaving only flags that they are set
+fn redact_secure(mut ds : Map<String,Value>) -> Value {
+    let fields : Map<String,Value> = match ds.remove("secureJsonData") {
+        Some(Value::Object(secure)) => secure.keys().map(|k| (k.clone(), Value::Bool(true))).collect(),
+        _ => Map::new(),
+    };
+    ds.insert(String::from("secureJsonFields"), Value::Object(fields));
+    Value::Object(ds)
+}
+
+/// URL friendly version of a title
+fn slug(title : &str) -> String {
+    title.to_lowercase()
+        .split(|c : char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn dashboard_url(d : &StoredDashboard) -> String {
+    format!("/d/{}/{}",d.uid(),slug(d.title()))
+}
+
+fn folder_url(f : &Folder) -> String {
+    format!("/dashboards/f/{}/{}",f.uid,slug(&f.title))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn post(state : &mut State, path : &str, body : Value) -> Response {
+        state.handle(&Request { method : String::from("POST"), path : path.to_string(), body : body.to_string(), ..Default::default() })
+    }
+
+    #[test]
+    fn test_dashboard_versions() {
+        let mut state = State::default();
+        let created = post(&mut state, "/api/dashboards/db", json!({ "dashboard" : { "title" : "Ops" } }));
+        assert_eq!(created.body["version"], 1);
+        let uid = created.body["uid"].as_str().unwrap().to_string();
+
+        let stale = post(&mut state, "/api/dashboards/db", json!({ "dashboard" : { "uid" : uid, "title" : "Ops", "version" : 0 } }));
+        assert_eq!(stale.status, 412);
+        assert_eq!(stale.body["status"], "version-mismatch");
+
+        let updated = post(&mut state, "/api/dashboards/db", json!({ "dashboard" : { "uid" : uid, "title" : "Ops", "version" : 1 } }));
+        assert_eq!(updated.body["version"], 2);
+        let forced = post(&mut state, "/api/dashboards/db", json!({ "dashboard" : { "uid" : uid, "title" : "Ops" }, "overwrite" : true }));
+        assert_eq!(forced.body["version"], 3);
+    }
+
+    #[test]
+    fn test_dashboard_name_exists() {
+        let mut state = State::default();
+        post(&mut state, "/api/dashboards/db", json!({ "dashboard" : { "title" : "Ops" } }));
+        let clash = post(&mut state, "/api/dashboards/db", json!({ "dashboard" : { "title" : "ops" } }));
+        assert_eq!((clash.status, clash.body["status"].as_str()), (412, Some("name-exists")));
+        let replaced = post(&mut state, "/api/dashboards/db", json!({ "dashboard" : { "title" : "ops" }, "overwrite" : true }));
+        assert_eq!(replaced.status, 200);
+        assert_eq!(state.dashboards.len(), 1);
+    }
+
+    #[test]
+    fn test_data_source_secrets() {
+        let mut state = State::default();
+        let created = post(&mut state, "/api/datasources", json!({ "name" : "prom", "type" : "prometheus", "secureJsonData" : { "password" : "x" } }));
+        assert_eq!(created.body["datasource"]["secureJsonFields"], json!({ "password" : true }));
+        assert!(!created.body.to_string().contains("\"x\""));
+        let duplicate = post(&mut state, "/api/datasources", json!({ "name" : "prom" }));
+        assert_eq!(duplicate.status, 409);
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("My  Dashboard (prod)"), "my-dashboard-prod");
+    }
+}